@@ -0,0 +1,83 @@
+use crate::chess::{Board, Side};
+use crate::engine::{Limits, Score, SearchInfo, Searcher};
+use crate::message::Message;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+/// The built-in engine's analysis of a position. It searches on a blocking thread until it's
+/// dropped, sending the lines of every depth it completes as a message.
+pub struct Analysis {
+    board: Board,
+    lines: Vec<SearchInfo>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Analysis {
+    /// Starts analysing `board`, looking for its best `count` moves.
+    pub fn start(board: &Board, count: usize, tx: Sender<Message>) -> Self {
+        let mut searcher = Searcher::new(32);
+        let stop = searcher.stop_handle();
+
+        let search_board = board.clone();
+        let hash = *board.hash();
+
+        tokio::task::spawn_blocking(move || {
+            searcher.search_lines(&search_board, &Limits::new(), count, |lines| {
+                let _ = tx.send(Message::AnalysisUpdate(hash, lines.to_vec()));
+            });
+        });
+
+        Self {
+            board: board.clone(),
+            lines: vec![],
+            stop,
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The lines of the deepest search so far, best first.
+    pub fn lines(&self) -> &Vec<SearchInfo> {
+        &self.lines
+    }
+
+    /// Takes the lines of a newly completed depth, unless they're for an earlier position.
+    pub fn update(&mut self, hash: u64, lines: Vec<SearchInfo>) {
+        if *self.board.hash() == hash {
+            self.lines = lines;
+        }
+    }
+
+    /// The score of a line from white's point of view.
+    pub fn white_score(&self, line: &SearchInfo) -> Score {
+        match self.board.turn() {
+            Side::White => *line.score(),
+            Side::Black => -*line.score(),
+        }
+    }
+
+    /// How likely white is to win by the best line, from 0 to 1, for the evaluation bar.
+    pub fn white_share(&self) -> f32 {
+        let line = match self.lines.first() {
+            Some(line) => line,
+            None => return 0.5,
+        };
+
+        match self.white_score(line) {
+            // the winning chances Lichess shows for a centipawn score
+            Score::Cp(cp) => 1.0 / (1.0 + (-0.003_682_08 * cp as f32).exp()),
+            Score::Mate(moves) if moves > 0 => 1.0,
+            Score::Mate(_) => 0.0,
+        }
+    }
+}
+
+impl Drop for Analysis {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
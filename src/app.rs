@@ -1,43 +1,120 @@
 use crate::{
-    chess::{Board, Side},
-    config::Config,
-    game::{ChatMessage, Game, GameData, GameState},
+    analysis::Analysis,
+    challenge::{Challenge, ChallengeForm, DeclineReason, Outgoing},
+    chat::Chat,
+    chess::{Board, ChessMove, Outcome, Side},
+    config::{Config, EngineConfig},
+    engine::{
+        uci::{EngineError, GoParams, UciEngine},
+        Opponent, SearchInfo, Searcher,
+    },
+    game::{Game, GameKind, GameState, Offer},
+    lichess::{Lichess, LichessError},
     message::Message,
+    pgn::{PgnError, PgnGame},
+    replay::Replay,
+    seek::SeekForm,
+    stream::GameEvent,
     ui::UIState,
     user::User,
     utils::debug,
 };
 
-use futures::stream::StreamExt;
-use serde_json::Value;
 use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+// how long an external engine may take past its remaining time before it counts as hung
+const EXTERNAL_ENGINE_GRACE: Duration = Duration::from_secs(5);
+
+// how long to wait for the game of a seek whose request has ended, as Lichess ends it once the seek
+// is accepted and the game starts on the event stream
+const SEEK_END_GRACE: Duration = Duration::from_secs(5);
+
+// the transposition table size of the built-in engine, in megabytes
+const ENGINE_HASH_MB: usize = 16;
 
 pub struct App {
     game: Option<Game>,
     own_info: Option<User>,
     config: Config,
+    lichess: Lichess,
     main_tx: Sender<Message>,
     ui_state: UIState,
     pub state_changed: bool,
     board_display_side: Side,
     small_board: bool,
+    status: Option<String>,
+    pgn_games: Vec<PgnGame>,
+    picker_idx: usize,
+    replay: Option<Replay>,
+    // stops the engine while it's thinking about a move
+    engine_stop: Option<Arc<AtomicBool>>,
+    // the built-in engine, kept across the moves of a game
+    searcher: Arc<std::sync::Mutex<Searcher>>,
+    // the external engine of the current game, started when it first has to move
+    uci_engine: Arc<Mutex<Option<UciEngine>>>,
+    analysis_enabled: bool,
+    // the analysis of the position on screen, while it's enabled and allowed
+    analysis: Option<Analysis>,
+    seek_form: SeekForm,
+    // the request of the real-time seek waiting for an opponent
+    seek: Option<JoinHandle<()>>,
+    // the stream of the current online game
+    game_stream: Option<JoinHandle<()>>,
+    // the challenges other players sent, oldest first
+    challenges: Vec<Challenge>,
+    challenge_idx: usize,
+    challenge_form: ChallengeForm,
+    outgoing: Option<Outgoing>,
+    // the chat panel of the current online game
+    chat: Chat,
 }
 
 impl App {
     pub async fn new(main_tx: Sender<Message>) -> Result<Self, Box<dyn Error>> {
         let config = Config::new().unwrap();
+        Ok(Self::with_config(main_tx, config))
+    }
+
+    /// An app using `config` instead of the one in `~/.config/zchess.toml`.
+    pub fn with_config(main_tx: Sender<Message>, config: Config) -> Self {
+        let lichess = Lichess::new(&config);
 
-        Ok(Self {
+        Self {
             game: None,
             main_tx,
             config,
+            lichess,
             state_changed: true,
             own_info: None,
             ui_state: UIState::Menu,
             board_display_side: Side::White,
             small_board: false,
-        })
+            status: None,
+            pgn_games: vec![],
+            picker_idx: 0,
+            replay: None,
+            engine_stop: None,
+            searcher: Arc::new(std::sync::Mutex::new(Searcher::new(ENGINE_HASH_MB))),
+            uci_engine: Default::default(),
+            analysis_enabled: false,
+            analysis: None,
+            seek_form: SeekForm::default(),
+            seek: None,
+            game_stream: None,
+            challenges: vec![],
+            challenge_idx: 0,
+            challenge_form: ChallengeForm::default(),
+            outgoing: None,
+            chat: Chat::default(),
+        }
     }
 
     pub fn toggle_small_board(&mut self) {
@@ -48,184 +125,918 @@ impl App {
         &self.small_board
     }
 
-    pub async fn get_own_info(&self) -> Result<User, Box<dyn Error>> {
-        let client = reqwest::Client::new();
-
-        let token = format!("Bearer {}", self.config.token());
-
-        let res = client
-            .get("https://lichess.org/api/account")
-            .header("Authorization", token)
-            .send()
-            .await?
-            .text()
-            .await?
-            .to_string();
+    pub async fn get_own_info(&self) -> Result<User, LichessError> {
+        let info = self.lichess.account().await?;
 
         if *self.config.debug() {
-            debug(&format!("own_info: {}", res));
+            debug(&format!("own_info: {:?}", info));
         }
 
-        Ok(serde_json::from_str(&res)?)
+        Ok(info)
     }
 
     pub async fn abort_game(&mut self) {
-        let token = format!("Bearer {}", self.config.token());
-        let client = reqwest::Client::new();
+        let id = match self.online_game_id() {
+            Some(id) => id,
+            // local games and games against the engine just end
+            None => return self.end_game(),
+        };
+
+        match self.lichess.abort(&id).await {
+            Ok(()) => self.end_game(),
+            Err(e) => self.set_status(format!("failed to abort the game: {}", e)),
+        }
+    }
 
-        let id = self.game().as_ref().unwrap().id();
+    pub async fn resign_game(&mut self) {
+        let id = match self.online_game_id() {
+            Some(id) => id,
+            // local games and games against the engine just end
+            None => return self.end_game(),
+        };
 
-        client
-            .post(&format!("https://lichess.org/api/board/game/{}/resign", id))
-            .header("Authorization", token)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .send()
-            .await
-            .unwrap();
+        match self.lichess.resign(&id).await {
+            Ok(()) => self.end_game(),
+            Err(e) => self.set_status(format!("failed to resign the game: {}", e)),
+        }
+    }
 
-        self.end_game();
+    /// Opens the seek dialog, with the settings of the last seek.
+    pub fn open_seek(&mut self) {
+        self.ui_state = UIState::Seek;
     }
 
-    pub async fn resign_game(&mut self) {
-        let token = format!("Bearer {}", self.config.token());
-        let client = reqwest::Client::new();
+    /// Posts a seek with the settings from the dialog. Real-time seeks last as long as their
+    /// request stays open, so they're canceled by dropping it, while correspondence seeks are
+    /// created right away.
+    pub fn seek_for_game(&mut self) {
+        let lichess = self.lichess.clone();
+        let params = self.seek_form.params(self.own_info.as_ref());
+        let correspondence = self.seek_form.is_correspondence();
+        let tx = self.main_tx.clone();
+
+        self.stop_seek();
+        self.ui_state = UIState::Seeking;
+
+        self.seek = Some(tokio::spawn(async move {
+            let mut stream = match lichess.seek(&params).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    let _ = tx.send(Message::SeekFailed(e.to_string()));
+                    return;
+                }
+            };
+
+            if correspondence {
+                let _ = tx.send(Message::SeekPosted);
+                return;
+            }
 
-        let id = self.game().as_ref().unwrap().id();
+            // Lichess sends empty lines to keep the seek open until someone accepts it
+            loop {
+                match stream.next().await {
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        let _ = tx.send(Message::SeekFailed(e.to_string()));
+                        return;
+                    }
+                    None => break,
+                }
+            }
 
-        client
-            .post(&format!("https://lichess.org/api/board/game/{}/abort", id))
-            .header("Authorization", token)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .send()
-            .await
-            .unwrap();
-        self.end_game();
+            // the seek is stopped once its game starts
+            tokio::time::sleep(SEEK_END_GRACE).await;
+            let _ = tx.send(Message::SeekFailed("the seek ended without a game".into()));
+        }));
     }
 
-    pub async fn seek_for_game(&mut self) {
-        let token = format!("Bearer {}", self.config.token());
+    // drops the request of a running seek, which cancels it on Lichess
+    fn stop_seek(&mut self) {
+        if let Some(seek) = self.seek.take() {
+            seek.abort();
+        }
+    }
 
+    /// Cancels the running seek and goes back to the seek dialog.
+    pub fn cancel_seek(&mut self) {
+        self.stop_seek();
         self.ui_state = UIState::Seek;
+    }
 
-        tokio::spawn(async move {
-            let client = reqwest::Client::new();
-
-            let params = [("rated", "false"), ("time", "10"), ("increment", "0")];
-
-            let mut stream = client
-                .post("https://lichess.org/api/board/seek")
-                .form(&params)
-                .header("Authorization", token)
-                .header("Content-Type", "application/x-www-form-urlencoded")
-                .send()
-                .await
-                .unwrap()
-                .bytes_stream();
-
-            while let Some(ev) = stream.next().await {
-                let e = ev.unwrap();
-                if e.len() > 1 {
-                    panic!("{:#?}", e);
+    pub fn seek_failed(&mut self, error: String) {
+        self.seek = None;
+
+        if self.ui_state == UIState::Seeking {
+            self.ui_state = UIState::Seek;
+        }
+
+        self.set_status(format!("seek failed: {}", error));
+    }
+
+    pub fn seek_posted(&mut self) {
+        self.seek = None;
+
+        if self.ui_state == UIState::Seeking {
+            self.ui_state = UIState::Menu;
+        }
+
+        self.set_status("posted the seek, the game starts once someone accepts it");
+    }
+
+    pub fn seek_form(&self) -> &SeekForm {
+        &self.seek_form
+    }
+
+    pub fn seek_form_mut(&mut self) -> &mut SeekForm {
+        &mut self.seek_form
+    }
+
+    /// Adds a challenge from another player to the inbox. The player's own challenges show up on
+    /// the event stream too, and are only used to follow the one they sent.
+    pub fn challenge_received(&mut self, challenge: Challenge) {
+        let own = match (challenge.challenger(), self.own_info.as_ref()) {
+            (Some(challenger), Some(info)) => challenger.id() == info.id(),
+            _ => false,
+        };
+
+        if own {
+            if let Some(Outgoing::Pending(pending)) = self.outgoing.as_ref() {
+                if pending.id() == challenge.id() {
+                    self.outgoing = Some(Outgoing::Pending(challenge));
                 }
             }
+        } else if self.challenges.iter().all(|c| c.id() != challenge.id()) {
+            self.challenges.push(challenge);
+        }
+    }
+
+    pub fn challenge_canceled(&mut self, id: &str) {
+        self.remove_challenge(id);
+
+        if let Some(Outgoing::Pending(pending)) = self.outgoing.as_ref() {
+            if pending.id() == id {
+                self.outgoing = None;
+            }
+        }
+    }
+
+    pub fn challenge_declined(&mut self, challenge: Challenge) {
+        if let Some(Outgoing::Pending(pending)) = self.outgoing.as_ref() {
+            if pending.id() == challenge.id() {
+                self.outgoing = Some(Outgoing::Declined(challenge));
+            }
+        }
+    }
+
+    fn remove_challenge(&mut self, id: &str) {
+        self.challenges.retain(|challenge| challenge.id() != id);
+        self.challenge_idx = self
+            .challenge_idx
+            .min(self.challenges.len().saturating_sub(1));
+    }
+
+    /// Accepts the selected challenge. Its game starts once Lichess sends `gameStart`.
+    pub async fn accept_challenge(&mut self) {
+        let id = match self.challenges.get(self.challenge_idx) {
+            Some(challenge) => challenge.id().clone(),
+            None => return,
+        };
+
+        let res = self.lichess.accept_challenge(&id).await;
+        self.remove_challenge(&id);
+
+        if let Err(e) = res {
+            self.set_status(format!("failed to accept the challenge: {}", e));
+        }
+    }
+
+    pub async fn decline_challenge(&mut self) {
+        let id = match self.challenges.get(self.challenge_idx) {
+            Some(challenge) => challenge.id().clone(),
+            None => return,
+        };
+
+        let res = self
+            .lichess
+            .decline_challenge(&id, DeclineReason::Generic)
+            .await;
+
+        self.remove_challenge(&id);
+
+        if let Err(e) = res {
+            self.set_status(format!("failed to decline the challenge: {}", e));
+        }
+    }
+
+    /// Challenges the player from the form and opens the inbox, where the challenge is shown until
+    /// it's accepted or declined.
+    pub async fn send_challenge(&mut self) {
+        let username = self.challenge_form.username().trim().to_string();
+
+        if username.is_empty() {
+            self.set_status("enter the name of the player to challenge");
+            return;
+        }
+
+        let params = self.challenge_form.params();
+
+        self.outgoing = Some(match self.lichess.challenge(&username, &params).await {
+            Ok(challenge) => Outgoing::Pending(challenge),
+            Err(e) => Outgoing::Failed(e.to_string()),
         });
+
+        self.ui_state = UIState::Challenges;
     }
 
-    pub fn local_game(&mut self) {
+    /// Cancels the challenge the player sent, if it's still waiting for an answer.
+    pub async fn cancel_challenge(&mut self) {
+        let id = match self.outgoing.as_ref() {
+            Some(Outgoing::Pending(challenge)) => challenge.id().clone(),
+            _ => return,
+        };
+
+        match self.lichess.cancel_challenge(&id).await {
+            Ok(_) => self.outgoing = None,
+            Err(e) => self.set_status(format!("failed to cancel the challenge: {}", e)),
+        }
+    }
+
+    pub fn challenges(&self) -> &Vec<Challenge> {
+        &self.challenges
+    }
+
+    pub fn challenge_idx(&self) -> &usize {
+        &self.challenge_idx
+    }
+
+    pub fn move_challenge_cursor(&mut self, up: bool) {
+        self.challenge_idx = match up {
+            true => self.challenge_idx.saturating_sub(1),
+            false => (self.challenge_idx + 1).min(self.challenges.len().saturating_sub(1)),
+        };
+    }
+
+    pub fn challenge_form(&self) -> &ChallengeForm {
+        &self.challenge_form
+    }
+
+    pub fn challenge_form_mut(&mut self) -> &mut ChallengeForm {
+        &mut self.challenge_form
+    }
+
+    pub fn outgoing(&self) -> &Option<Outgoing> {
+        &self.outgoing
+    }
+
+    /// Starts a local game from `board`, e.g. the standard position or a Chess960 one.
+    pub fn local_game(&mut self, board: Board) {
         self.ui_state = UIState::Game;
-        self.game = Some(Game::local(600000, 5000));
+        self.game = Some(Game::local(board, 600000, 5000));
     }
 
-    pub fn end_game(&mut self) {
-        self.game = None;
-        self.ui_state = UIState::Menu;
+    /// Starts a game against an engine, with the player as white.
+    pub fn computer_game(&mut self, opponent: Opponent) {
+        // a new searcher, as a search of the last game may still be winding down on the old one
+        self.searcher = Arc::new(std::sync::Mutex::new(Searcher::new(ENGINE_HASH_MB)));
+        self.ui_state = UIState::Game;
+        self.board_display_side = Side::White;
+        self.game = Some(Game::computer(
+            Board::default(),
+            Side::Black,
+            opponent,
+            600000,
+            5000,
+        ));
+    }
+
+    /// Starts a game against the UCI engine from the configuration.
+    pub fn external_engine_game(&mut self) {
+        match self.config.engine() {
+            Some(engine) => {
+                let name = engine.name();
+                self.computer_game(Opponent::External(name));
+            }
+            None => self.set_status("no engine configured"),
+        }
+    }
+
+    /// Whether the player may move now: always in local games, and only on their own turn
+    /// otherwise.
+    pub fn can_move(&self) -> bool {
+        let game = match self.game.as_ref() {
+            Some(game) => game,
+            None => return false,
+        };
+
+        let turn = game.board().turn();
+
+        match game.engine_side() {
+            Some(side) => side != turn,
+            None if game.is_online() => self.check_own_side() == *turn,
+            None => true,
+        }
+    }
+
+    // lets the engine think about its move if it's its turn, on a blocking thread for the built-in
+    // one, sending the move back as a message
+    fn start_engine(&mut self) {
+        let game = match self.game.as_ref() {
+            Some(game) if !game.is_over() => game,
+            _ => return,
+        };
+
+        let opponent = match game.kind() {
+            GameKind::Computer {
+                engine_side,
+                opponent,
+            } if engine_side == game.board().turn() => opponent.clone(),
+            _ => return,
+        };
+
+        let (wtime, btime) = (*game.state().wtime(), *game.state().btime());
+        let increment = game.data().increment();
+
+        let remaining = match game.board().turn() {
+            Side::White => wtime,
+            Side::Black => btime,
+        };
+
+        let board = game.board().clone();
+        let tx = self.main_tx.clone();
+
+        self.stop_engine();
+
+        let level = match opponent {
+            Opponent::BuiltIn(level) => level,
+            Opponent::External(_) => {
+                let params = GoParams::clock(wtime, btime, increment, increment);
+                let timeout = Duration::from_millis(remaining) + EXTERNAL_ENGINE_GRACE;
+                return self.start_external_engine(board, params, timeout);
+            }
+        };
+
+        let limits = level.limits(
+            Duration::from_millis(remaining),
+            Duration::from_millis(increment),
+        );
+
+        let stop = Arc::new(AtomicBool::new(false));
+        self.engine_stop = Some(stop.clone());
+
+        let searcher = self.searcher.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut searcher = searcher.lock().unwrap_or_else(|e| e.into_inner());
+            searcher.set_stop_handle(stop);
+
+            let info = searcher.search(&board, &limits, |_| {});
+
+            if let Some(mv) = info.best_move() {
+                let _ = tx.send(Message::EngineMove(mv, *board.hash()));
+            }
+        });
+    }
+
+    // asks the configured UCI engine for a move, starting it first if it isn't running yet, and
+    // reports its failures back as a message
+    fn start_external_engine(&mut self, board: Board, params: GoParams, timeout: Duration) {
+        let config = match self.config.engine() {
+            Some(config) => config.clone(),
+            None => return,
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        self.engine_stop = Some(stop.clone());
+
+        let slot = self.uci_engine.clone();
+        let tx = self.main_tx.clone();
+
+        tokio::spawn(async move {
+            let mut engine = slot.lock().await;
+
+            match external_move(&mut engine, &config, &board, &params, &stop, timeout).await {
+                Ok(Some(mv)) => {
+                    let _ = tx.send(Message::EngineMove(mv, *board.hash()));
+                }
+                Ok(None) => (),
+                Err(e) => {
+                    // started again for the next move
+                    *engine = None;
+                    let _ = tx.send(Message::EngineError(e.to_string()));
+                }
+            }
+        });
+    }
+
+    fn stop_engine(&mut self) {
+        if let Some(stop) = self.engine_stop.take() {
+            stop.store(true, Ordering::Relaxed);
+        }
     }
 
-    pub fn update_game_state(&mut self, state: GameState) {
-        if state.status() == "aborted" {
+    /// Plays the move the engine found, unless the position changed since it started thinking.
+    pub async fn play_engine_move(&mut self, mv: ChessMove, hash: u64) {
+        let is_current = match self.game.as_ref() {
+            Some(game) => *game.board().hash() == hash && !self.can_move(),
+            None => false,
+        };
+
+        if is_current {
+            self.engine_stop = None;
+            self.play_move(mv).await;
+        }
+    }
+
+    /// Plays a move in the current game, submitting it to Lichess for online games,
+    /// and updates the clocks.
+    pub async fn play_move(&mut self, mv: ChessMove) {
+        let game = self.game.as_mut().unwrap();
+
+        let id = game.id().to_string();
+        let is_online = game.is_online();
+
+        let board = game.board_mut();
+
+        let piece_side = board.turn().clone();
+        let turn_time_taken = board.turn_time_taken().elapsed().as_millis();
+
+        let res = board.make_move(mv);
+        board.set_generated_moves(vec![]);
+
+        if let Err(e) = res {
+            self.set_status(format!("failed to play {}: {}", mv, e));
             return;
         }
 
-        let moves: Vec<&str> = state.moves().split(" ").collect();
+        if is_online {
+            // the played move knows which piece a pawn actually promoted to
+            let uci = match board.played_moves().last() {
+                Some(played) => played.uci(),
+                None => mv.to_string(),
+            };
 
-        let game = self.game_mut().as_mut().unwrap();
+            if let Err(e) = self.lichess.make_move(&id, &uci).await {
+                // Lichess didn't get the move, so the player has to play it again
+                board.unmake_move();
+                self.set_status(format!("failed to play {}: {}", mv, e));
+                return;
+            }
+        }
+
+        let game = self.game.as_mut().unwrap();
+
+        game.incr_move_count();
+
+        let mut new_state = game.state().clone();
+
+        let wtime = *game.state().wtime();
+        let btime = *game.state().btime();
 
-        let mut board = Board::default();
+        // correspondence games count down each move from the days per turn on Lichess' side
+        if *game.move_count() >= 3 && game.data().clock().is_some() {
+            let (wtime, btime) = match piece_side {
+                Side::White => (
+                    wtime.saturating_sub(turn_time_taken as u64) + game.data().increment(),
+                    btime,
+                ),
+                Side::Black => (
+                    wtime,
+                    btime.saturating_sub(turn_time_taken as u64) + game.data().increment(),
+                ),
+            };
 
-        for mv in moves {
-            board.make_move_str(mv);
+            new_state.set_btime(btime);
+            new_state.set_wtime(wtime);
+
+            game.set_state(new_state);
         }
 
-        std::mem::swap(game.board_mut(), &mut board);
+        if !is_online {
+            game.board_mut().reset_turn_timer();
+        }
 
-        game.set_state(state);
-        game.board_mut().reset_turn_timer();
+        self.update_outcome();
+        self.start_engine();
     }
 
-    pub fn init_new_game<T: ToString>(&mut self, id: T) {
-        let id = id.to_string();
-        let tx = self.main_tx.clone();
+    /// Takes back the last move of a local game. Against the engine, the moves are taken back up to
+    /// the player's last one.
+    pub fn takeback(&mut self) {
+        self.stop_engine();
+
+        if let Some(game) = self.game.as_mut() {
+            if game.is_online() || game.board().played_moves().is_empty() {
+                return;
+            }
+
+            loop {
+                let board = game.board_mut();
+
+                board.unmake_move();
+                board.set_generated_moves(vec![]);
+                board.reset_turn_timer();
+
+                game.decr_move_count();
+                game.set_outcome(None);
+
+                let engine_to_move = game.engine_side() == Some(game.board().turn());
+
+                if !engine_to_move || game.board().played_moves().is_empty() {
+                    break;
+                }
+            }
+        }
+
+        self.start_engine();
+    }
+
+    /// The offer of the opponent in an online game that the player hasn't answered yet, a draw
+    /// offer before a takeback.
+    pub fn pending_offer(&self) -> Option<Offer> {
+        let game = self.game.as_ref()?;
+
+        if !game.is_online() || game.is_over() || self.own_info.is_none() {
+            return None;
+        }
+
+        let opponent = self.check_own_side().opposite();
+
+        if game.state().draw_offered(&opponent) {
+            Some(Offer::Draw)
+        } else if game.state().takeback_proposed(&opponent) {
+            Some(Offer::Takeback)
+        } else {
+            None
+        }
+    }
+
+    /// The prompt for the opponent's offer, or what the player's own offer is waiting for.
+    pub fn offer_prompt(&self) -> Option<String> {
+        let game = self.game.as_ref()?;
+
+        if !game.is_online() || game.is_over() || self.own_info.is_none() {
+            return None;
+        }
+
+        let side = self.check_own_side();
+
+        let opponent = match side {
+            Side::White => game.data().black().name(),
+            Side::Black => game.data().white().name(),
+        };
+
+        let prompt = match self.pending_offer() {
+            Some(Offer::Draw) => format!("{} offers a draw | y to accept | n to decline", opponent),
+            Some(Offer::Takeback) => format!(
+                "{} proposes a takeback | y to accept | n to decline",
+                opponent
+            ),
+            None if game.state().draw_offered(&side) => {
+                format!("draw offered, waiting for {}", opponent)
+            }
+            None if game.state().takeback_proposed(&side) => {
+                format!("takeback proposed, waiting for {}", opponent)
+            }
+            None => return None,
+        };
+
+        Some(prompt)
+    }
+
+    /// Offers a draw or accepts the opponent's offer with `yes`, and declines it otherwise.
+    pub async fn answer_draw(&mut self, yes: bool) {
+        let id = match self.online_game_id() {
+            Some(id) => id,
+            None => return,
+        };
+
+        if let Err(e) = self.lichess.draw(&id, yes).await {
+            self.set_status(format!("failed to answer the draw offer: {}", e));
+        }
+    }
 
-        let token = format!("Bearer {}", self.config.token());
+    /// Proposes a takeback or accepts the opponent's proposal with `yes`, and declines it
+    /// otherwise. Lichess sends the position after the takeback as a new game state.
+    pub async fn answer_takeback(&mut self, yes: bool) {
+        let id = match self.online_game_id() {
+            Some(id) => id,
+            None => return,
+        };
 
+        if let Err(e) = self.lichess.takeback(&id, yes).await {
+            self.set_status(format!("failed to answer the takeback: {}", e));
+        }
+    }
+
+    fn online_game_id(&self) -> Option<String> {
+        match self.game.as_ref() {
+            Some(game) if game.is_online() => Some(game.id().clone()),
+            _ => None,
+        }
+    }
+
+    pub fn chat(&self) -> &Chat {
+        &self.chat
+    }
+
+    pub fn chat_mut(&mut self) -> &mut Chat {
+        &mut self.chat
+    }
+
+    pub fn clamp_chat_scroll(&mut self, width: usize, height: usize) {
+        if let Some(game) = self.game.as_ref() {
+            self.chat.clamp_scroll(game.messages(), width, height);
+        }
+    }
+
+    /// Sends the message typed in the chat input to the room shown. It appears in the panel once
+    /// Lichess sends it back on the game stream.
+    pub async fn send_chat(&mut self) {
+        let text = match self.chat.take_input() {
+            Some(text) if !text.trim().is_empty() => text,
+            _ => return,
+        };
+
+        let id = match self.game.as_ref() {
+            Some(game) if game.is_online() => game.id().clone(),
+            _ => return,
+        };
+
+        let res = self.lichess.chat(&id, self.chat.room(), text.trim()).await;
+
+        if let Err(e) = res {
+            self.set_status(format!("failed to send the message: {}", e));
+        }
+    }
+
+    /// Shows why the engine couldn't move. The game goes on, and the engine is asked again after a
+    /// takeback.
+    pub fn engine_failed(&mut self, error: String) {
+        self.engine_stop = None;
+        self.set_status(format!("engine error: {}", error));
+    }
+
+    /// Turns analysis of the position on screen on or off. It can't be turned on during online games.
+    pub fn toggle_analysis(&mut self) {
+        if !self.analysis_enabled && !self.analysis_allowed() {
+            self.set_status("analysis isn't available during online games");
+            return;
+        }
+
+        self.analysis_enabled = !self.analysis_enabled;
+        self.update_analysis();
+    }
+
+    fn analysis_allowed(&self) -> bool {
+        match self.ui_state {
+            UIState::Game => match self.game.as_ref() {
+                Some(game) => game.analysis_allowed(),
+                None => false,
+            },
+            UIState::Replay => true,
+            _ => false,
+        }
+    }
+
+    // the position shown on the board, in a game or a replay
+    fn shown_board(&self) -> Option<&Board> {
+        match self.ui_state {
+            UIState::Game => self.game.as_ref().map(|game| game.board()),
+            UIState::Replay => self.replay.as_ref().map(|replay| replay.board()),
+            _ => None,
+        }
+    }
+
+    /// Starts analysing the position on screen if it changed, and stops when analysis isn't
+    /// enabled or allowed anymore.
+    pub fn update_analysis(&mut self) {
+        let board = match self.shown_board() {
+            Some(board) if self.analysis_enabled && self.analysis_allowed() => board,
+            _ => {
+                self.analysis = None;
+                return;
+            }
+        };
+
+        let is_current = match self.analysis.as_ref() {
+            Some(analysis) => analysis.board().hash() == board.hash(),
+            None => false,
+        };
+
+        if !is_current {
+            let lines = self.config.analysis_lines();
+            self.analysis = Some(Analysis::start(board, lines, self.main_tx.clone()));
+        }
+    }
+
+    pub fn update_analysis_lines(&mut self, hash: u64, lines: Vec<SearchInfo>) {
+        if let Some(analysis) = self.analysis.as_mut() {
+            analysis.update(hash, lines);
+        }
+    }
+
+    pub fn analysis(&self) -> &Option<Analysis> {
+        &self.analysis
+    }
+
+    /// Checks whether the last move ended a local game. Online games are ended by Lichess instead.
+    pub fn update_outcome(&mut self) {
+        if let Some(game) = self.game.as_mut() {
+            if !game.is_online() {
+                let outcome = game.board().outcome();
+                game.set_outcome(outcome);
+            }
+        }
+    }
+
+    /// Writes the current game to the configured PGN directory, returning the path of the file.
+    pub fn save_pgn(&self) -> Result<PathBuf, Box<dyn Error>> {
+        let game = self.game.as_ref().ok_or("no game to save")?;
+
+        let name = match game.is_online() {
+            true => format!("{}.pgn", game.id()),
+            false => {
+                let secs = game.started().duration_since(UNIX_EPOCH)?.as_secs();
+                format!("local-{}.pgn", secs)
+            }
+        };
+
+        let dir = self.config.pgn_dir();
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join(name);
+        fs::write(&path, game.to_pgn(self.lichess.base_url()))?;
+
+        Ok(path)
+    }
+
+    /// Opens the games of an imported PGN file, straight in the viewer if there's only one.
+    pub fn open_pgn(&mut self, games: Vec<PgnGame>) -> Result<(), PgnError> {
+        self.pgn_games = games;
+        self.picker_idx = 0;
+
+        match self.pgn_games.len() {
+            1 => self.open_replay(0),
+            _ => {
+                self.ui_state = UIState::GamePicker;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn open_replay(&mut self, idx: usize) -> Result<(), PgnError> {
+        let game = self.pgn_games.get(idx).cloned().ok_or(PgnError::NoGames)?;
+
+        self.replay = Some(Replay::new(game)?);
+        self.ui_state = UIState::Replay;
+        self.board_display_side = Side::White;
+
+        Ok(())
+    }
+
+    /// Leaves the viewer, going back to the game picker for files with several games.
+    pub fn close_replay(&mut self) {
+        self.replay = None;
+
+        self.ui_state = match self.pgn_games.len() {
+            0 | 1 => UIState::Menu,
+            _ => UIState::GamePicker,
+        };
+    }
+
+    pub fn pgn_games(&self) -> &Vec<PgnGame> {
+        &self.pgn_games
+    }
+
+    pub fn picker_idx(&self) -> &usize {
+        &self.picker_idx
+    }
+
+    pub fn move_picker(&mut self, up: bool) {
+        self.picker_idx = match up {
+            true => self.picker_idx.saturating_sub(1),
+            false => (self.picker_idx + 1).min(self.pgn_games.len().saturating_sub(1)),
+        };
+    }
+
+    pub fn replay(&self) -> &Option<Replay> {
+        &self.replay
+    }
+
+    pub fn replay_mut(&mut self) -> &mut Option<Replay> {
+        &mut self.replay
+    }
+
+    pub fn status(&self) -> &Option<String> {
+        &self.status
+    }
+
+    pub fn set_status<T: ToString>(&mut self, status: T) {
+        self.status = Some(status.to_string());
+    }
+
+    pub fn clear_status(&mut self) {
+        self.status = None;
+    }
+
+    pub fn end_game(&mut self) {
+        self.stop_engine();
+        // the engine process is killed once a search still running on it is done
+        self.uci_engine = Default::default();
+
+        if let Some(stream) = self.game_stream.take() {
+            stream.abort();
+        }
+
+        self.game = None;
+        self.ui_state = UIState::Menu;
+    }
+
+    /// Marks the open online game `id` as over. It stays on the board, so it can be saved or
+    /// analysed, until the player leaves it with enter.
+    pub fn game_finished(&mut self, id: &str) {
+        if let Some(game) = self.game.as_mut() {
+            // the final state on the game stream, which usually comes first, has the outcome
+            if game.is_online() && game.id() == id && !game.is_over() {
+                game.set_outcome(Some(Outcome::Ended(None)));
+            }
+        }
+    }
+
+    /// Takes over a state sent on the stream of the online game `id`, if it's still open.
+    pub fn update_game_state(&mut self, id: &str, state: GameState) {
         let debug_enabled = *self.config.debug();
 
-        tokio::spawn(async move {
-            let path = format!(
-                "https://lichess.org/api/board/game/stream/{}",
-                id.to_string()
-            );
-
-            let client = reqwest::Client::new();
-
-            let mut stream = client
-                .get(path)
-                .header("Authorization", token)
-                .send()
-                .await
-                .unwrap()
-                .bytes_stream();
-
-            while let Some(ev) = stream.next().await {
-                let ev = ev.unwrap();
-                let data = String::from_utf8(ev.to_vec()).unwrap();
-
-                if data.len() > 1 {
-                    if debug_enabled {
-                        debug(&format!("game_stream: {}", data));
-                    }
+        let game = match self.game.as_mut() {
+            Some(game) if game.is_online() && game.id() == id => game,
+            _ => return,
+        };
 
-                    let json: Value = serde_json::from_str(&data).unwrap_or_else(|e| {
-                        debug(&format!("error data: {}\n", data));
-                        panic!("data error: {}", e);
-                    });
+        if let Err(e) = game.apply_state(state) {
+            if debug_enabled {
+                debug(&format!("game_state: {}\n", e));
+            }
+        }
+    }
 
-                    match json["type"].as_str().unwrap() {
-                        "gameFull" => {
-                            let state: GameState =
-                                serde_json::from_value(json["state"].clone()).unwrap();
+    pub fn init_new_game<T: ToString>(&mut self, id: T) {
+        let id = id.to_string();
 
-                            let data: GameData = serde_json::from_value(json).unwrap();
-                            let game = Game::online(id.clone(), data, state);
-                            tx.send(Message::GameDataInit(game)).unwrap();
-                        }
+        // the seek or challenge is over once its game starts, and challenges share their game's id
+        self.stop_seek();
+        self.remove_challenge(&id);
 
-                        "gameState" => {
-                            let state: GameState = serde_json::from_value(json).unwrap();
+        if let Some(Outgoing::Pending(challenge)) = self.outgoing.as_ref() {
+            if *challenge.id() == id {
+                self.outgoing = None;
+            }
+        }
+        let tx = self.main_tx.clone();
+
+        let lichess = self.lichess.clone();
+
+        let debug_enabled = *self.config.debug();
+
+        if let Some(stream) = self.game_stream.take() {
+            stream.abort();
+        }
 
-                            tx.send(Message::GameStateUpdate(state)).unwrap();
-                        }
+        self.game_stream = Some(tokio::spawn(async move {
+            let mut stream = match lichess.stream_game(&id).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    debug(&format!("game_stream: {}\n", e));
+                    return;
+                }
+            };
 
-                        "chatLine" => {
-                            let msg: ChatMessage = serde_json::from_value(json).unwrap();
+            while let Some(json) = stream.next().await {
+                if debug_enabled {
+                    debug(&format!("game_stream: {:?}\n", json));
+                }
+
+                let event = match json.and_then(GameEvent::from_json) {
+                    Ok(event) => event,
+                    Err(e) => {
+                        debug(&format!("game_stream error: {}\n", e));
+                        continue;
+                    }
+                };
 
-                            tx.send(Message::NewMessage(msg)).unwrap();
-                        }
-                        _ => (),
+                let message = match event {
+                    GameEvent::GameFull { data, state } => {
+                        Message::GameDataInit(Box::new(Game::online(id.clone(), *data, state)))
                     }
+                    GameEvent::GameState(state) => Message::GameStateUpdate(id.clone(), state),
+                    GameEvent::ChatLine(msg) => Message::NewMessage(msg),
+                    GameEvent::Other => continue,
+                };
+
+                if tx.send(message).is_err() {
+                    return;
                 }
             }
-        });
+        }));
     }
 
     pub fn check_own_side(&self) -> Side {
@@ -238,9 +1049,9 @@ impl App {
                 return Side::White;
             }
 
-            return Side::Black;
+            Side::Black
         } else {
-            return Side::White;
+            Side::White
         }
     }
 
@@ -264,6 +1075,10 @@ impl App {
         &self.config
     }
 
+    pub fn lichess(&self) -> &Lichess {
+        &self.lichess
+    }
+
     pub fn game(&self) -> &Option<Game> {
         &self.game
     }
@@ -274,6 +1089,7 @@ impl App {
 
     pub fn start_game(&mut self, game: Game) {
         self.game = Some(game);
+        self.chat = Chat::default();
         self.ui_state = UIState::Game;
         self.board_display_side = self.check_own_side();
     }
@@ -298,3 +1114,27 @@ impl App {
         }
     }
 }*/
+
+// the external engine's move for `board`, from the engine in `slot` or a newly started one
+async fn external_move(
+    slot: &mut Option<UciEngine>,
+    config: &EngineConfig,
+    board: &Board,
+    params: &GoParams,
+    stop: &AtomicBool,
+    timeout: Duration,
+) -> Result<Option<ChessMove>, EngineError> {
+    if slot.is_none() {
+        let mut engine = UciEngine::start(config).await?;
+        engine.client().new_game().await?;
+        *slot = Some(engine);
+    }
+
+    let client = match slot.as_mut() {
+        Some(engine) => engine.client(),
+        None => return Err(EngineError::Exited),
+    };
+
+    client.set_position(board).await?;
+    client.go(params, stop, timeout).await
+}
@@ -0,0 +1,349 @@
+use crate::challenge::{Challenge, DeclineReason};
+use crate::chess::{ChessMove, Side};
+use crate::config::Config;
+use crate::engine::uci::{GoParams, UciEngine};
+use crate::engine::{Level, Searcher};
+use crate::game::Game;
+use crate::lichess::Lichess;
+use crate::stream::{Event, GameEvent, StreamError};
+
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// correspondence games have days on the clock, so the engine thinks as if there were this much left
+const MAX_REMAINING: Duration = Duration::from_secs(600);
+
+// how long an external engine may take past its time before it counts as hung
+const EXTERNAL_ENGINE_GRACE: Duration = Duration::from_secs(5);
+
+// how many times a move is sent before giving up on it, and how long to wait in between
+const MOVE_ATTEMPTS: u32 = 3;
+const MOVE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize)]
+struct Account {
+    id: String,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+/// Plays on Lichess as a BOT account without the UI: answers challenges by the rules in the `[bot]`
+/// table, plays every game it's in with the built-in or the configured UCI engine, and saves the
+/// finished games as PGN.
+pub async fn run(config: Config) -> Result<(), Box<dyn Error>> {
+    let mut bot = Bot {
+        lichess: Lichess::new(&config),
+        config,
+        own_id: String::new(),
+        games: Mutex::new(HashSet::new()),
+    };
+
+    let account: Account = bot.lichess.account().await?;
+
+    if account.title.as_deref() != Some("BOT") {
+        return Err(format!("{} isn't a BOT account", account.id).into());
+    }
+
+    bot.own_id = account.id;
+    let bot = Arc::new(bot);
+
+    println!("playing as {}", bot.own_id);
+
+    let mut events = bot.lichess.stream_events().await?;
+
+    while let Some(json) = events.next().await {
+        let event = match json.and_then(Event::from_json) {
+            Ok(event) => event,
+            Err(e) => {
+                println!("invalid event: {}", e);
+                continue;
+            }
+        };
+
+        match event {
+            Event::Challenge { challenge } => bot.answer(&challenge).await,
+            Event::ChallengeCanceled { challenge } => {
+                println!("challenge {} was canceled", challenge.id())
+            }
+            Event::GameStart { game } => {
+                let id = game.id().clone();
+
+                // the event is sent again for games that are still going after a reconnect
+                if bot.games.lock().unwrap().insert(id.clone()) {
+                    tokio::spawn(bot.clone().play(id));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Err("the event stream was closed".into())
+}
+
+struct Bot {
+    lichess: Lichess,
+    config: Config,
+    own_id: String,
+    // the ids of the games being played
+    games: Mutex<HashSet<String>>,
+}
+
+impl Bot {
+    async fn answer(&self, challenge: &Challenge) {
+        // our own challenges show up on the event stream too
+        let own = match challenge.challenger() {
+            Some(user) => *user.id() == self.own_id,
+            None => false,
+        };
+
+        if own {
+            return;
+        }
+
+        let verdict = match self.games.lock().unwrap().len() >= self.config.bot().concurrency() {
+            true => Err(DeclineReason::Later),
+            false => self.config.bot().check(challenge),
+        };
+
+        let res = match verdict {
+            Ok(()) => {
+                println!("accepting {} ({})", challenge, challenge.id());
+                self.lichess.accept_challenge(challenge.id()).await
+            }
+            Err(reason) => {
+                println!(
+                    "declining {} ({}): {}",
+                    challenge,
+                    challenge.id(),
+                    reason.key()
+                );
+                self.lichess.decline_challenge(challenge.id(), reason).await
+            }
+        };
+
+        if let Err(e) = res {
+            println!("failed to answer challenge {}: {}", challenge.id(), e);
+        }
+    }
+
+    async fn play(self: Arc<Self>, id: String) {
+        if let Err(e) = self.play_game(&id).await {
+            println!("game {}: {}", id, e);
+        }
+
+        self.games.lock().unwrap().remove(&id);
+    }
+
+    async fn play_game(&self, id: &str) -> Result<(), StreamError> {
+        let mut stream = self.lichess.stream_bot_game(id).await?;
+
+        let mut game: Option<Game> = None;
+        let mut engine: Option<BotEngine> = None;
+        // how many moves had been played when the engine last moved, so a position isn't answered
+        // twice when Lichess repeats a state, e.g. with a draw offer
+        let mut answered: Option<usize> = None;
+
+        while let Some(json) = stream.next().await {
+            let event = match json.and_then(GameEvent::from_json) {
+                Ok(event) => event,
+                Err(e) => {
+                    println!("game {}: invalid event: {}", id, e);
+                    continue;
+                }
+            };
+
+            match event {
+                GameEvent::GameFull { data, state } => {
+                    let full = Game::online(id, *data, state);
+                    println!(
+                        "game {} started: {} vs {}",
+                        id,
+                        full.data().white().name(),
+                        full.data().black().name()
+                    );
+                    game = Some(full);
+                }
+                GameEvent::GameState(state) => match game.as_mut() {
+                    Some(game) => {
+                        if let Err(e) = game.apply_state(state) {
+                            println!("game {}: {}", id, e);
+                        }
+                    }
+                    None => continue,
+                },
+                _ => continue,
+            }
+
+            let game = match game.as_ref() {
+                Some(game) => game,
+                None => continue,
+            };
+
+            if !matches!(game.state().status().as_str(), "created" | "started") {
+                break;
+            }
+
+            let own_side = match *game.data().white().id() == self.own_id {
+                true => Side::White,
+                false => Side::Black,
+            };
+
+            let ply = game.board().played_moves().len();
+
+            if *game.board().turn() != own_side || answered == Some(ply) {
+                continue;
+            }
+
+            match self.find_move(&mut engine, game).await {
+                Ok(Some(mv)) => {
+                    if self.send_move(id, mv).await {
+                        answered = Some(ply);
+                    }
+                }
+                Ok(None) => (),
+                Err(e) => {
+                    println!("game {}: the engine failed again, resigning: {}", id, e);
+                    answered = Some(ply);
+
+                    if let Err(e) = self.lichess.resign_bot_game(id).await {
+                        println!("game {}: failed to resign: {}", id, e);
+                    }
+                }
+            }
+        }
+
+        if let Some(game) = game {
+            let dir = self.config.pgn_dir();
+            fs::create_dir_all(&dir)?;
+
+            let path = dir.join(format!("{}.pgn", id));
+            fs::write(&path, game.to_pgn(self.lichess.base_url()))?;
+
+            println!(
+                "game {} finished: {}, saved to {}",
+                id,
+                game.result(),
+                path.display()
+            );
+        }
+
+        Ok(())
+    }
+
+    // the engine's move, restarting the engine once if it fails, e.g. after it crashed
+    async fn find_move(
+        &self,
+        engine: &mut Option<BotEngine>,
+        game: &Game,
+    ) -> Result<Option<ChessMove>, StreamError> {
+        match self.engine_move(engine, game).await {
+            Ok(mv) => Ok(mv),
+            Err(e) => {
+                println!("game {}: engine error, restarting it: {}", game.id(), e);
+                *engine = None;
+                self.engine_move(engine, game).await
+            }
+        }
+    }
+
+    async fn engine_move(
+        &self,
+        engine: &mut Option<BotEngine>,
+        game: &Game,
+    ) -> Result<Option<ChessMove>, StreamError> {
+        if engine.is_none() {
+            *engine = Some(BotEngine::start(&self.config).await?);
+        }
+
+        engine.as_mut().unwrap().best_move(game).await
+    }
+
+    // sends `mv`, trying again a few times if the request fails, as Lichess sends no new state
+    // until it gets a move
+    async fn send_move(&self, id: &str, mv: ChessMove) -> bool {
+        for attempt in 1..=MOVE_ATTEMPTS {
+            match self.lichess.make_bot_move(id, &mv.to_string()).await {
+                Ok(()) => return true,
+                Err(e) => println!(
+                    "game {}: failed to play {} ({}/{}): {}",
+                    id, mv, attempt, MOVE_ATTEMPTS, e
+                ),
+            }
+
+            if attempt < MOVE_ATTEMPTS {
+                tokio::time::sleep(MOVE_RETRY_DELAY).await;
+            }
+        }
+
+        false
+    }
+}
+
+// the engine playing one of the bot's games
+enum BotEngine {
+    BuiltIn(Arc<Mutex<Searcher>>),
+    External(Box<UciEngine>),
+}
+
+impl BotEngine {
+    async fn start(config: &Config) -> Result<Self, StreamError> {
+        match (config.bot().external_engine(), config.engine()) {
+            (true, Some(engine)) => {
+                let mut engine = UciEngine::start(engine).await?;
+                engine.client().new_game().await?;
+                Ok(BotEngine::External(Box::new(engine)))
+            }
+            (true, None) => Err("no engine configured".into()),
+            (false, _) => Ok(BotEngine::BuiltIn(Arc::new(Mutex::new(Searcher::new(64))))),
+        }
+    }
+
+    async fn best_move(&mut self, game: &Game) -> Result<Option<ChessMove>, StreamError> {
+        let board = game.board().clone();
+
+        let wtime = Duration::from_millis(*game.state().wtime()).min(MAX_REMAINING);
+        let btime = Duration::from_millis(*game.state().btime()).min(MAX_REMAINING);
+        let increment = Duration::from_millis(game.data().increment());
+
+        let remaining = match board.turn() {
+            Side::White => wtime,
+            Side::Black => btime,
+        };
+
+        match self {
+            BotEngine::BuiltIn(searcher) => {
+                let searcher = searcher.clone();
+                let limits = Level::Maximum.limits(remaining, increment);
+
+                let info = tokio::task::spawn_blocking(move || {
+                    let mut searcher = searcher.lock().unwrap_or_else(|e| e.into_inner());
+                    searcher.search(&board, &limits, |_| {})
+                })
+                .await?;
+
+                Ok(info.best_move())
+            }
+            BotEngine::External(engine) => {
+                let params = GoParams::clock(
+                    wtime.as_millis() as u64,
+                    btime.as_millis() as u64,
+                    increment.as_millis() as u64,
+                    increment.as_millis() as u64,
+                );
+
+                let client = engine.client();
+                client.set_position(&board).await?;
+
+                let stop = AtomicBool::new(false);
+                let timeout = remaining + EXTERNAL_ENGINE_GRACE;
+
+                Ok(client.go(&params, &stop, timeout).await?)
+            }
+        }
+    }
+}
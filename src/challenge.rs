@@ -0,0 +1,310 @@
+use crate::game::GameVariant;
+use crate::seek::{SeekField, SeekForm};
+use serde::Deserialize;
+use std::fmt;
+
+/// A challenge to or from the logged in player, as sent on the event stream.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Challenge {
+    id: String,
+    #[serde(default)]
+    challenger: Option<ChallengeUser>,
+    #[serde(default, rename = "destUser")]
+    dest_user: Option<ChallengeUser>,
+    #[serde(default)]
+    variant: GameVariant,
+    #[serde(default)]
+    rated: bool,
+    #[serde(default, rename = "timeControl")]
+    time_control: TimeControl,
+    #[serde(default)]
+    color: String,
+    // the reason the challenged player gave, in a `challengeDeclined` event
+    #[serde(default, rename = "declineReason")]
+    decline_reason: Option<String>,
+}
+
+impl Challenge {
+    pub fn id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn challenger(&self) -> &Option<ChallengeUser> {
+        &self.challenger
+    }
+
+    pub fn dest_user(&self) -> &Option<ChallengeUser> {
+        &self.dest_user
+    }
+
+    pub fn variant(&self) -> &GameVariant {
+        &self.variant
+    }
+
+    pub fn rated(&self) -> &bool {
+        &self.rated
+    }
+
+    pub fn time_control(&self) -> &TimeControl {
+        &self.time_control
+    }
+
+    /// The color the challenger asked for: `white`, `black` or `random`.
+    pub fn color(&self) -> &String {
+        &self.color
+    }
+
+    pub fn decline_reason(&self) -> &Option<String> {
+        &self.decline_reason
+    }
+}
+
+impl fmt::Display for Challenge {
+    /// E.g. `5+3 rated Chess960 from DrNykterstein (2860)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.time_control,
+            match self.rated {
+                true => "rated",
+                false => "casual",
+            },
+            self.variant.name()
+        )?;
+
+        match &self.challenger {
+            Some(user) => write!(f, " from {}", user),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ChallengeUser {
+    id: String,
+    name: String,
+    #[serde(default)]
+    rating: Option<u32>,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+impl ChallengeUser {
+    pub fn id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn rating(&self) -> &Option<u32> {
+        &self.rating
+    }
+
+    /// The title, e.g. `GM` or `BOT`.
+    pub fn title(&self) -> &Option<String> {
+        &self.title
+    }
+}
+
+impl fmt::Display for ChallengeUser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(title) = &self.title {
+            write!(f, "{} ", title)?;
+        }
+
+        write!(f, "{}", self.name)?;
+
+        match self.rating {
+            Some(rating) => write!(f, " ({})", rating),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The time control of a challenge. Clock times are in seconds.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TimeControl {
+    Clock {
+        limit: u64,
+        increment: u64,
+    },
+    Correspondence {
+        #[serde(rename = "daysPerTurn")]
+        days_per_turn: u32,
+    },
+    #[default]
+    Unlimited,
+}
+
+impl fmt::Display for TimeControl {
+    /// Clocks in minutes and seconds of increment, e.g. `5+3` or `½+0`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeControl::Clock { limit, increment } => match limit {
+                15 => write!(f, "¼+{}", increment),
+                30 => write!(f, "½+{}", increment),
+                45 => write!(f, "¾+{}", increment),
+                limit => write!(f, "{}+{}", limit / 60, increment),
+            },
+            TimeControl::Correspondence { days_per_turn: 1 } => write!(f, "1 day per move"),
+            TimeControl::Correspondence { days_per_turn } => {
+                write!(f, "{} days per move", days_per_turn)
+            }
+            TimeControl::Unlimited => write!(f, "unlimited"),
+        }
+    }
+}
+
+/// Why a challenge was declined, as one of the reasons Lichess shows the challenger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclineReason {
+    Generic,
+    Later,
+    TooFast,
+    TooSlow,
+    TimeControl,
+    Rated,
+    Casual,
+    Variant,
+}
+
+impl DeclineReason {
+    /// The reason's key for `/api/challenge/{id}/decline`.
+    pub fn key(&self) -> &'static str {
+        use DeclineReason::*;
+
+        match self {
+            Generic => "generic",
+            Later => "later",
+            TooFast => "tooFast",
+            TooSlow => "tooSlow",
+            TimeControl => "timeControl",
+            Rated => "rated",
+            Casual => "casual",
+            Variant => "variant",
+        }
+    }
+}
+
+/// What became of the challenge the player sent last.
+#[derive(Debug, Clone)]
+pub enum Outgoing {
+    Pending(Challenge),
+    Declined(Challenge),
+    Failed(String),
+}
+
+impl fmt::Display for Outgoing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opponent = |challenge: &Challenge| match &challenge.dest_user {
+            Some(user) => user.name.clone(),
+            None => "?".into(),
+        };
+
+        match self {
+            Outgoing::Pending(challenge) => write!(
+                f,
+                "waiting for {} to accept {} {} {}",
+                opponent(challenge),
+                challenge.time_control,
+                match challenge.rated {
+                    true => "rated",
+                    false => "casual",
+                },
+                challenge.variant.name()
+            ),
+            Outgoing::Declined(challenge) => match &challenge.decline_reason {
+                Some(reason) => write!(f, "{} declined: {}", opponent(challenge), reason),
+                None => write!(f, "{} declined the challenge", opponent(challenge)),
+            },
+            Outgoing::Failed(e) => write!(f, "failed to send the challenge: {}", e),
+        }
+    }
+}
+
+/// The "Challenge a player" form: the player to challenge, and the settings of the game, picked
+/// like in the seek dialog.
+#[derive(Debug, Clone, Default)]
+pub struct ChallengeForm {
+    username: String,
+    // 0 is the username, and the rest the fields in `ChallengeForm::SETTINGS`
+    field: usize,
+    settings: SeekForm,
+}
+
+impl ChallengeForm {
+    pub const SETTINGS: [SeekField; 4] = [
+        SeekField::TimeControl,
+        SeekField::Mode,
+        SeekField::Color,
+        SeekField::Variant,
+    ];
+
+    pub fn username(&self) -> &String {
+        &self.username
+    }
+
+    /// Whether the username is selected, so typed characters go into it.
+    pub fn editing_username(&self) -> bool {
+        self.field == 0
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        self.username.push(c);
+    }
+
+    pub fn pop_char(&mut self) {
+        self.username.pop();
+    }
+
+    /// The selected setting, or `None` while the username is selected.
+    pub fn field(&self) -> Option<SeekField> {
+        match self.field {
+            0 => None,
+            idx => Some(Self::SETTINGS[idx - 1]),
+        }
+    }
+
+    pub fn move_field(&mut self, up: bool) {
+        self.field = match up {
+            true => self.field.saturating_sub(1),
+            false => (self.field + 1).min(Self::SETTINGS.len()),
+        };
+    }
+
+    pub fn change(&mut self, forward: bool) {
+        if let Some(field) = self.field() {
+            self.settings.change_field(field, forward);
+        }
+    }
+
+    pub fn settings(&self) -> &SeekForm {
+        &self.settings
+    }
+
+    /// The form parameters of `/api/challenge/{username}`.
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("rated", self.settings.rated().to_string()),
+            ("variant", self.settings.variant().0.into()),
+            ("color", self.settings.color().into()),
+        ];
+
+        match self.settings.time_control() {
+            TimeControl::Clock { limit, increment } => {
+                params.push(("clock.limit", limit.to_string()));
+                params.push(("clock.increment", increment.to_string()));
+            }
+            TimeControl::Correspondence { days_per_turn } => {
+                params.push(("days", days_per_turn.to_string()))
+            }
+            TimeControl::Unlimited => (),
+        }
+
+        params
+    }
+}
@@ -0,0 +1,161 @@
+use crate::game::ChatMessage;
+
+/// A chat room of an online game, one for the players and one for the spectators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Room {
+    Player,
+    Spectator,
+}
+
+impl Room {
+    /// The `room` of the Lichess API.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Room::Player => "player",
+            Room::Spectator => "spectator",
+        }
+    }
+}
+
+/// The chat panel of an online game: the room shown, how far it's scrolled back and the message
+/// being typed.
+#[derive(Debug, Clone)]
+pub struct Chat {
+    room: Room,
+    // how many lines up from the newest one the panel is scrolled
+    scroll: usize,
+    input: Option<String>,
+}
+
+impl Default for Chat {
+    fn default() -> Self {
+        Self {
+            room: Room::Player,
+            scroll: 0,
+            input: None,
+        }
+    }
+}
+
+impl Chat {
+    pub fn room(&self) -> &Room {
+        &self.room
+    }
+
+    pub fn toggle_room(&mut self) {
+        self.room = match self.room {
+            Room::Player => Room::Spectator,
+            Room::Spectator => Room::Player,
+        };
+
+        self.scroll = 0;
+    }
+
+    pub fn scroll(&self) -> &usize {
+        &self.scroll
+    }
+
+    /// Scrolls `lines` back towards older messages, or forward with `up` unset.
+    pub fn scroll_by(&mut self, lines: usize, up: bool) {
+        self.scroll = match up {
+            true => self.scroll + lines,
+            false => self.scroll.saturating_sub(lines),
+        };
+    }
+
+    /// The message being typed, while the input is open.
+    pub fn input(&self) -> &Option<String> {
+        &self.input
+    }
+
+    pub fn open_input(&mut self) {
+        self.input.get_or_insert_with(String::new);
+    }
+
+    pub fn close_input(&mut self) {
+        self.input = None;
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        if let Some(input) = self.input.as_mut() {
+            input.push(c);
+        }
+    }
+
+    pub fn pop_char(&mut self) {
+        if let Some(input) = self.input.as_mut() {
+            input.pop();
+        }
+    }
+
+    /// Closes the input, returning what was typed in it.
+    pub fn take_input(&mut self) -> Option<String> {
+        self.input.take()
+    }
+
+    /// Keeps the panel from scrolling back past the first message, for a panel of `height` rows
+    /// of `width` columns.
+    pub fn clamp_scroll(&mut self, messages: &[ChatMessage], width: usize, height: usize) {
+        let lines = room_lines(messages, &self.room, width).len();
+        self.scroll = self.scroll.min(lines.saturating_sub(height));
+    }
+
+    /// The lines that fit in a panel of `height` rows of `width` columns, oldest first.
+    pub fn visible_lines(
+        &self,
+        messages: &[ChatMessage],
+        width: usize,
+        height: usize,
+    ) -> Vec<String> {
+        let lines = room_lines(messages, &self.room, width);
+
+        let end = lines.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(height);
+
+        lines[start..end].to_vec()
+    }
+}
+
+/// The messages of `room` as `username: text`, wrapped to `width` columns.
+pub fn room_lines(messages: &[ChatMessage], room: &Room, width: usize) -> Vec<String> {
+    messages
+        .iter()
+        .filter(|msg| msg.room() == room.key())
+        .flat_map(|msg| wrap(&format!("{}: {}", msg.username(), msg.text()), width))
+        .collect()
+}
+
+/// Wraps `text` at spaces into lines of at most `width` characters, breaking up words that are
+/// longer than a line.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = vec![];
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+
+        if !line.is_empty() && line.chars().count() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+        }
+
+        while line.chars().count() + word.len() > width {
+            let rest = word.split_off(width - line.chars().count());
+            line.extend(word);
+            lines.push(std::mem::take(&mut line));
+            word = rest;
+        }
+
+        line.extend(word);
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
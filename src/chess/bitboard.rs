@@ -0,0 +1,183 @@
+/// A set of squares, one bit per square with a1 as the least significant bit and h8 as the most.
+pub type Bitboard = u64;
+
+pub const EMPTY: Bitboard = 0;
+
+pub const RANK_1: Bitboard = 0xff;
+pub const RANK_2: Bitboard = RANK_1 << 8;
+pub const RANK_7: Bitboard = RANK_1 << 48;
+pub const RANK_8: Bitboard = RANK_1 << 56;
+
+// d4, e4, d5 and e5
+pub const CENTER: Bitboard = 0x1818 << 24;
+
+pub const fn bit(sq: usize) -> Bitboard {
+    1 << sq
+}
+
+pub const fn contains(bb: Bitboard, sq: usize) -> bool {
+    bb & bit(sq) != 0
+}
+
+/// Iterates over the squares of a bitboard, from a1 towards h8.
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+
+        let sq = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+
+        Some(sq)
+    }
+}
+
+pub fn squares(bb: Bitboard) -> Squares {
+    Squares(bb)
+}
+
+/// Builds an attack table for a piece that jumps by fixed (file, rank) offsets.
+pub const fn leaper_attacks(offsets: &[(isize, isize)]) -> [Bitboard; 64] {
+    let mut table = [EMPTY; 64];
+    let mut sq = 0;
+
+    while sq < 64 {
+        let (x, y) = ((sq % 8) as isize, (sq / 8) as isize);
+        let mut idx = 0;
+
+        while idx < offsets.len() {
+            let (tx, ty) = (x + offsets[idx].0, y + offsets[idx].1);
+
+            if tx >= 0 && tx < 8 && ty >= 0 && ty < 8 {
+                table[sq] |= bit((ty * 8 + tx) as usize);
+            }
+
+            idx += 1;
+        }
+
+        sq += 1;
+    }
+
+    table
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    pub const ORTHOGONAL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    pub const DIAGONAL: [Direction; 4] = [
+        Direction::NorthEast,
+        Direction::SouthEast,
+        Direction::SouthWest,
+        Direction::NorthWest,
+    ];
+
+    const fn offset(self) -> (isize, isize) {
+        use Direction::*;
+
+        match self {
+            North => (0, 1),
+            NorthEast => (1, 1),
+            East => (1, 0),
+            SouthEast => (1, -1),
+            South => (0, -1),
+            SouthWest => (-1, -1),
+            West => (-1, 0),
+            NorthWest => (-1, 1),
+        }
+    }
+
+    // whether the square indices grow along the ray
+    const fn is_positive(self) -> bool {
+        let (x, y) = self.offset();
+        y > 0 || (y == 0 && x > 0)
+    }
+}
+
+const fn init_rays() -> [[Bitboard; 64]; 8] {
+    use Direction::*;
+
+    let directions = [
+        North, NorthEast, East, SouthEast, South, SouthWest, West, NorthWest,
+    ];
+
+    let mut rays = [[EMPTY; 64]; 8];
+    let mut dir = 0;
+
+    while dir < 8 {
+        let (dx, dy) = directions[dir].offset();
+        let mut sq = 0;
+
+        while sq < 64 {
+            let (mut x, mut y) = ((sq % 8) as isize + dx, (sq / 8) as isize + dy);
+
+            while x >= 0 && x < 8 && y >= 0 && y < 8 {
+                rays[dir][sq] |= bit((y * 8 + x) as usize);
+                x += dx;
+                y += dy;
+            }
+
+            sq += 1;
+        }
+
+        dir += 1;
+    }
+
+    rays
+}
+
+// every square from a square to the edge of the board in each direction, excluding the square itself
+const RAYS: [[Bitboard; 64]; 8] = init_rays();
+
+/// The squares a slider on `sq` attacks in one direction, stopping at (and including) the first
+/// occupied square.
+pub fn ray_attacks(sq: usize, occupied: Bitboard, dir: Direction) -> Bitboard {
+    let ray = RAYS[dir as usize][sq];
+    let blockers = ray & occupied;
+
+    if blockers == 0 {
+        return ray;
+    }
+
+    let blocker = match dir.is_positive() {
+        true => blockers.trailing_zeros() as usize,
+        false => 63 - blockers.leading_zeros() as usize,
+    };
+
+    ray ^ RAYS[dir as usize][blocker]
+}
+
+/// Every square strictly between two squares on the same line, or an empty set if they aren't on one.
+pub fn between(a: usize, b: usize) -> Bitboard {
+    for dir in Direction::ORTHOGONAL
+        .iter()
+        .chain(Direction::DIAGONAL.iter())
+    {
+        if contains(RAYS[*dir as usize][a], b) {
+            return ray_attacks(a, bit(b), *dir) & !bit(b);
+        }
+    }
+
+    EMPTY
+}
@@ -1,26 +1,33 @@
-use super::{CastleKind, Piece, PieceKind, PlayedMove, PlayedMoveKind, Side};
+use super::{
+    CastleKind, CastlingRights, ChessMove, MoveError, Outcome, Piece, PieceKind, PlayedMove,
+    PlayedMoveKind, Pocket, Side, Square, Variant,
+};
 
-use crate::chess::utils::{idx_to_square, square_to_idx};
+use crate::chess::bitboard::{bit, contains, squares, Bitboard, CENTER, EMPTY, RANK_1, RANK_8};
+use crate::chess::fen::{self, FenError};
+use crate::chess::zobrist;
 
-use crate::chess::moves::bishop::generate_bishop_moves;
-use crate::chess::moves::king::generate_king_moves;
-use crate::chess::moves::knight::generate_knight_moves;
-use crate::chess::moves::pawn::generate_pawn_moves;
+use crate::chess::utils::get_square_color;
+
+use crate::chess::moves::bishop::{bishop_attacks, generate_bishop_moves};
+use crate::chess::moves::king::{generate_castling_moves, generate_king_moves, king_attacks};
+use crate::chess::moves::knight::{generate_knight_moves, knight_attacks};
+use crate::chess::moves::pawn::{generate_pawn_moves, pawn_attacks};
 use crate::chess::moves::queen::generate_queen_moves;
-use crate::chess::moves::rook::generate_rook_moves;
+use crate::chess::moves::rook::{generate_rook_moves, rook_attacks};
 
 use std::time::Instant;
 
 #[allow(unused)]
 use crate::utils::debug;
 
-pub trait Square {
+pub trait Coords {
     fn x(&self) -> usize;
     fn y(&self) -> usize;
     fn pos(&self) -> (usize, usize);
 }
 
-impl Square for usize {
+impl Coords for usize {
     fn x(&self) -> usize {
         self - self.y() * 8
     }
@@ -34,90 +41,165 @@ impl Square for usize {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SquareColor {
     Light,
     Dark,
 }
 
+// everything besides the played move itself that's needed to take a move back
 #[derive(Debug, Clone)]
-pub enum Edge {
-    Left,
-    Right,
-    Top,
-    Bottom,
+struct Undo {
+    captured: Option<Piece>,
+    // the pieces an Atomic capture blew up, including the capturer
+    exploded: Vec<(usize, Piece)>,
+    checks: [u8; 2],
+    pockets: [Pocket; 2],
+    promoted: Bitboard,
+    en_passant: Option<usize>,
+    castling: CastlingRights,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    hash: u64,
 }
 
 #[derive(Debug, Clone)]
 pub struct Board {
+    // the mailbox and the bitboards always describe the same position, see `set_piece`
     pieces: Vec<Option<Piece>>,
+    by_kind: [Bitboard; 6],
+    by_side: [Bitboard; 2],
     en_passant: Option<usize>,
+    castling: CastlingRights,
+    chess960: bool,
+    variant: Variant,
+    // how many times each side has given check, for Three-check
+    checks: [u8; 2],
+    // the Crazyhouse pockets, and the pieces that were pawns and go back to a pocket as pawns
+    pockets: [Pocket; 2],
+    promoted: Bitboard,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    hash: u64,
+    history: Vec<Undo>,
     turn: Side,
-    current_generated_moves: Vec<usize>,
+    current_generated_moves: Vec<ChessMove>,
     played_moves: Vec<PlayedMove>,
     turn_time_taken: Instant,
 }
 
 impl Board {
-    pub fn current_generated_moves(&self) -> &Vec<usize> {
+    pub fn current_generated_moves(&self) -> &Vec<ChessMove> {
         &self.current_generated_moves
     }
 
-    pub fn make_move_str(&mut self, mv: &str) {
-        let (src, dest) = mv.split_at(2);
-        let (src, dest) = (square_to_idx(src), square_to_idx(dest));
-
-        self.make_move(src, dest);
+    /// Plays a move in UCI notation, e.g. `e2e4`, `e7e8n` or `P@e4`.
+    pub fn make_move_str(&mut self, uci: &str) -> Result<(), MoveError> {
+        self.make_move(uci.parse()?)
     }
 
-    // used for reverting played moves
-    fn make_move_str_raw(&mut self, mv: &str, promotion: bool) {
-        let (src, dest) = mv.split_at(2);
-        let (src, dest) = (square_to_idx(src), square_to_idx(dest));
-
-        self.make_move_raw(src, dest, promotion);
+    /// The starting position of `variant`.
+    pub fn new(variant: Variant) -> Self {
+        Self::from_fen_variant(variant.starting_fen(), variant).unwrap()
     }
 
-    pub fn from_str(fen: &str, turn: Side) -> Self {
-        let mut pieces: Vec<Option<Piece>> = vec![];
+    /// Builds a board from all six fields of a FEN string.
+    /// The halfmove clock and fullmove number may be omitted, in which case they default to 0 and 1.
+    /// Castling rights that only make sense in Chess960 switch the board to Chess960 castling.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        Self::from_fen_variant(fen, Variant::Standard)
+    }
 
-        for row in fen.split("/") {
-            for c in row.split("") {
-                use PieceKind::*;
-                use Side::*;
-                match c {
-                    "P" => pieces.push(Some(Piece::new(Pawn, White))),
-                    "N" => pieces.push(Some(Piece::new(Knight, White))),
-                    "B" => pieces.push(Some(Piece::new(Bishop, White))),
-                    "R" => pieces.push(Some(Piece::new(Rook, White))),
-                    "Q" => pieces.push(Some(Piece::new(Queen, White))),
-                    "K" => pieces.push(Some(Piece::new(King, White))),
+    /// Like `from_fen`, for a board played by the rules of `variant`.
+    pub fn from_fen_variant(fen: &str, variant: Variant) -> Result<Self, FenError> {
+        let mut fields = fen.split_whitespace();
+
+        let (placement, pockets) =
+            fen::split_pockets(fields.next().ok_or(FenError::MissingField("placement"))?)?;
+        let (pieces, promoted) = fen::parse_placement(placement, variant)?;
+        let turn = fen::parse_side(
+            fields
+                .next()
+                .ok_or(FenError::MissingField("side to move"))?,
+        )?;
+
+        let castling_field = fields.next().ok_or(FenError::MissingField("castling"))?;
+        let castling = CastlingRights::from_fen(castling_field, &pieces)
+            .ok_or_else(|| FenError::InvalidCastling(castling_field.into()))?;
+
+        let en_passant = fen::parse_en_passant(
+            fields.next().ok_or(FenError::MissingField("en passant"))?,
+            &turn,
+        )?;
+
+        let halfmove_clock = match fields.next() {
+            Some(field) => fen::parse_clock(field)?,
+            None => 0,
+        };
 
-                    "p" => pieces.push(Some(Piece::new(Pawn, Black))),
-                    "n" => pieces.push(Some(Piece::new(Knight, Black))),
-                    "b" => pieces.push(Some(Piece::new(Bishop, Black))),
-                    "r" => pieces.push(Some(Piece::new(Rook, Black))),
-                    "q" => pieces.push(Some(Piece::new(Queen, Black))),
-                    "k" => pieces.push(Some(Piece::new(King, Black))),
-                    _ => (),
-                }
+        let fullmove_number = match fields.next() {
+            Some(field) => fen::parse_clock(field)?,
+            None => 1,
+        };
 
-                if let Ok(res) = c.parse::<usize>() {
-                    for _ in 0..res {
-                        pieces.push(None);
-                    }
-                }
-            }
+        if fields.next().is_some() {
+            return Err(FenError::TooManyFields);
         }
 
-        Self {
-            pieces,
+        let mut board = Self {
+            pieces: vec![None; 64],
+            by_kind: [EMPTY; 6],
+            by_side: [EMPTY; 2],
             turn,
-            en_passant: None,
+            en_passant,
+            chess960: castling.is_chess960(),
+            castling,
+            variant,
+            checks: [0; 2],
+            pockets,
+            promoted,
+            halfmove_clock,
+            fullmove_number,
+            hash: 0,
+            history: vec![],
             current_generated_moves: vec![],
             played_moves: vec![],
             turn_time_taken: Instant::now(),
+        };
+
+        for (idx, piece) in pieces.into_iter().enumerate() {
+            board.set_piece(idx, piece);
         }
+
+        board.hash = board.compute_hash();
+
+        Ok(board)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let turn = match self.turn {
+            Side::White => "w",
+            Side::Black => "b",
+        };
+
+        let placement = match self.variant.has_pockets() {
+            true => format!(
+                "{}[{}]",
+                fen::placement_to_fen(&self.pieces, self.promoted),
+                Pocket::pair_to_fen(&self.pockets)
+            ),
+            false => fen::placement_to_fen(&self.pieces, EMPTY),
+        };
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement,
+            turn,
+            self.castling.to_fen(&self.pieces),
+            fen::en_passant_to_fen(&self.en_passant),
+            self.halfmove_clock,
+            self.fullmove_number
+        )
     }
 
     pub fn turn_time_taken(&self) -> &Instant {
@@ -128,283 +210,923 @@ impl Board {
         self.turn_time_taken = Instant::now();
     }
 
-    pub fn revert_move(&mut self) {
-        if let Some(mv) = self.played_moves.last() {
-            if *mv.kind() == PlayedMoveKind::Promotion {
-                let rev_mv = mv.reverse().first().unwrap().clone();
-                self.make_move_str_raw(&rev_mv, true);
-                return;
+    /// Takes back the last played move, restoring the board to exactly the state it was in before it.
+    pub fn unmake_move(&mut self) {
+        let (mv, undo) = match (self.played_moves.pop(), self.history.pop()) {
+            (Some(mv), Some(undo)) => (mv, undo),
+            _ => return,
+        };
+
+        self.swap_turn();
+
+        let side = self.turn.clone();
+        let (source, dest) = (mv.chess_move().from().index(), mv.chess_move().to().index());
+
+        for (sq, piece) in undo.exploded.iter() {
+            self.set_piece(*sq, Some(piece.clone()));
+        }
+
+        match mv.kind() {
+            PlayedMoveKind::Castle(kind) => {
+                let (king_dest, rook_dest) = kind.destination_squares();
+                let (king, rook) = (
+                    self.piece_at(king_dest).clone(),
+                    self.piece_at(rook_dest).clone(),
+                );
+
+                self.set_piece(king_dest, None);
+                self.set_piece(rook_dest, None);
+
+                self.set_piece(source, king);
+
+                if let Some(sq) = undo.castling.rook_square(kind) {
+                    self.set_piece(sq, rook);
+                }
+            }
+
+            PlayedMoveKind::Promotion(_) => {
+                self.set_piece(source, Some(Piece::new(PieceKind::Pawn, side)));
+                self.set_piece(dest, undo.captured);
             }
 
-            for mv in mv.reverse() {
-                self.make_move_str_raw(&mv, false);
+            PlayedMoveKind::EnPassant => {
+                let pawn = self.piece_at(dest).clone();
+
+                self.set_piece(source, pawn);
+                self.set_piece(dest, None);
+                self.set_piece(source.y() * 8 + dest.x(), undo.captured);
             }
 
-            self.swap_turn();
+            PlayedMoveKind::Normal => {
+                let piece = self.piece_at(dest).clone();
+
+                self.set_piece(source, piece);
+                self.set_piece(dest, undo.captured);
+            }
 
-            self.played_moves.pop();
+            PlayedMoveKind::Drop(_) => self.set_piece(dest, None),
         }
+
+        self.en_passant = undo.en_passant;
+        self.castling = undo.castling;
+        self.checks = undo.checks;
+        self.pockets = undo.pockets;
+        self.promoted = undo.promoted;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.hash = undo.hash;
     }
 
     pub fn played_moves(&self) -> &Vec<PlayedMove> {
         &self.played_moves
     }
 
-    pub fn generate_moves(&self, sq: usize, piece: &Piece) -> Vec<usize> {
-        use PieceKind::*;
+    /// Every legal move of the side to move, with one move per promotion piece, followed by the
+    /// drops in Crazyhouse.
+    pub fn legal_moves(&self) -> Vec<ChessMove> {
+        let mut moves: Vec<ChessMove> = squares(self.side_bitboard(&self.turn))
+            .flat_map(|sq| self.legal_moves_from(Square::from_index_unchecked(sq)))
+            .collect();
+
+        for kind in self.pockets[self.turn.index()].kinds() {
+            moves.append(&mut self.legal_drops(&kind));
+        }
+
+        moves
+    }
 
-        let mut moves = match piece.kind() {
-            Pawn => generate_pawn_moves(&self, sq, piece),
-            Rook => generate_rook_moves(&self, sq, piece),
-            Knight => generate_knight_moves(&self, sq, piece),
-            Bishop => generate_bishop_moves(&self, sq, piece),
-            Queen => generate_queen_moves(&self, sq, piece),
-            King => generate_king_moves(&self, sq, piece),
+    /// The legal drops of a piece of `kind` from the pocket of the side to move.
+    pub fn legal_drops(&self, kind: &PieceKind) -> Vec<ChessMove> {
+        squares(self.drop_targets(kind))
+            .map(|sq| ChessMove::new_drop(*kind, Square::from_index_unchecked(sq)))
+            .collect()
+    }
+
+    // the empty squares a piece of `kind` can be dropped on, which aren't on the first or last
+    // rank for pawns
+    fn drop_targets(&self, kind: &PieceKind) -> Bitboard {
+        if !self.variant.has_pockets() || self.pockets[self.turn.index()].count(kind) == 0 {
+            return EMPTY;
+        }
+
+        let targets = match kind {
+            PieceKind::Pawn => !self.occupied() & !(RANK_1 | RANK_8),
+            _ => !self.occupied(),
         };
 
-        moves.retain(|m| m < &64);
+        // a dropped piece can't capture, so it only gets the king out of check by blocking it
+        match self.king_square(&self.turn) {
+            Some(king) if self.is_check(&self.turn) => squares(targets)
+                .filter(|sq| {
+                    let occupied = self.occupied() | bit(*sq);
+                    self.attackers(king, &self.turn.opposite(), occupied) == EMPTY
+                })
+                .fold(EMPTY, |bb, sq| bb | bit(sq)),
+            _ => targets,
+        }
+    }
 
-        if piece.side() == &self.turn {
-            let mut board = self.clone();
+    /// The legal moves of the piece on `from`, or none if it doesn't belong to the side to move.
+    pub fn legal_moves_from(&self, from: Square) -> Vec<ChessMove> {
+        let piece = match self.piece_at(from.index()) {
+            Some(p) if *p.side() == self.turn => p,
+            _ => return vec![],
+        };
 
-            for mv in moves.clone().iter() {
-                board.make_move(sq, *mv);
-                board.swap_turn();
+        let mut moves = vec![];
 
-                if board.is_check(piece.side()) {
-                    moves.retain(|m| m != mv);
-                }
+        for dest in squares(self.generate_move_bitboard(from.index(), piece)) {
+            let to = Square::from_index_unchecked(dest);
 
-                board.swap_turn();
-                board.revert_move();
+            match self.is_promotion(from, to) {
+                true => {
+                    for kind in self.variant.promotion_kinds() {
+                        moves.push(ChessMove::new(from, to, Some(*kind)));
+                    }
+                }
+                false => moves.push(ChessMove::new(from, to, None)),
             }
         }
 
         moves
     }
 
+    /// Whether `mv` is a legal move for the side to move. Promotions without a piece count as
+    /// queen promotions.
+    pub fn is_legal(&self, mv: &ChessMove) -> bool {
+        if let Some(kind) = mv.dropped() {
+            return contains(self.drop_targets(&kind), mv.to().index());
+        }
+
+        let piece = match self.piece_at(mv.from().index()) {
+            Some(p) if *p.side() == self.turn => p,
+            _ => return false,
+        };
+
+        let promotion_ok = match (self.is_promotion(mv.from(), mv.to()), mv.promotion()) {
+            (_, None) => true,
+            (true, Some(kind)) => self.variant.promotion_kinds().contains(&kind),
+            (false, Some(_)) => false,
+        };
+
+        promotion_ok
+            && contains(
+                self.generate_move_bitboard(mv.from().index(), piece),
+                mv.to().index(),
+            )
+    }
+
+    /// Generates the destination squares of the piece on `sq`. Moves of the side to move are fully
+    /// legal, while the other side's moves don't account for checks.
+    pub fn generate_move_bitboard(&self, sq: usize, piece: &Piece) -> Bitboard {
+        let moves = self.pseudo_legal_moves(sq, piece);
+
+        if piece.side() != &self.turn {
+            return moves;
+        }
+
+        let moves = match self.variant {
+            Variant::Antichess if self.must_capture() => moves & self.capture_targets(piece),
+            Variant::Antichess => moves,
+            Variant::Atomic | Variant::RacingKings => squares(moves)
+                .filter(|dest| self.variant_allows(sq, *dest))
+                .fold(EMPTY, |bb, dest| bb | bit(dest)),
+            _ => squares(moves)
+                .filter(|dest| self.leaves_king_safe(sq, *dest))
+                .fold(EMPTY, |bb, dest| bb | bit(dest)),
+        };
+
+        match piece.kind() {
+            PieceKind::King if self.variant.allows_castling() => {
+                moves | generate_castling_moves(self, sq, piece)
+            }
+            _ => moves,
+        }
+    }
+
+    fn pseudo_legal_moves(&self, sq: usize, piece: &Piece) -> Bitboard {
+        use PieceKind::*;
+
+        match piece.kind() {
+            Pawn => generate_pawn_moves(self, sq, piece),
+            Rook => generate_rook_moves(self, sq, piece),
+            Knight => generate_knight_moves(self, sq, piece),
+            Bishop => generate_bishop_moves(self, sq, piece),
+            Queen => generate_queen_moves(self, sq, piece),
+            King => generate_king_moves(self, sq, piece),
+        }
+    }
+
+    // the squares `piece` captures on when it moves there, which includes en passant for pawns
+    fn capture_targets(&self, piece: &Piece) -> Bitboard {
+        let enemies = self.side_bitboard(&piece.side().opposite());
+
+        match (piece.kind(), self.en_passant) {
+            (PieceKind::Pawn, Some(ep)) => enemies | bit(ep),
+            _ => enemies,
+        }
+    }
+
+    // in Antichess, the side to move has to capture whenever it can
+    fn must_capture(&self) -> bool {
+        squares(self.side_bitboard(&self.turn)).any(|sq| match self.piece_at(sq) {
+            Some(p) => self.pseudo_legal_moves(sq, p) & self.capture_targets(p) != EMPTY,
+            None => false,
+        })
+    }
+
+    // Atomic and Racing Kings moves are checked by playing them on a copy of the board
+    fn variant_allows(&self, source: usize, dest: usize) -> bool {
+        let side = self.turn.clone();
+
+        let mut board = self.clone();
+        board.play(ChessMove::new(
+            Square::from_index_unchecked(source),
+            Square::from_index_unchecked(dest),
+            None,
+        ));
+
+        match self.variant {
+            // blowing up the enemy king wins even if the own king ends up in check,
+            // and kings that touch can't check each other
+            Variant::Atomic => match (
+                board.king_square(&side),
+                board.king_square(&side.opposite()),
+            ) {
+                (None, _) => false,
+                (_, None) => true,
+                (Some(_), Some(_)) => !board.is_check(&side),
+            },
+            // nobody may give check at all
+            _ => !board.is_check(&side) && !board.is_check(&side.opposite()),
+        }
+    }
+
+    // checks a pseudo-legal move by looking at the occupancy after it, without playing it
+    fn leaves_king_safe(&self, source: usize, dest: usize) -> bool {
+        let piece = match self.piece_at(source) {
+            Some(p) => p,
+            None => return false,
+        };
+
+        let side = piece.side();
+
+        let captured = match *piece.kind() == PieceKind::Pawn && self.en_passant == Some(dest) {
+            true => bit(source.y() * 8 + dest.x()),
+            false => bit(dest),
+        };
+
+        let king = match piece.kind() {
+            PieceKind::King => dest,
+            _ => match self.king_square(side) {
+                Some(sq) => sq,
+                None => return true,
+            },
+        };
+
+        let occupied = (self.occupied() & !bit(source) & !captured) | bit(dest);
+        let attackers = self.attackers(king, &side.opposite(), occupied) & !captured;
+
+        attackers == EMPTY
+    }
+
+    pub fn king_square(&self, side: &Side) -> Option<usize> {
+        squares(self.pieces_bitboard(&PieceKind::King, side)).next()
+    }
+
     pub fn is_check(&self, side: &Side) -> bool {
-        let mut king = None;
+        if !self.variant.has_king_safety() || self.kings_touch() {
+            return false;
+        }
 
-        for (idx, piece) in self.pieces.iter().enumerate() {
-            if let Some(p) = piece {
-                if p.kind() == &PieceKind::King && p.side() == side {
-                    king = Some(idx)
+        match self.king_square(side) {
+            Some(sq) => self.is_square_attacked(sq, &side.opposite()),
+            None => false,
+        }
+    }
+
+    // in Atomic, a king next to the enemy king can't be in check, since capturing it would blow up
+    // both kings
+    fn kings_touch(&self) -> bool {
+        if self.variant != Variant::Atomic {
+            return false;
+        }
+
+        match (
+            self.king_square(&Side::White),
+            self.king_square(&Side::Black),
+        ) {
+            (Some(white), Some(black)) => contains(king_attacks(white), black),
+            _ => false,
+        }
+    }
+
+    /// Whether any piece of `side` attacks `sq`, regardless of whose turn it is.
+    pub fn is_square_attacked(&self, sq: usize, side: &Side) -> bool {
+        self.attackers(sq, side, self.occupied()) != EMPTY
+    }
+
+    /// The pieces of `side` that attack `sq` when the board is occupied by `occupied`.
+    pub fn attackers(&self, sq: usize, side: &Side, occupied: Bitboard) -> Bitboard {
+        use PieceKind::*;
+
+        let queens = self.kind_bitboard(&Queen);
+        let diagonal = self.kind_bitboard(&Bishop) | queens;
+        let orthogonal = self.kind_bitboard(&Rook) | queens;
+
+        // a pawn of `side` attacks `sq` from where a pawn of the other side on `sq` would attack
+        let attackers = (pawn_attacks(sq, &side.opposite()) & self.kind_bitboard(&Pawn))
+            | (knight_attacks(sq) & self.kind_bitboard(&Knight))
+            | (king_attacks(sq) & self.kind_bitboard(&King))
+            | (bishop_attacks(sq, occupied) & diagonal)
+            | (rook_attacks(sq, occupied) & orthogonal);
+
+        attackers & self.side_bitboard(side)
+    }
+
+    /// Counts the leaf nodes of the legal move tree `depth` plies deep.
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = self.legal_moves();
+
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        let mut nodes = 0;
+
+        for mv in moves {
+            self.play(mv);
+            nodes += self.perft(depth - 1);
+            self.unmake_move();
+        }
+
+        nodes
+    }
+
+    /// Like `perft`, but returns the node count below each legal move.
+    pub fn divide(&mut self, depth: u32) -> Vec<(ChessMove, u64)> {
+        let mut result = vec![];
+
+        if depth == 0 {
+            return result;
+        }
+
+        for mv in self.legal_moves() {
+            self.play(mv);
+            result.push((mv, self.perft(depth - 1)));
+            self.unmake_move();
+        }
+
+        result
+    }
+
+    pub fn has_legal_moves(&self) -> bool {
+        let pocket = self.pockets[self.turn.index()];
+
+        squares(self.side_bitboard(&self.turn)).any(|sq| match self.piece_at(sq) {
+            Some(p) => self.generate_move_bitboard(sq, p) != EMPTY,
+            None => false,
+        }) || pocket.kinds().any(|kind| self.drop_targets(&kind) != EMPTY)
+    }
+
+    /// Returns how the game ended, or `None` if it's still going.
+    pub fn outcome(&self) -> Option<Outcome> {
+        if let Some(outcome) = self.variant_outcome() {
+            return Some(outcome);
+        }
+
+        if !self.has_legal_moves() {
+            return match (self.variant, self.is_check(&self.turn)) {
+                (Variant::Antichess, _) => Some(Outcome::NoMovesLeft(self.turn.clone())),
+                (_, true) => Some(Outcome::Checkmate(self.turn.opposite())),
+                (_, false) => Some(Outcome::Stalemate),
+            };
+        }
+
+        if self.variant == Variant::Standard && self.is_insufficient_material() {
+            Some(Outcome::InsufficientMaterial)
+        } else if self.halfmove_clock >= 100 {
+            Some(Outcome::FiftyMoveRule)
+        } else if self.repetition_count() >= 3 {
+            Some(Outcome::ThreefoldRepetition)
+        } else {
+            None
+        }
+    }
+
+    // the ways of winning that only some variants have
+    pub(crate) fn variant_outcome(&self) -> Option<Outcome> {
+        use Variant::*;
+
+        let sides = [Side::White, Side::Black];
+
+        match self.variant {
+            ThreeCheck => sides
+                .iter()
+                .find(|side| self.checks[side.index()] >= 3)
+                .map(|side| Outcome::ThreeChecks(side.clone())),
+
+            KingOfTheHill => sides
+                .iter()
+                .find(|side| self.pieces_bitboard(&PieceKind::King, side) & CENTER != EMPTY)
+                .map(|side| Outcome::KingOfTheHill(side.clone())),
+
+            Atomic => sides
+                .iter()
+                .find(|side| self.king_square(side).is_none())
+                .map(|side| Outcome::KingExploded(side.opposite())),
+
+            Horde => match self.side_bitboard(&Side::White) {
+                EMPTY => Some(Outcome::HordeCaptured),
+                _ => None,
+            },
+
+            RacingKings => self.race_outcome(),
+            Standard | Antichess | Crazyhouse => None,
+        }
+    }
+
+    fn race_outcome(&self) -> Option<Outcome> {
+        let on_last_rank = |side: &Side| match self.king_square(side) {
+            Some(sq) => sq.y() == 7,
+            None => false,
+        };
+
+        match (on_last_rank(&Side::White), on_last_rank(&Side::Black)) {
+            (true, true) => Some(Outcome::RaceTied),
+            (false, true) => Some(Outcome::RaceWon(Side::Black)),
+            // black gets one more move to draw level
+            (true, false) if self.turn == Side::Black => {
+                let king = Square::from_index_unchecked(self.king_square(&Side::Black)?);
+
+                match self
+                    .legal_moves_from(king)
+                    .iter()
+                    .any(|mv| mv.to().rank() == 7)
+                {
+                    true => None,
+                    false => Some(Outcome::RaceWon(Side::White)),
                 }
             }
+            (true, false) => Some(Outcome::RaceWon(Side::White)),
+            (false, false) => None,
+        }
+    }
+
+    /// How many times the current position has occurred, counting the current one.
+    pub fn repetition_count(&self) -> usize {
+        // positions before the last capture or pawn move can't repeat
+        let reversible = self.history.len().min(self.halfmove_clock as usize);
+
+        let previous = self.history[self.history.len() - reversible..]
+            .iter()
+            .filter(|undo| undo.hash == self.hash)
+            .count();
+
+        previous + 1
+    }
+
+    /// Neither side can checkmate with any series of legal moves:
+    /// lone kings, a single minor piece, or bishops that are all on squares of the same color.
+    pub fn is_insufficient_material(&self) -> bool {
+        use PieceKind::*;
+
+        let pieces: Vec<(usize, &Piece)> = self
+            .pieces
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, p)| p.as_ref().map(|p| (idx, p)))
+            .filter(|(_, p)| *p.kind() != King)
+            .collect();
+
+        match pieces.as_slice() {
+            [] => true,
+            [(_, p)] => *p.kind() == Knight || *p.kind() == Bishop,
+            pieces => {
+                let mut colors = pieces.iter().map(|(idx, p)| match p.kind() {
+                    Bishop => Some(get_square_color(*idx)),
+                    _ => None,
+                });
+
+                let first = colors.next().flatten();
+
+                first.is_some() && colors.all(|c| c == first)
+            }
         }
+    }
+
+    pub fn hash(&self) -> &u64 {
+        &self.hash
+    }
+
+    fn compute_hash(&self) -> u64 {
+        let mut hash = zobrist::side_key(&self.turn)
+            ^ zobrist::castling_key(&self.castling)
+            ^ self.en_passant_hash()
+            ^ self.pockets_hash();
 
         for (idx, piece) in self.pieces.iter().enumerate() {
-            if let Some(p) = piece.as_ref() {
-                if p.kind() == &PieceKind::King {
-                    continue;
-                }
+            if let Some(p) = piece {
+                hash ^= zobrist::piece_key(p, idx);
+            }
+        }
 
-                if p.side() == side {
-                    continue;
-                }
+        hash
+    }
 
-                if p.side() != side && self.generate_moves(idx, p).contains(&king.unwrap()) {
-                    return true;
-                }
+    fn pockets_hash(&self) -> u64 {
+        match self.variant.has_pockets() {
+            true => {
+                zobrist::pocket_key(&Side::White, &self.pockets[0])
+                    ^ zobrist::pocket_key(&Side::Black, &self.pockets[1])
             }
+            false => 0,
         }
+    }
 
-        false
+    // the en passant square only changes the position if a pawn can actually capture on it
+    fn en_passant_hash(&self) -> u64 {
+        let sq = match self.en_passant {
+            Some(sq) => sq,
+            None => return 0,
+        };
+
+        let pawns = self.pieces_bitboard(&PieceKind::Pawn, &self.turn);
+
+        match pawn_attacks(sq, &self.turn.opposite()) & pawns {
+            EMPTY => 0,
+            _ => zobrist::en_passant_key(sq.x()),
+        }
     }
 
     pub fn swap_turn(&mut self) {
-        self.turn = match self.turn {
-            Side::White => Side::Black,
-            Side::Black => Side::White,
-        };
+        self.hash ^= zobrist::side_key(&self.turn);
+        self.turn = self.turn.opposite();
+        self.hash ^= zobrist::side_key(&self.turn);
     }
 
-    pub fn fen(&self) -> String {
-        let mut string = String::new();
-        self.pieces.iter().enumerate().for_each(|(idx, piece)| {
-            if idx % 8 == 0 {
-                string.push('/');
-            } else {
-                if let Some(p) = piece {
-                    string.push_str(&format!("{}", p.as_ref()));
-                } else {
-                    string.push('e')
-                }
+    /// Whether moving the piece on `from` to `to` promotes a pawn.
+    pub fn is_promotion(&self, from: Square, to: Square) -> bool {
+        match self.piece_at(from.index()) {
+            Some(p) => *p.kind() == PieceKind::Pawn && (to.rank() == 0 || to.rank() == 7),
+            None => false,
+        }
+    }
+
+    pub fn en_passant(&self) -> Option<Square> {
+        self.en_passant.map(Square::from_index_unchecked)
+    }
+
+    /// Plays a legal move of the side to move.
+    pub fn make_move(&mut self, mv: ChessMove) -> Result<(), MoveError> {
+        match self.is_legal(&mv) {
+            true => {
+                self.play(mv);
+                Ok(())
             }
-        });
+            false => Err(MoveError::Illegal(mv.to_string())),
+        }
+    }
 
-        string
-    }
-
-    pub async fn submit_move(
-        &mut self,
-        source: usize,
-        dest: usize,
-        game_id: String,
-        token: String,
-        online: bool,
-    ) {
-        self.make_move(source, dest);
-        if online {
-            let (src, dest) = (idx_to_square(source), idx_to_square(dest));
-
-            let client = reqwest::Client::new();
-            let url = format!(
-                "https://lichess.org/api/board/game/{}/move/{}{}",
-                game_id, src, dest
-            );
+    // plays a move without checking that it's legal
+    pub(crate) fn play(&mut self, mv: ChessMove) {
+        if let Some(kind) = mv.dropped() {
+            return self.drop_piece(kind, mv);
+        }
+
+        let (source, dest) = (mv.from().index(), mv.to().index());
+
+        let piece = match self.piece_at(source) {
+            Some(p) => p.clone(),
+            None => return,
+        };
+
+        let castle = self
+            .castle_kind(&mv)
+            .and_then(|kind| self.castling.rook_square(&kind).map(|rook| (kind, rook)));
+
+        let is_en_passant = *piece.kind() == PieceKind::Pawn && self.en_passant == Some(dest);
+
+        // in Chess960 castling the king "captures" its own rook
+        let captured_sq = match (is_en_passant, &castle) {
+            (true, _) => Some(source.y() * 8 + dest.x()),
+            (false, Some(_)) => None,
+            (false, None) => Some(dest),
+        };
+
+        let captured = captured_sq.and_then(|sq| self.piece_at(sq).clone());
+        let capture = captured.is_some();
 
-            let token = format!("Bearer {}", token);
+        // a captured piece that was promoted goes to the pocket as a pawn
+        let pocketed = match (&captured, captured_sq) {
+            (Some(_), Some(sq)) if contains(self.promoted, sq) => Some(PieceKind::Pawn),
+            (Some(p), _) => Some(*p.kind()),
+            _ => None,
+        };
+
+        self.push_undo(captured);
 
-            client
-                .post(url)
-                .header("Authorization", token)
-                .send()
-                .await
-                .unwrap();
+        if *piece.kind() == PieceKind::Pawn || capture {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
         }
-    }
 
-    pub fn make_move(&mut self, source: usize, dest: usize) {
-        let mut piece = self.piece_at(source).clone().unwrap();
+        if let Some(kind) = pocketed.filter(|_| self.variant.has_pockets()) {
+            self.pockets[self.turn.index()].add(&kind);
+        }
 
-        let (src_str, dest_str) = (idx_to_square(source), idx_to_square(dest));
+        let was_promoted = contains(self.promoted, source);
+        self.promoted &= !bit(source) & !bit(dest);
 
-        if piece.kind() == &PieceKind::King && *piece.move_count() == 0 {
-            let idx = dest as isize - source as isize;
+        self.castling.update(source);
+        self.castling.update(dest);
 
-            if idx == 2 || idx == -2 {
-                let long = idx < 2;
+        if let Some((kind, rook)) = castle {
+            self.castle(&kind, mv, rook);
+        } else if *piece.kind() == PieceKind::Pawn && (dest.y() == 7 || dest.y() == 0) {
+            self.promote_piece(source, dest, mv.promotion().unwrap_or(PieceKind::Queen));
+            self.promoted |= bit(dest);
+        } else {
+            let mut kind = PlayedMoveKind::Normal;
+
+            if *piece.kind() == PieceKind::Pawn {
+                if is_en_passant {
+                    // the bypassed pawn sits next to the source square, on the destination file
+                    self.set_piece(source.y() * 8 + dest.x(), None);
+                    kind = PlayedMoveKind::EnPassant;
+                } else if (dest as isize - source as isize).abs() == 16 && source.y() != 0 {
+                    // Horde pawns pushed two squares from the first rank can't be taken en passant
+                    self.en_passant = Some((source + dest) / 2);
+                }
+            }
 
-                self.castle(piece.side().clone(), long);
-                return;
+            if was_promoted {
+                self.promoted |= bit(dest);
             }
+
+            let played = PlayedMove::new(kind, ChessMove::new(mv.from(), mv.to(), None));
+
+            self.played_moves.push(played);
+
+            self.set_piece(dest, Some(piece));
+            self.set_piece(source, None);
         }
 
-        if *piece.kind() == PieceKind::Pawn && (dest.y() == 7 || dest.y() == 0) {
-            self.promote_piece(source, dest);
-            return;
+        if capture && self.variant == Variant::Atomic {
+            self.explode(dest);
         }
 
-        let mv = PlayedMove::new(PlayedMoveKind::Normal, format!("{}{}", src_str, dest_str));
+        self.finish_move();
+    }
 
-        self.played_moves.push(mv);
+    // puts a piece from the pocket of the side to move on the board
+    fn drop_piece(&mut self, kind: PieceKind, mv: ChessMove) {
+        self.push_undo(None);
+
+        if kind == PieceKind::Pawn {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
 
-        piece.increment_moves();
+        self.pockets[self.turn.index()].remove(&kind);
+        self.set_piece(mv.to().index(), Some(Piece::new(kind, self.turn.clone())));
 
-        self.set_piece(dest, Some(piece));
-        self.set_piece(source, None);
+        self.played_moves
+            .push(PlayedMove::new(PlayedMoveKind::Drop(kind), mv));
 
+        self.finish_move();
+    }
+
+    // saves the state the next move changes, and hashes out the parts of it that are hashed back
+    // in by `finish_move`
+    fn push_undo(&mut self, captured: Option<Piece>) {
+        let state_hash =
+            zobrist::castling_key(&self.castling) ^ self.en_passant_hash() ^ self.pockets_hash();
+
+        self.history.push(Undo {
+            captured,
+            exploded: vec![],
+            checks: self.checks,
+            pockets: self.pockets,
+            promoted: self.promoted,
+            en_passant: self.en_passant.take(),
+            castling: self.castling.clone(),
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash,
+        });
+
+        self.hash ^= state_hash;
+
+        if self.turn == Side::Black {
+            self.fullmove_number += 1;
+        }
+    }
+
+    fn finish_move(&mut self) {
         self.swap_turn();
+        self.hash ^=
+            zobrist::castling_key(&self.castling) ^ self.en_passant_hash() ^ self.pockets_hash();
+
+        if self.variant == Variant::ThreeCheck && self.is_check(&self.turn) {
+            self.checks[self.turn.opposite().index()] += 1;
+        }
     }
 
-    fn make_move_raw(&mut self, source: usize, dest: usize, promotion: bool) {
-        let mut piece = self.piece_at(source).clone().unwrap();
-        piece.decrement_moves();
+    // an Atomic capture blows up the capturing piece along with every piece but pawns around it
+    fn explode(&mut self, sq: usize) {
+        let blast: Vec<usize> = squares(king_attacks(sq) | bit(sq))
+            .filter(|idx| match self.piece_at(*idx) {
+                Some(p) => *idx == sq || *p.kind() != PieceKind::Pawn,
+                None => false,
+            })
+            .collect();
+
+        let mut exploded = vec![];
+
+        for idx in blast {
+            if let Some(piece) = self.piece_at(idx).clone() {
+                self.set_piece(idx, None);
+                self.castling.update(idx);
+                exploded.push((idx, piece));
+            }
+        }
 
-        if promotion {
-            let new_piece = Piece::new(PieceKind::Pawn, piece.side().clone());
-            self.set_piece(dest, Some(new_piece));
-        } else {
-            self.set_piece(dest, Some(piece));
+        if let Some(undo) = self.history.last_mut() {
+            undo.exploded = exploded;
         }
+    }
 
-        self.set_piece(source, None);
+    pub fn variant(&self) -> &Variant {
+        &self.variant
     }
 
-    pub fn castle(&mut self, side: Side, long: bool) {
-        let king_idx = match side {
-            Side::White => 4,
-            Side::Black => 60,
-        };
+    /// How many times `side` has given check, which only gets counted in Three-check.
+    pub fn checks_given(&self, side: &Side) -> u8 {
+        self.checks[side.index()]
+    }
 
-        let rook_idx = match side {
-            Side::Black => match long {
-                true => 56,
-                false => 63,
-            },
-            Side::White => match long {
-                true => 0,
-                false => 7,
-            },
+    /// The Crazyhouse pocket of `side`, which is always empty in other variants.
+    pub fn pocket(&self, side: &Side) -> &Pocket {
+        &self.pockets[side.index()]
+    }
+
+    /// Replaces both pockets, e.g. with the ones Lichess reports for a Crazyhouse game.
+    pub fn set_pockets(&mut self, pockets: [Pocket; 2]) {
+        self.hash ^= self.pockets_hash();
+        self.pockets = pockets;
+        self.hash ^= self.pockets_hash();
+    }
+
+    /// Whether the rook and the king are in place for a castling move the side still has the right to.
+    /// Doesn't check that the squares in between are empty or safe, see `generate_king_moves`.
+    pub fn can_castle(&self, kind: &CastleKind) -> bool {
+        if !self.castling.has(kind) {
+            return false;
+        }
+
+        let side = kind.side();
+
+        let in_place = |sq: usize, piece_kind: PieceKind| match self.piece_at(sq) {
+            Some(p) => *p.kind() == piece_kind && *p.side() == side,
+            None => false,
         };
 
-        let dest_squares: (usize, usize) = match side {
-            Side::White => match long {
-                true => (2, 3),
-                false => (6, 5),
-            },
-            Side::Black => match long {
-                true => (58, 59),
-                false => (62, 61),
-            },
+        match self.castling.rook_square(kind) {
+            Some(rook) => {
+                in_place(self.castling.king_square(kind), PieceKind::King)
+                    && in_place(rook, PieceKind::Rook)
+            }
+            None => false,
+        }
+    }
+
+    /// The castling move `mv` stands for, if any. That's the king moving two files, or taking its
+    /// own rook in Chess960.
+    pub fn castle_kind(&self, mv: &ChessMove) -> Option<CastleKind> {
+        let (source, dest) = (mv.from().index(), mv.to().index());
+
+        let piece = match self.piece_at(source) {
+            Some(p) if *p.kind() == PieceKind::King => p,
+            _ => return None,
         };
 
-        let mut king = self.piece_at(king_idx).clone().unwrap();
-        let mut rook = self.piece_at(rook_idx).clone().unwrap();
+        let kind = CastleKind::new(piece.side(), dest < source);
 
-        king.increment_moves();
-        rook.increment_moves();
+        let is_castle = match self.chess960 {
+            true => self.castling.rook_square(&kind) == Some(dest),
+            false => source.y() == dest.y() && (dest as isize - source as isize).abs() == 2,
+        };
 
-        self.set_piece(dest_squares.0, Some(king));
-        self.set_piece(king_idx, None);
+        match is_castle && self.can_castle(&kind) {
+            true => Some(kind),
+            false => None,
+        }
+    }
 
-        self.set_piece(dest_squares.1, Some(rook));
-        self.set_piece(rook_idx, None);
+    /// The move that castles `kind` in the notation the board uses, see `castle_kind`.
+    /// It isn't necessarily legal.
+    pub fn castle_move(&self, kind: &CastleKind) -> Option<ChessMove> {
+        let rook = self.castling.rook_square(kind)?;
+        let king = self.castling.king_square(kind);
 
-        let castle_kind = match (side, long) {
-            (Side::White, true) => CastleKind::WhiteLong,
-            (Side::White, false) => CastleKind::WhiteShort,
-            (Side::Black, true) => CastleKind::BlackLong,
-            (Side::Black, false) => CastleKind::BlackShort,
+        let dest = match self.chess960 {
+            true => rook,
+            false => kind.destination_squares().0,
         };
 
-        let mv = PlayedMove::new(
-            PlayedMoveKind::Castle(castle_kind),
-            format!(
-                "{}{}",
-                idx_to_square(king_idx),
-                idx_to_square(dest_squares.0)
-            ),
+        Some(ChessMove::new(
+            Square::from_index_unchecked(king),
+            Square::from_index_unchecked(dest),
+            None,
+        ))
+    }
+
+    /// Whether castling is written as the king taking its own rook, as it is in Chess960.
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+    }
+
+    fn castle(&mut self, kind: &CastleKind, mv: ChessMove, rook_idx: usize) {
+        let king_idx = mv.from().index();
+        let (king_dest, rook_dest) = kind.destination_squares();
+
+        let (king, rook) = (
+            self.piece_at(king_idx).clone(),
+            self.piece_at(rook_idx).clone(),
         );
 
-        self.played_moves.push(mv);
+        self.set_piece(king_idx, None);
+        self.set_piece(rook_idx, None);
 
-        self.swap_turn();
+        self.set_piece(king_dest, king);
+        self.set_piece(rook_dest, rook);
+
+        let mv = ChessMove::new(mv.from(), mv.to(), None);
+        let mv = PlayedMove::new(PlayedMoveKind::Castle(kind.clone()), mv);
+
+        self.played_moves.push(mv);
     }
 
-    pub fn promote_piece(&mut self, source: usize, dest: usize) {
+    fn promote_piece(&mut self, source: usize, dest: usize, kind: PieceKind) {
         if let Some(p) = self.piece_at(source) {
             if *p.kind() != PieceKind::Pawn {
                 return;
             }
-            let new_piece = Piece::new(PieceKind::Queen, p.side().clone());
 
-            drop(p);
+            let kind = match self.variant.promotion_kinds().contains(&kind) {
+                true => kind,
+                false => PieceKind::Queen,
+            };
+
+            let new_piece = Piece::new(kind, p.side().clone());
 
             self.set_piece(dest, Some(new_piece));
             self.set_piece(source, None);
 
-            let (src, dst) = (idx_to_square(source), idx_to_square(dest));
-            let mv = PlayedMove::new(PlayedMoveKind::Promotion, format!("{}{}q", src, dst));
-            self.played_moves.push(mv);
+            let mv = ChessMove::new(
+                Square::from_index_unchecked(source),
+                Square::from_index_unchecked(dest),
+                Some(kind),
+            );
 
-            self.swap_turn();
+            let mv = PlayedMove::new(PlayedMoveKind::Promotion(kind), mv);
+            self.played_moves.push(mv);
         }
     }
 
+    // the only place pieces are put on or taken off the board, keeping the bitboards and the hash in sync
     fn set_piece(&mut self, dest: usize, piece: Option<Piece>) {
-        let p = self.pieces.get_mut(dest).unwrap();
-        *p = piece;
+        if let Some(old) = self.pieces[dest].take() {
+            self.by_kind[old.kind().index()] &= !bit(dest);
+            self.by_side[old.side().index()] &= !bit(dest);
+            self.hash ^= zobrist::piece_key(&old, dest);
+        }
+
+        if let Some(new) = piece.as_ref() {
+            self.by_kind[new.kind().index()] |= bit(dest);
+            self.by_side[new.side().index()] |= bit(dest);
+            self.hash ^= zobrist::piece_key(new, dest);
+        }
+
+        self.pieces[dest] = piece;
     }
 
     pub fn piece_at(&self, square: usize) -> &Option<Piece> {
-        if square > 63 {
-            return &None;
+        match self.pieces.get(square) {
+            Some(piece) => piece,
+            None => &None,
         }
-
-        self.pieces.get(square).unwrap()
     }
 
     pub fn get_row(square: usize) -> usize {
@@ -415,21 +1137,45 @@ impl Board {
         &self.pieces
     }
 
-    pub fn pieces_mut(&mut self) -> &mut Vec<Option<Piece>> {
-        &mut self.pieces
+    pub fn occupied(&self) -> Bitboard {
+        self.by_side[0] | self.by_side[1]
+    }
+
+    pub fn side_bitboard(&self, side: &Side) -> Bitboard {
+        self.by_side[side.index()]
+    }
+
+    pub fn kind_bitboard(&self, kind: &PieceKind) -> Bitboard {
+        self.by_kind[kind.index()]
+    }
+
+    pub fn pieces_bitboard(&self, kind: &PieceKind, side: &Side) -> Bitboard {
+        self.kind_bitboard(kind) & self.side_bitboard(side)
+    }
+
+    pub fn castling(&self) -> &CastlingRights {
+        &self.castling
+    }
+
+    pub fn halfmove_clock(&self) -> &u32 {
+        &self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> &u32 {
+        &self.fullmove_number
     }
 
     pub fn turn(&self) -> &Side {
         &self.turn
     }
 
-    pub fn set_generated_moves(&mut self, moves: Vec<usize>) {
+    pub fn set_generated_moves(&mut self, moves: Vec<ChessMove>) {
         self.current_generated_moves = moves;
     }
 }
 
 impl Default for Board {
     fn default() -> Self {
-        Self::from_str("RNBQKBNR/PPPPPPPP/8/8/8/8/pppppppp/rnbqkbnr", Side::White)
+        Self::new(Variant::Standard)
     }
 }
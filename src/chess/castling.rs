@@ -0,0 +1,261 @@
+use super::{CastleKind, Piece, PieceKind, Side};
+
+impl CastleKind {
+    pub fn new(side: &Side, long: bool) -> Self {
+        match (side, long) {
+            (Side::White, true) => CastleKind::WhiteLong,
+            (Side::White, false) => CastleKind::WhiteShort,
+            (Side::Black, true) => CastleKind::BlackLong,
+            (Side::Black, false) => CastleKind::BlackShort,
+        }
+    }
+
+    /// The short and the long castling move of `side`, in that order.
+    pub fn for_side(side: &Side) -> [Self; 2] {
+        [Self::new(side, false), Self::new(side, true)]
+    }
+
+    pub fn side(&self) -> Side {
+        use CastleKind::*;
+
+        match self {
+            WhiteLong | WhiteShort => Side::White,
+            BlackLong | BlackShort => Side::Black,
+        }
+    }
+
+    pub fn is_long(&self) -> bool {
+        matches!(self, CastleKind::WhiteLong | CastleKind::BlackLong)
+    }
+
+    /// The squares the king and the rook end up on, in that order.
+    /// These don't depend on where they start, which is what makes Chess960 castling work.
+    pub fn destination_squares(&self) -> (usize, usize) {
+        use CastleKind::*;
+
+        match self {
+            WhiteLong => (2, 3),
+            WhiteShort => (6, 5),
+            BlackLong => (58, 59),
+            BlackShort => (62, 61),
+        }
+    }
+
+    fn back_rank(&self) -> usize {
+        match self.side() {
+            Side::White => 0,
+            Side::Black => 56,
+        }
+    }
+}
+
+/// The castling rights of both sides. Each right remembers the file of its rook and the file the
+/// king castles from, so the king and the rooks may start anywhere on the back rank, as in Chess960.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CastlingRights {
+    white_short: Option<usize>,
+    white_long: Option<usize>,
+    black_short: Option<usize>,
+    black_long: Option<usize>,
+    king_files: [usize; 2],
+}
+
+impl Default for CastlingRights {
+    fn default() -> Self {
+        Self {
+            white_short: None,
+            white_long: None,
+            black_short: None,
+            black_long: None,
+            king_files: [4, 4],
+        }
+    }
+}
+
+impl CastlingRights {
+    /// Every right of the standard starting position.
+    pub fn all() -> Self {
+        Self {
+            white_short: Some(7),
+            white_long: Some(0),
+            black_short: Some(7),
+            black_long: Some(0),
+            king_files: [4, 4],
+        }
+    }
+
+    pub fn has(&self, kind: &CastleKind) -> bool {
+        self.rook_file(kind).is_some()
+    }
+
+    /// The file of the rook `kind` castles with, if the right hasn't been lost.
+    pub fn rook_file(&self, kind: &CastleKind) -> Option<usize> {
+        use CastleKind::*;
+
+        match kind {
+            WhiteShort => self.white_short,
+            WhiteLong => self.white_long,
+            BlackShort => self.black_short,
+            BlackLong => self.black_long,
+        }
+    }
+
+    pub fn rook_square(&self, kind: &CastleKind) -> Option<usize> {
+        self.rook_file(kind).map(|file| kind.back_rank() + file)
+    }
+
+    pub fn king_square(&self, kind: &CastleKind) -> usize {
+        kind.back_rank() + self.king_files[kind.side().index()]
+    }
+
+    fn set(&mut self, kind: &CastleKind, rook_file: Option<usize>) {
+        use CastleKind::*;
+
+        match kind {
+            WhiteShort => self.white_short = rook_file,
+            WhiteLong => self.white_long = rook_file,
+            BlackShort => self.black_short = rook_file,
+            BlackLong => self.black_long = rook_file,
+        }
+    }
+
+    /// Removes every right that depends on a piece staying on `square`.
+    /// Called with both the source and the destination square of each move,
+    /// which covers king moves, rook moves and rook captures.
+    pub fn update(&mut self, square: usize) {
+        use CastleKind::*;
+
+        for kind in [WhiteLong, WhiteShort, BlackLong, BlackShort].iter() {
+            if square == self.king_square(kind) || Some(square) == self.rook_square(kind) {
+                self.set(kind, None);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        use CastleKind::*;
+
+        [WhiteLong, WhiteShort, BlackLong, BlackShort]
+            .iter()
+            .all(|kind| !self.has(kind))
+    }
+
+    /// Whether castling starts from anywhere but the standard king and rook squares.
+    pub fn is_chess960(&self) -> bool {
+        use CastleKind::*;
+
+        let rooks_moved = [WhiteLong, WhiteShort, BlackLong, BlackShort]
+            .iter()
+            .any(|kind| match self.rook_file(kind) {
+                Some(file) => file != if kind.is_long() { 0 } else { 7 },
+                None => false,
+            });
+
+        rooks_moved || (!self.is_empty() && self.king_files != [4, 4])
+    }
+
+    /// Parses the castling field of a FEN string against the pieces on the board.
+    /// Accepts `KQkq` or `-`, the rook files of Shredder-FEN such as `HAha`, and X-FEN, which
+    /// mixes the two: `K` and `Q` stand for the outermost rook on that side of the king.
+    pub fn from_fen(field: &str, pieces: &[Option<Piece>]) -> Option<Self> {
+        let mut rights = Self::default();
+
+        if field == "-" {
+            return Some(rights);
+        }
+
+        for c in field.chars() {
+            let side = match c.is_ascii_uppercase() {
+                true => Side::White,
+                false => Side::Black,
+            };
+
+            let rank = match side {
+                Side::White => 0,
+                Side::Black => 56,
+            };
+
+            let is_own = |file: &usize, kind: PieceKind| match pieces.get(rank + file) {
+                Some(Some(p)) => *p.kind() == kind && *p.side() == side,
+                _ => false,
+            };
+
+            let king_file = (0..8).find(|file| is_own(file, PieceKind::King))?;
+            let is_rook = |file: &usize| is_own(file, PieceKind::Rook);
+
+            let (long, rook_file) = match c.to_ascii_lowercase() {
+                'k' => (false, (king_file + 1..8).rev().find(is_rook)?),
+                'q' => (true, (0..king_file).find(is_rook)?),
+                file @ 'a'..='h' => {
+                    let file = file as usize - 'a' as usize;
+
+                    if !is_rook(&file) {
+                        return None;
+                    }
+
+                    (file < king_file, file)
+                }
+                _ => return None,
+            };
+
+            let kind = CastleKind::new(&side, long);
+
+            if rights.has(&kind) {
+                return None;
+            }
+
+            rights.set(&kind, Some(rook_file));
+            rights.king_files[side.index()] = king_file;
+        }
+
+        match field.is_empty() {
+            true => None,
+            false => Some(rights),
+        }
+    }
+
+    /// Writes the rights in X-FEN: `K` and `Q` for the outermost rook on either side of the king,
+    /// which covers every standard position, and the rook's file for any other rook.
+    pub fn to_fen(&self, pieces: &[Option<Piece>]) -> String {
+        use CastleKind::*;
+
+        if self.is_empty() {
+            return "-".into();
+        }
+
+        let mut fen = String::new();
+
+        for kind in [WhiteShort, WhiteLong, BlackShort, BlackLong].iter() {
+            let file = match self.rook_file(kind) {
+                Some(file) => file,
+                None => continue,
+            };
+
+            let side = kind.side();
+            let king_file = self.king_files[side.index()];
+
+            let is_rook = |f: usize| match pieces.get(kind.back_rank() + f) {
+                Some(Some(p)) => *p.kind() == PieceKind::Rook && *p.side() == side,
+                _ => false,
+            };
+
+            let outermost = match kind.is_long() {
+                true => (0..king_file).find(|f| is_rook(*f)),
+                false => (king_file + 1..8).rev().find(|f| is_rook(*f)),
+            };
+
+            let c = match (outermost == Some(file), kind.is_long()) {
+                (true, false) => 'k',
+                (true, true) => 'q',
+                (false, _) => (b'a' + file as u8) as char,
+            };
+
+            match side {
+                Side::White => fen.push(c.to_ascii_uppercase()),
+                Side::Black => fen.push(c),
+            }
+        }
+
+        fen
+    }
+}
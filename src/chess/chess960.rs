@@ -0,0 +1,87 @@
+use super::{Board, PieceKind};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The back rank of Chess960 start position `n`, from the a-file to the h-file, in the standard
+/// numbering where position 518 is the regular starting position.
+pub fn back_rank(n: usize) -> Option<[PieceKind; 8]> {
+    use PieceKind::*;
+
+    // the pairs of free squares the knights go on, in order
+    const KNIGHTS: [(usize, usize); 10] = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ];
+
+    if n >= 960 {
+        return None;
+    }
+
+    let mut rank: [Option<PieceKind>; 8] = [None; 8];
+
+    let (n, light) = (n / 4, n % 4);
+    let (n, dark) = (n / 4, n % 4);
+    let (n, queen) = (n / 6, n % 6);
+
+    rank[light * 2 + 1] = Some(Bishop);
+    rank[dark * 2] = Some(Bishop);
+
+    let free = |rank: &[Option<PieceKind>; 8]| -> Vec<usize> {
+        (0..8).filter(|file| rank[*file].is_none()).collect()
+    };
+
+    rank[free(&rank)[queen]] = Some(Queen);
+
+    let empty = free(&rank);
+    let (first, second) = KNIGHTS[n];
+    rank[empty[first]] = Some(Knight);
+    rank[empty[second]] = Some(Knight);
+
+    // the king always ends up between the rooks
+    for (file, kind) in free(&rank).into_iter().zip([Rook, King, Rook]) {
+        rank[file] = Some(kind);
+    }
+
+    let mut pieces = [Pawn; 8];
+
+    for (file, kind) in rank.iter().enumerate() {
+        pieces[file] = (*kind)?;
+    }
+
+    Some(pieces)
+}
+
+impl Board {
+    /// Chess960 start position `n`, from 0 to 959.
+    pub fn chess960(n: usize) -> Option<Self> {
+        let rank: String = back_rank(n)?.iter().map(|kind| kind.to_char()).collect();
+
+        let fen = format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+            rank,
+            rank.to_uppercase()
+        );
+
+        let mut board = Self::from_fen(&fen).ok()?;
+        board.set_chess960(true);
+
+        Some(board)
+    }
+
+    /// A Chess960 start position picked at random.
+    pub fn random_chess960() -> Self {
+        let nanos = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(time) => time.subsec_nanos() as usize,
+            Err(_) => 518,
+        };
+
+        Self::chess960(nanos % 960).unwrap_or_default()
+    }
+}
@@ -0,0 +1,136 @@
+use super::{PieceKind, Square};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    InvalidSquare(String),
+    InvalidUci(String),
+    Illegal(String),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use MoveError::*;
+
+        match self {
+            InvalidSquare(sq) => write!(f, "invalid square '{}'", sq),
+            InvalidUci(uci) => write!(f, "invalid UCI move '{}'", uci),
+            Illegal(mv) => write!(f, "illegal move '{}'", mv),
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+/// A move from one square to another. `promotion` is the piece a pawn reaching the last rank turns
+/// into, a queen if it's left out.
+/// In Crazyhouse, a move can also drop a piece from the pocket, in which case `from` and `to` are
+/// both the square it's dropped on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChessMove {
+    from: Square,
+    to: Square,
+    promotion: Option<PieceKind>,
+    drop: Option<PieceKind>,
+}
+
+impl ChessMove {
+    pub fn new(from: Square, to: Square, promotion: Option<PieceKind>) -> Self {
+        Self {
+            from,
+            to,
+            promotion,
+            drop: None,
+        }
+    }
+
+    /// Drops a piece of `kind` from the pocket onto `to`.
+    pub fn new_drop(kind: PieceKind, to: Square) -> Self {
+        Self {
+            from: to,
+            to,
+            promotion: None,
+            drop: Some(kind),
+        }
+    }
+
+    pub fn from(&self) -> Square {
+        self.from
+    }
+
+    pub fn to(&self) -> Square {
+        self.to
+    }
+
+    pub fn promotion(&self) -> Option<PieceKind> {
+        self.promotion
+    }
+
+    /// The kind of piece the move drops, if it's a drop.
+    pub fn dropped(&self) -> Option<PieceKind> {
+        self.drop
+    }
+
+    pub fn is_drop(&self) -> bool {
+        self.drop.is_some()
+    }
+}
+
+/// Parses a move in UCI notation, e.g. `e2e4`, `e7e8n` or the drop `P@e4`.
+impl FromStr for ChessMove {
+    type Err = MoveError;
+
+    fn from_str(s: &str) -> Result<Self, MoveError> {
+        let invalid = || MoveError::InvalidUci(s.to_string());
+
+        if !s.is_ascii() || !(4..=5).contains(&s.len()) {
+            return Err(invalid());
+        }
+
+        if s.len() == 4 && &s[1..2] == "@" {
+            let kind = s
+                .chars()
+                .next()
+                .and_then(|c| PieceKind::from_char(c.to_ascii_lowercase()));
+
+            return match kind {
+                Some(PieceKind::King) | None => Err(invalid()),
+                Some(kind) => Ok(Self::new_drop(
+                    kind,
+                    s[2..4].parse().map_err(|_| invalid())?,
+                )),
+            };
+        }
+
+        let from = s[0..2].parse().map_err(|_| invalid())?;
+        let to = s[2..4].parse().map_err(|_| invalid())?;
+
+        let promotion = match s[4..].chars().next() {
+            Some(c) => match PieceKind::from_char(c) {
+                Some(PieceKind::Pawn) | None => return Err(invalid()),
+                kind => kind,
+            },
+            None => None,
+        };
+
+        Ok(Self::new(from, to, promotion))
+    }
+}
+
+impl fmt::Display for ChessMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(kind) = self.drop {
+            return write!(f, "{}@{}", kind.to_char().to_ascii_uppercase(), self.to);
+        }
+
+        write!(f, "{}{}", self.from, self.to)?;
+
+        if let Some(kind) = self.promotion {
+            write!(f, "{}", kind.to_char())?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use super::{Piece, PieceKind, Pocket, Side, Variant};
+use crate::chess::bitboard::{bit, contains, Bitboard, EMPTY};
+use crate::chess::board::Coords;
+use crate::chess::utils::idx_to_square;
+use std::error::Error;
+use std::fmt;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    MissingField(&'static str),
+    TooManyFields,
+    InvalidRankCount(usize),
+    InvalidRank(String),
+    InvalidPiece(char),
+    InvalidPocket(String),
+    InvalidKings,
+    PawnOnBackRank,
+    InvalidSide(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidClock(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use FenError::*;
+
+        match self {
+            MissingField(field) => write!(f, "missing {} field", field),
+            TooManyFields => write!(f, "too many fields"),
+            InvalidRankCount(count) => write!(f, "expected 8 ranks, found {}", count),
+            InvalidRank(rank) => write!(f, "rank '{}' does not describe 8 squares", rank),
+            InvalidPiece(c) => write!(f, "invalid piece '{}'", c),
+            InvalidPocket(pocket) => write!(f, "invalid pocket '{}'", pocket),
+            InvalidKings => write!(f, "wrong number of kings for the variant"),
+            PawnOnBackRank => write!(f, "pawns cannot be placed on the first or last rank"),
+            InvalidSide(side) => write!(f, "invalid side to move '{}'", side),
+            InvalidCastling(castling) => write!(f, "invalid castling rights '{}'", castling),
+            InvalidEnPassant(sq) => write!(f, "invalid en passant square '{}'", sq),
+            InvalidClock(clock) => write!(f, "invalid move counter '{}'", clock),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+/// Splits the Crazyhouse pockets, written as `[QPn]` after the pieces, off the placement field.
+/// The pockets are empty if the field has none.
+pub fn split_pockets(field: &str) -> Result<(&str, [Pocket; 2]), FenError> {
+    let (placement, pockets) = match field.find('[') {
+        Some(idx) => (&field[..idx], &field[idx..]),
+        None => return Ok((field, [Pocket::default(); 2])),
+    };
+
+    let pockets = pockets
+        .strip_prefix('[')
+        .and_then(|pockets| pockets.strip_suffix(']'))
+        .and_then(Pocket::parse_pair)
+        .ok_or_else(|| FenError::InvalidPocket(pockets.into()))?;
+
+    Ok((placement, pockets))
+}
+
+/// Parses the piece placement field, returning the pieces indexed from a1 to h8 and the squares of
+/// the pieces marked as promoted pawns with a `~`, as in Crazyhouse.
+/// The kings and pawns are checked against the rules of `variant`.
+pub fn parse_placement(
+    field: &str,
+    variant: Variant,
+) -> Result<(Vec<Option<Piece>>, Bitboard), FenError> {
+    let ranks: Vec<&str> = field.split('/').collect();
+
+    if ranks.len() != 8 {
+        return Err(FenError::InvalidRankCount(ranks.len()));
+    }
+
+    let mut pieces = vec![None; 64];
+    let mut promoted = EMPTY;
+
+    // FEN lists the ranks from the 8th down to the 1st
+    for (rank_idx, rank) in ranks.iter().rev().enumerate() {
+        let mut file = 0;
+        let mut last_piece = None;
+
+        for c in rank.chars() {
+            if c == '~' {
+                match last_piece.take() {
+                    Some(sq) => promoted |= bit(sq),
+                    None => return Err(FenError::InvalidRank(rank.to_string())),
+                }
+
+                continue;
+            }
+
+            last_piece = None;
+
+            if let Some(empty) = c.to_digit(10) {
+                if empty == 0 || empty > 8 {
+                    return Err(FenError::InvalidRank(rank.to_string()));
+                }
+
+                file += empty as usize;
+                continue;
+            }
+
+            let piece = Piece::from_char(c).ok_or(FenError::InvalidPiece(c))?;
+
+            if file >= 8 {
+                return Err(FenError::InvalidRank(rank.to_string()));
+            }
+
+            pieces[rank_idx * 8 + file] = Some(piece);
+            last_piece = Some(rank_idx * 8 + file);
+            file += 1;
+        }
+
+        if file != 8 {
+            return Err(FenError::InvalidRank(rank.to_string()));
+        }
+    }
+
+    for side in [Side::White, Side::Black].iter() {
+        let kings = pieces
+            .iter()
+            .flatten()
+            .filter(|p| p.kind() == &PieceKind::King && p.side() == side)
+            .count();
+
+        if variant.king_count(side).is_some_and(|count| count != kings) {
+            return Err(FenError::InvalidKings);
+        }
+    }
+
+    // the horde starts with pawns on the first rank
+    let pawn_on_back_rank = pieces.iter().enumerate().any(|(idx, p)| match p {
+        Some(p) if variant == Variant::Horde && *p.side() == Side::White => {
+            p.kind() == &PieceKind::Pawn && idx.y() == 7
+        }
+        Some(p) => p.kind() == &PieceKind::Pawn && (idx.y() == 0 || idx.y() == 7),
+        None => false,
+    });
+
+    if pawn_on_back_rank {
+        return Err(FenError::PawnOnBackRank);
+    }
+
+    Ok((pieces, promoted))
+}
+
+pub fn parse_side(field: &str) -> Result<Side, FenError> {
+    match field {
+        "w" => Ok(Side::White),
+        "b" => Ok(Side::Black),
+        _ => Err(FenError::InvalidSide(field.into())),
+    }
+}
+
+/// Parses the en passant field. The target square must be on the 3rd rank when black is to move,
+/// and on the 6th rank when white is.
+pub fn parse_en_passant(field: &str, turn: &Side) -> Result<Option<usize>, FenError> {
+    if field == "-" {
+        return Ok(None);
+    }
+
+    let err = || FenError::InvalidEnPassant(field.into());
+
+    let mut chars = field.chars();
+
+    let (file, rank) = match (chars.next(), chars.next(), chars.next()) {
+        (Some(file @ 'a'..='h'), Some(rank), None) => (file, rank),
+        _ => return Err(err()),
+    };
+
+    let expected_rank = match turn {
+        Side::White => '6',
+        Side::Black => '3',
+    };
+
+    if rank != expected_rank {
+        return Err(err());
+    }
+
+    let file = file as usize - 'a' as usize;
+    let rank = rank.to_digit(10).unwrap() as usize - 1;
+
+    Ok(Some(rank * 8 + file))
+}
+
+pub fn parse_clock(field: &str) -> Result<u32, FenError> {
+    field
+        .parse::<u32>()
+        .map_err(|_| FenError::InvalidClock(field.into()))
+}
+
+/// Writes the piece placement field for pieces indexed from a1 to h8, marking the `promoted` ones.
+pub fn placement_to_fen(pieces: &[Option<Piece>], promoted: Bitboard) -> String {
+    let mut ranks = vec![];
+
+    for rank in (0..8).rev() {
+        let mut string = String::new();
+        let mut empty = 0;
+
+        for file in 0..8 {
+            match &pieces[rank * 8 + file] {
+                Some(p) => {
+                    if empty > 0 {
+                        string.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+
+                    string.push_str(&p.render_char());
+
+                    if contains(promoted, rank * 8 + file) {
+                        string.push('~');
+                    }
+                }
+                None => empty += 1,
+            }
+        }
+
+        if empty > 0 {
+            string.push_str(&empty.to_string());
+        }
+
+        ranks.push(string);
+    }
+
+    ranks.join("/")
+}
+
+pub fn en_passant_to_fen(en_passant: &Option<usize>) -> String {
+    match en_passant {
+        Some(sq) => idx_to_square(*sq),
+        None => "-".into(),
+    }
+}
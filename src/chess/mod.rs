@@ -1,41 +1,43 @@
 pub mod board;
-pub use board::{Board, Square};
+pub use board::{Board, Coords};
+
+pub mod square;
+pub use square::Square;
+
+pub mod chess_move;
+pub use chess_move::{ChessMove, MoveError};
+
+pub mod castling;
+pub use castling::CastlingRights;
+
+pub mod chess960;
+
+pub mod fen;
+pub use fen::FenError;
+
+pub mod san;
+pub use san::SanError;
+
+pub mod outcome;
+pub use outcome::Outcome;
+
+pub mod variant;
+pub use variant::Variant;
+
+pub mod pocket;
+pub use pocket::Pocket;
 
 pub mod piece;
 pub use piece::{Piece, PieceKind, Side};
 
+pub mod bitboard;
 pub mod moves;
 pub mod utils;
+pub mod zobrist;
 
 pub mod played_move;
 pub use played_move::{PlayedMove, PlayedMoveKind};
 
-pub const WHITE_KING_DEFAULT_POS: usize = 4;
-pub const BLACK_KING_DEFAULT_POS: usize = 60;
-
-pub struct Move<'a> {
-    pub x: isize,
-    pub y: isize,
-    pub constraints: &'a [MoveConstraint],
-}
-
-impl<'a> Move<'a> {
-    pub fn invert_coordinates(&self) -> Self {
-        Self {
-            x: -self.x,
-            y: -self.y,
-            constraints: self.constraints.clone(),
-        }
-    }
-}
-
-#[derive(Debug, Clone)]
-pub enum MoveConstraint {
-    MaxMoves(usize),
-    Castling,
-    PieceOnTargetSquare,
-}
-
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CastleKind {
     WhiteLong,
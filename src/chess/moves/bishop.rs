@@ -1,152 +1,12 @@
-use crate::chess::{board::Edge, Board, Move, Piece, Side, Square};
-use std::cmp::Ordering;
+use crate::chess::bitboard::{ray_attacks, Bitboard, Direction};
+use crate::chess::{Board, Piece};
 
-pub const BISHOP_MOVES: &[Move] = &[
-    Move {
-        x: 8,
-        y: 8,
-        constraints: &[],
-    },
-    Move {
-        x: -8,
-        y: 8,
-        constraints: &[],
-    },
-    Move {
-        x: 8,
-        y: -8,
-        constraints: &[],
-    },
-    Move {
-        x: -8,
-        y: -8,
-        constraints: &[],
-    },
-];
-
-pub fn generate_bishop_moves(board: &Board, sq: usize, piece: &Piece) -> Vec<usize> {
-    let mut moves = vec![];
-
-    'moves: for mv in BISHOP_MOVES.iter() {
-        let (x, y) = match piece.side() {
-            Side::White => (mv.x, mv.y),
-            Side::Black => {
-                let mv = mv.invert_coordinates();
-                (mv.x, mv.y)
-            }
-        };
-
-        match y.cmp(&0) {
-            Ordering::Greater => match x.cmp(&0) {
-                Ordering::Greater => {
-                    if sq.y() == 7 || sq.x() == 7 {
-                        continue;
-                    }
-
-                    for idx in 1..=8 {
-                        let square = sq + (idx * 8 + idx) as usize;
-
-                        if let Some(ref p) = board.piece_at(square) {
-                            if p.side() != piece.side() {
-                                moves.push(square);
-                            }
-
-                            continue 'moves;
-                        }
-
-                        if square.y() == 7 || square.x() == 7 {
-                            moves.push(square);
-                            continue 'moves;
-                        }
-
-                        if square < 63 {
-                            moves.push(square);
-                        }
-                    }
-                }
-                Ordering::Less => {
-                    if sq.y() == 7 || sq.x() == 0 {
-                        continue;
-                    }
-
-                    for idx in 1..=8 {
-                        let square = sq + (idx * 8 - idx) as usize;
-
-                        if let Some(ref p) = board.piece_at(square) {
-                            if p.side() != piece.side() {
-                                moves.push(square);
-                            }
-
-                            continue 'moves;
-                        }
-
-                        if square.y() == 0 || square.x() == 0 {
-                            moves.push(square);
-                            continue 'moves;
-                        }
-                        if square > 0 {
-                            moves.push(square);
-                        }
-                    }
-                }
-                _ => (),
-            },
-            Ordering::Less => match x.cmp(&0) {
-                Ordering::Greater => {
-                    if sq.y() == 0 || sq.x() == 7 {
-                        continue;
-                    }
-
-                    for idx in 1..=sq.y() {
-                        let square = sq - (idx as usize * 8) + idx;
-
-                        if let Some(ref p) = board.piece_at(square) {
-                            if p.side() != piece.side() {
-                                moves.push(square);
-                            }
-
-                            continue 'moves;
-                        }
-
-                        if square.y() == 7 || square.x() == 7 {
-                            moves.push(square);
-                            continue 'moves;
-                        }
-
-                        if square > 0 && square < 63 {
-                            moves.push(square);
-                        }
-                    }
-                }
-                Ordering::Less => {
-                    if sq.y() == 0 || sq.x() == 0 {
-                        continue;
-                    }
-                    for idx in 1..=sq.y() {
-                        let square = sq - (idx as usize * 8) - idx;
-
-                        if let Some(ref p) = board.piece_at(square) {
-                            if p.side() != piece.side() {
-                                moves.push(square);
-                            }
-
-                            continue 'moves;
-                        }
-
-                        if square.y() == 0 || square.x() == 0 {
-                            moves.push(square);
-                            continue 'moves;
-                        }
+pub fn bishop_attacks(sq: usize, occupied: Bitboard) -> Bitboard {
+    Direction::DIAGONAL
+        .iter()
+        .fold(0, |bb, dir| bb | ray_attacks(sq, occupied, *dir))
+}
 
-                        if square > 0 && square < 63 {
-                            moves.push(square);
-                        }
-                    }
-                }
-                _ => (),
-            },
-            _ => (),
-        }
-    }
-    moves
+pub fn generate_bishop_moves(board: &Board, sq: usize, piece: &Piece) -> Bitboard {
+    bishop_attacks(sq, board.occupied()) & !board.side_bitboard(piece.side())
 }
@@ -1,99 +1,64 @@
-use crate::chess::{board::Edge, Board, Move, MoveConstraint, Piece, PieceKind, Side};
+use crate::chess::bitboard::{between, bit, leaper_attacks, squares, Bitboard, EMPTY};
+use crate::chess::{Board, CastleKind, Piece};
 
-pub const KING_MOVES: &[Move] = &[
-    Move {
-        x: 1,
-        y: 0,
-        constraints: &[],
-    },
-    Move {
-        x: 2,
-        y: 0,
-        constraints: &[MoveConstraint::Castling],
-    },
-    Move {
-        x: -2,
-        y: 0,
-        constraints: &[MoveConstraint::Castling],
-    },
-    Move {
-        x: 1,
-        y: 1,
-        constraints: &[],
-    },
-    Move {
-        x: 0,
-        y: 1,
-        constraints: &[],
-    },
-    Move {
-        x: -1,
-        y: 1,
-        constraints: &[],
-    },
-    Move {
-        x: -1,
-        y: 0,
-        constraints: &[],
-    },
-    Move {
-        x: 0,
-        y: -1,
-        constraints: &[],
-    },
-    Move {
-        x: -1,
-        y: -1,
-        constraints: &[],
-    },
-    Move {
-        x: -1,
-        y: 1,
-        constraints: &[],
-    },
-];
+pub const KING_ATTACKS: [Bitboard; 64] = leaper_attacks(&[
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+]);
 
-pub fn generate_king_moves(board: &Board, sq: usize, piece: &Piece) -> Vec<usize> {
-    let mut moves = vec![];
+pub fn king_attacks(sq: usize) -> Bitboard {
+    KING_ATTACKS[sq]
+}
+
+pub fn generate_king_moves(board: &Board, sq: usize, piece: &Piece) -> Bitboard {
+    king_attacks(sq) & !board.side_bitboard(piece.side())
+}
+
+/// The fully legal castling moves of the king on `sq`. Each one is marked on the square the king
+/// ends up on, or on the rook's square in Chess960, where castling is written as the king taking
+/// its own rook.
+pub fn generate_castling_moves(board: &Board, sq: usize, piece: &Piece) -> Bitboard {
+    let mut moves = EMPTY;
+    let enemy = piece.side().opposite();
 
-    'moves: for mv in KING_MOVES.iter() {
-        let (x, y) = match piece.side() {
-            Side::White => (mv.x, mv.y),
-            Side::Black => {
-                let mv = mv.invert_coordinates();
-                (mv.x, mv.y)
+    for kind in CastleKind::for_side(piece.side()).iter() {
+        let rook = match board.castling().rook_square(kind) {
+            Some(rook) if board.can_castle(kind) && sq == board.castling().king_square(kind) => {
+                rook
             }
+            _ => continue,
         };
 
-        let final_sq = (sq as isize + (y * 8 + x)) as usize;
+        let (king_dest, rook_dest) = kind.destination_squares();
 
-        if final_sq > 64 {
+        // the squares the king and the rook cross or land on must be empty, apart from the two of them
+        let path =
+            between(sq, king_dest) | bit(king_dest) | between(rook, rook_dest) | bit(rook_dest);
+
+        if path & board.occupied() & !bit(sq) & !bit(rook) != EMPTY {
             continue;
         }
 
-        for (idx, p) in board.pieces().iter().enumerate() {
-            if let Some(p) = p {
-                if p.side() == piece.side() {
-                    continue;
-                }
-
-                if p.kind() == &PieceKind::King && p.side() == board.turn() {
-                    continue;
-                }
+        // and the king can't castle out of, through or into check, which includes attacks that
+        // only the castling rook is blocking
+        let occupied = board.occupied() & !bit(rook);
+        let king_path = between(sq, king_dest) | bit(sq) | bit(king_dest);
 
-                if board.generate_moves(idx, p).contains(&final_sq) {
-                    continue 'moves;
-                }
-            }
-        }
-
-        if let Some(ref p) = board.piece_at(final_sq) {
-            if p.side() == piece.side() {
-                continue 'moves;
-            }
+        if squares(king_path).any(|idx| board.attackers(idx, &enemy, occupied) != EMPTY) {
+            continue;
         }
 
-        moves.push(final_sq);
+        moves |= match board.is_chess960() {
+            true => bit(rook),
+            false => bit(king_dest),
+        };
     }
+
     moves
 }
@@ -1,111 +1,44 @@
-use crate::chess::utils::calculate_squares_to_edge;
-use crate::chess::{board::Edge, Board, Move, MoveConstraint, Piece, Side, Square};
-use crate::utils::debug;
-use std::cmp::Ordering;
+use crate::chess::bitboard::{bit, leaper_attacks, Bitboard, EMPTY, RANK_1, RANK_2, RANK_7};
+use crate::chess::{Board, Piece, Side, Variant};
 
-pub const PAWN_MOVES: &[Move] = &[
-    Move {
-        x: 0,
-        y: 2,
-        constraints: &[MoveConstraint::MaxMoves(0)],
-    },
-    Move {
-        x: 0,
-        y: 1,
-        constraints: &[],
-    },
-    Move {
-        x: 1,
-        y: 1,
-        constraints: &[MoveConstraint::PieceOnTargetSquare],
-    },
-    Move {
-        x: -1,
-        y: 1,
-        constraints: &[MoveConstraint::PieceOnTargetSquare],
-    },
-];
+const WHITE_PAWN_ATTACKS: [Bitboard; 64] = leaper_attacks(&[(-1, 1), (1, 1)]);
+const BLACK_PAWN_ATTACKS: [Bitboard; 64] = leaper_attacks(&[(-1, -1), (1, -1)]);
 
-pub fn generate_pawn_moves(board: &Board, sq: usize, piece: &Piece) -> Vec<usize> {
-    let mut moves = vec![];
-    'moves: for mv in PAWN_MOVES.iter() {
-        let (x, y): (isize, isize) = match piece.side() {
-            Side::White => (mv.x, mv.y),
-            Side::Black => {
-                let mv = mv.invert_coordinates();
-                (mv.x, mv.y)
-            }
-        };
-
-        let idx_change = y * 8 + x;
-
-        let final_sq = (sq as isize + idx_change) as usize;
-        let mut move_constr = false;
-        let mut max_constr = false;
-
-        for c in mv.constraints.iter() {
-            match c {
-                MoveConstraint::MaxMoves(_) => {
-                    match piece.side() {
-                        Side::White => {
-                            if sq.y() != 1 {
-                                continue 'moves;
-                            }
-                        }
-                        Side::Black => {
-                            if sq.y() != 6 {
-                                continue 'moves;
-                            }
-                        }
-                    }
-                    max_constr = true;
-                }
-
-                MoveConstraint::PieceOnTargetSquare => {
-                    if let None = board.piece_at(final_sq) {
-                        continue 'moves;
-                    }
-
-                    move_constr = true;
-                }
-                _ => (),
-            }
-        }
+/// The squares a pawn of `side` standing on `sq` attacks.
+pub fn pawn_attacks(sq: usize, side: &Side) -> Bitboard {
+    match side {
+        Side::White => WHITE_PAWN_ATTACKS[sq],
+        Side::Black => BLACK_PAWN_ATTACKS[sq],
+    }
+}
 
-        let is_corner = match x.cmp(&0) {
-            Ordering::Greater => calculate_squares_to_edge(Edge::Right, sq) >= x as usize,
-            Ordering::Less => calculate_squares_to_edge(Edge::Left, sq) as isize >= -x,
-            _ => true,
-        };
+pub fn generate_pawn_moves(board: &Board, sq: usize, piece: &Piece) -> Bitboard {
+    let side = piece.side();
+    let empty = !board.occupied();
 
-        if !is_corner {
-            continue 'moves;
+    let (single, start_rank) = match side {
+        // the pawns of the horde can also move two squares from the first rank
+        Side::White if *board.variant() == Variant::Horde => {
+            ((bit(sq) << 8) & empty, RANK_1 | RANK_2)
         }
-
-        if max_constr {
-            if board
-                .piece_at((sq as isize + idx_change) as usize)
-                .is_some()
-                || board
-                    .piece_at((sq as isize + idx_change / 2) as usize)
-                    .is_some()
-            {
-                continue 'moves;
-            }
-        }
-
-        if move_constr {
-            if let Some(p) = board.piece_at(final_sq) {
-                if p.side() != piece.side() {
-                    moves.push(final_sq);
-                }
-            }
-        } else {
-            if board.piece_at(final_sq).is_none() {
-                moves.push(final_sq);
-            }
+        Side::White => ((bit(sq) << 8) & empty, RANK_2),
+        Side::Black => ((bit(sq) >> 8) & empty, RANK_7),
+    };
+
+    let double = match (bit(sq) & start_rank != 0, side) {
+        (true, Side::White) => (single << 8) & empty,
+        (true, Side::Black) => (single >> 8) & empty,
+        (false, _) => EMPTY,
+    };
+
+    let mut targets = board.side_bitboard(&side.opposite());
+
+    // en passant is only available to the side to move
+    if let Some(ep) = board.en_passant() {
+        if side == board.turn() {
+            targets |= bit(ep.index());
         }
     }
 
-    moves
+    single | double | (pawn_attacks(sq, side) & targets)
 }
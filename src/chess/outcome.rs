@@ -0,0 +1,90 @@
+use super::Side;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Checkmate(Side), // winner
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    ThreeChecks(Side),
+    KingOfTheHill(Side),
+    KingExploded(Side),
+    NoMovesLeft(Side),
+    HordeCaptured,
+    RaceWon(Side),
+    RaceTied,
+    // the endings of online games that aren't down to the position
+    Resignation(Side), // winner
+    Timeout(Side),     // winner, on time or because the other player left
+    Draw,              // agreed, or claimed on Lichess
+    Aborted,
+    Ended(Option<Side>), // winner, of a game Lichess ended some other way
+}
+
+impl Outcome {
+    pub fn winner(&self) -> Option<Side> {
+        match self {
+            Outcome::Checkmate(side)
+            | Outcome::ThreeChecks(side)
+            | Outcome::KingOfTheHill(side)
+            | Outcome::KingExploded(side)
+            | Outcome::NoMovesLeft(side)
+            | Outcome::RaceWon(side)
+            | Outcome::Resignation(side)
+            | Outcome::Timeout(side)
+            | Outcome::Ended(Some(side)) => Some(side.clone()),
+            Outcome::HordeCaptured => Some(Side::Black),
+            _ => None,
+        }
+    }
+
+    /// The result in PGN notation, e.g. `1-0` or `1/2-1/2`.
+    pub fn result(&self) -> &'static str {
+        if matches!(self, Outcome::Aborted | Outcome::Ended(None)) {
+            return "*";
+        }
+
+        match self.winner() {
+            Some(Side::White) => "1-0",
+            Some(Side::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Outcome::*;
+
+        match self {
+            Checkmate(Side::White) => write!(f, "white wins by checkmate"),
+            Checkmate(Side::Black) => write!(f, "black wins by checkmate"),
+            Stalemate => write!(f, "draw by stalemate"),
+            ThreefoldRepetition => write!(f, "draw by threefold repetition"),
+            FiftyMoveRule => write!(f, "draw by the fifty-move rule"),
+            InsufficientMaterial => write!(f, "draw by insufficient material"),
+            ThreeChecks(side) => write!(f, "{} wins by giving three checks", side_name(side)),
+            KingOfTheHill(side) => write!(f, "{} wins by reaching the center", side_name(side)),
+            KingExploded(side) => write!(f, "{} wins by exploding the king", side_name(side)),
+            NoMovesLeft(side) => write!(f, "{} wins by running out of moves", side_name(side)),
+            HordeCaptured => write!(f, "black wins by capturing the horde"),
+            RaceWon(side) => write!(f, "{} wins the race to the eighth rank", side_name(side)),
+            RaceTied => write!(f, "draw, both kings reached the eighth rank"),
+            Resignation(side) => write!(f, "{} wins by resignation", side_name(side)),
+            Timeout(side) => write!(f, "{} wins on time", side_name(side)),
+            Draw => write!(f, "draw"),
+            Aborted => write!(f, "game aborted"),
+            Ended(Some(side)) => write!(f, "{} wins", side_name(side)),
+            Ended(None) => write!(f, "game over"),
+        }
+    }
+}
+
+fn side_name(side: &Side) -> &'static str {
+    match side {
+        Side::White => "white",
+        Side::Black => "black",
+    }
+}
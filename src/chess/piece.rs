@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PieceKind {
     Pawn,
     Knight,
@@ -8,11 +8,54 @@ pub enum PieceKind {
     King,
 }
 
+impl PieceKind {
+    /// Parses the lowercase letter used for the piece in FEN and UCI.
+    pub fn from_char(c: char) -> Option<Self> {
+        use PieceKind::*;
+
+        match c {
+            'p' => Some(Pawn),
+            'n' => Some(Knight),
+            'b' => Some(Bishop),
+            'r' => Some(Rook),
+            'q' => Some(Queen),
+            'k' => Some(King),
+            _ => None,
+        }
+    }
+
+    /// Position of the kind in per-kind tables, such as `Board`'s bitboards.
+    pub fn index(&self) -> usize {
+        use PieceKind::*;
+
+        match self {
+            Pawn => 0,
+            Knight => 1,
+            Bishop => 2,
+            Rook => 3,
+            Queen => 4,
+            King => 5,
+        }
+    }
+
+    pub fn to_char(&self) -> char {
+        use PieceKind::*;
+
+        match self {
+            Pawn => 'p',
+            Knight => 'n',
+            Bishop => 'b',
+            Rook => 'r',
+            Queen => 'q',
+            King => 'k',
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Piece {
     kind: PieceKind,
     side: Side,
-    move_count: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -21,35 +64,45 @@ pub enum Side {
     Black,
 }
 
-impl Piece {
-    pub fn new(kind: PieceKind, side: Side) -> Self {
-        Self {
-            kind,
-            side,
-            move_count: 0,
+impl Side {
+    pub fn index(&self) -> usize {
+        match self {
+            Side::White => 0,
+            Side::Black => 1,
         }
     }
 
-    pub fn kind(&self) -> &PieceKind {
-        &self.kind
+    pub fn opposite(&self) -> Self {
+        match self {
+            Side::White => Side::Black,
+            Side::Black => Side::White,
+        }
     }
+}
 
-    pub fn side(&self) -> &Side {
-        &self.side
+impl Piece {
+    pub fn new(kind: PieceKind, side: Side) -> Self {
+        Self { kind, side }
     }
 
-    pub fn move_count(&self) -> &u32 {
-        &self.move_count
+    /// Creates a piece from its FEN character, uppercase for white and lowercase for black.
+    pub fn from_char(c: char) -> Option<Self> {
+        let kind = PieceKind::from_char(c.to_ascii_lowercase())?;
+
+        let side = match c.is_ascii_uppercase() {
+            true => Side::White,
+            false => Side::Black,
+        };
+
+        Some(Self::new(kind, side))
     }
 
-    pub fn increment_moves(&mut self) {
-        self.move_count += 1;
+    pub fn kind(&self) -> &PieceKind {
+        &self.kind
     }
 
-    pub fn decrement_moves(&mut self) {
-        if self.move_count > 0 {
-            self.move_count -= 1;
-        }
+    pub fn side(&self) -> &Side {
+        &self.side
     }
 
     pub fn render(&self, tile_width: usize) -> &str {
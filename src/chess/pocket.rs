@@ -0,0 +1,86 @@
+use super::{Piece, PieceKind, Side};
+
+// the kinds that can end up in a pocket, in the order they're shown
+const KINDS: [PieceKind; 5] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+];
+
+/// The pieces a side has captured in Crazyhouse, which it can drop back onto the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Pocket {
+    // indexed by `PieceKind::index`, kings can't be captured
+    counts: [u8; 5],
+}
+
+impl Pocket {
+    pub fn count(&self, kind: &PieceKind) -> u8 {
+        match kind {
+            PieceKind::King => 0,
+            kind => self.counts[kind.index()],
+        }
+    }
+
+    pub fn add(&mut self, kind: &PieceKind) {
+        if *kind != PieceKind::King {
+            self.counts[kind.index()] = self.counts[kind.index()].saturating_add(1);
+        }
+    }
+
+    /// Takes a piece out of the pocket, returning false if there was none.
+    pub fn remove(&mut self, kind: &PieceKind) -> bool {
+        match self.count(kind) {
+            0 => false,
+            _ => {
+                self.counts[kind.index()] -= 1;
+                true
+            }
+        }
+    }
+
+    /// The kinds there's at least one piece of, from pawns up to queens.
+    pub fn kinds(&self) -> impl Iterator<Item = PieceKind> + '_ {
+        KINDS
+            .iter()
+            .copied()
+            .filter(move |kind| self.count(kind) > 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|count| *count == 0)
+    }
+
+    /// Parses the pockets of both sides from the `[...]` part of a Crazyhouse FEN, e.g. `QPPn`.
+    pub fn parse_pair(field: &str) -> Option<[Pocket; 2]> {
+        let mut pockets = [Pocket::default(); 2];
+
+        for c in field.chars() {
+            match Piece::from_char(c) {
+                Some(p) if *p.kind() != PieceKind::King => pockets[p.side().index()].add(p.kind()),
+                _ => return None,
+            }
+        }
+
+        Some(pockets)
+    }
+
+    /// Writes both pockets the way `parse_pair` reads them, white's pieces first.
+    pub fn pair_to_fen(pockets: &[Pocket; 2]) -> String {
+        let mut fen = String::new();
+
+        for side in [Side::White, Side::Black].iter() {
+            for kind in KINDS.iter().rev() {
+                let piece = Piece::new(*kind, side.clone());
+
+                for _ in 0..pockets[side.index()].count(kind) {
+                    fen.push_str(&piece.render_char());
+                }
+            }
+        }
+
+        fen
+    }
+}
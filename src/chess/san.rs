@@ -0,0 +1,324 @@
+use super::{Board, CastleKind, ChessMove, PieceKind, Side, Square};
+use crate::chess::bitboard::{contains, squares};
+use crate::chess::board::Coords;
+use crate::chess::utils::idx_to_square;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    Invalid(String),
+    Illegal(String),
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use SanError::*;
+
+        match self {
+            Invalid(san) => write!(f, "'{}' is not a valid move", san),
+            Illegal(san) => write!(f, "'{}' is not a legal move", san),
+            Ambiguous(san) => write!(f, "'{}' matches more than one move", san),
+        }
+    }
+}
+
+impl Error for SanError {}
+
+fn file_index(c: char) -> Option<usize> {
+    match c {
+        'a'..='h' => Some(c as usize - 'a' as usize),
+        _ => None,
+    }
+}
+
+fn rank_index(c: char) -> Option<usize> {
+    match c {
+        '1'..='8' => Some(c as usize - '1' as usize),
+        _ => None,
+    }
+}
+
+impl Board {
+    /// Renders a legal move of the side to move in Standard Algebraic Notation, e.g. `Nbd7`, `exd6`,
+    /// `e8=Q+`, `O-O` or the Crazyhouse drop `N@f3`.
+    pub fn move_to_san(&mut self, mv: &ChessMove) -> String {
+        let (source, dest) = (mv.from().index(), mv.to().index());
+
+        let mut san = String::new();
+
+        let piece = match (self.piece_at(source), mv.dropped()) {
+            (_, Some(_)) => None,
+            (Some(p), None) => Some(p.clone()),
+            (None, None) => return mv.to_string(),
+        };
+
+        if let Some(kind) = mv.dropped() {
+            san.push(kind.to_char().to_ascii_uppercase());
+            san.push('@');
+            san.push_str(&idx_to_square(dest));
+        } else if let Some(kind) = self.castle_kind(mv) {
+            san.push_str(match kind.is_long() {
+                true => "O-O-O",
+                false => "O-O",
+            });
+        } else if let Some(piece) = piece {
+            let capture = self.piece_at(dest).is_some()
+                || (*piece.kind() == PieceKind::Pawn && self.en_passant() == Some(mv.to()));
+
+            match piece.kind() {
+                PieceKind::Pawn => {
+                    if capture {
+                        san.push_str(&idx_to_square(source)[..1]);
+                    }
+                }
+                kind => {
+                    san.push(kind.to_char().to_ascii_uppercase());
+                    san.push_str(&self.disambiguation(source, dest));
+                }
+            }
+
+            if capture {
+                san.push('x');
+            }
+
+            san.push_str(&idx_to_square(dest));
+
+            if self.is_promotion(mv.from(), mv.to()) {
+                let kind = mv.promotion().unwrap_or(PieceKind::Queen);
+                san.push('=');
+                san.push(kind.to_char().to_ascii_uppercase());
+            }
+        }
+
+        if self.make_move(*mv).is_ok() {
+            if self.is_check(self.turn()) {
+                san.push(match self.has_legal_moves() {
+                    true => '+',
+                    false => '#',
+                });
+            }
+
+            self.unmake_move();
+        }
+
+        san
+    }
+
+    // the source file, rank or square needed to tell the move apart from the same kind of piece
+    // moving to the same square
+    fn disambiguation(&self, source: usize, dest: usize) -> String {
+        let piece = match self.piece_at(source) {
+            Some(p) => p,
+            None => return String::new(),
+        };
+
+        let others: Vec<usize> = squares(self.pieces_bitboard(piece.kind(), piece.side()))
+            .filter(|sq| *sq != source)
+            .filter(|sq| match self.piece_at(*sq) {
+                Some(p) => contains(self.generate_move_bitboard(*sq, p), dest),
+                None => false,
+            })
+            .collect();
+
+        let square = idx_to_square(source);
+
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|sq| sq.x() != source.x()) {
+            square[..1].to_string()
+        } else if others.iter().all(|sq| sq.y() != source.y()) {
+            square[1..].to_string()
+        } else {
+            square
+        }
+    }
+
+    /// Resolves a move in Standard Algebraic Notation against the current position, returning its
+    /// source square, destination square and promotion piece.
+    pub fn parse_san(&self, san: &str) -> Result<ChessMove, SanError> {
+        let invalid = || SanError::Invalid(san.to_string());
+
+        let trimmed = san.trim_end_matches(['+', '#', '!', '?']);
+        let side = self.turn().clone();
+
+        if matches!(trimmed, "O-O" | "0-0" | "O-O-O" | "0-0-0") {
+            let kind = CastleKind::new(&side, trimmed.len() == 5);
+
+            let mv = self
+                .castle_move(&kind)
+                .ok_or_else(|| SanError::Illegal(san.to_string()))?;
+
+            return match self.is_legal(&mv) {
+                true => Ok(mv),
+                false => Err(SanError::Illegal(san.to_string())),
+            };
+        }
+
+        if let Some((kind, dest)) = trimmed.split_once('@') {
+            let kind = match kind.chars().collect::<Vec<char>>()[..] {
+                [] => PieceKind::Pawn,
+                [c] if c.is_ascii_uppercase() => {
+                    PieceKind::from_char(c.to_ascii_lowercase()).ok_or_else(invalid)?
+                }
+                _ => return Err(invalid()),
+            };
+
+            let dest: Square = dest.parse().map_err(|_| invalid())?;
+            let mv = ChessMove::new_drop(kind, dest);
+
+            return match self.is_legal(&mv) {
+                true => Ok(mv),
+                false => Err(SanError::Illegal(san.to_string())),
+            };
+        }
+
+        let mut chars: Vec<char> = trimmed.chars().collect();
+
+        // the promotion piece, written as `e8=Q` or `e8Q`
+        let mut promotion = None;
+
+        if let Some(c) = chars.last() {
+            if c.is_ascii_uppercase() && chars.len() > 2 {
+                let kind = PieceKind::from_char(c.to_ascii_lowercase()).ok_or_else(invalid)?;
+
+                if !self.variant().promotion_kinds().contains(&kind) {
+                    return Err(invalid());
+                }
+
+                promotion = Some(kind);
+                chars.pop();
+
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+        }
+
+        let kind = match chars.first() {
+            Some(c) if c.is_ascii_uppercase() => {
+                let kind = PieceKind::from_char(c.to_ascii_lowercase()).ok_or_else(invalid)?;
+                chars.remove(0);
+                kind
+            }
+            _ => PieceKind::Pawn,
+        };
+
+        if chars.len() < 2 {
+            return Err(invalid());
+        }
+
+        let rank = chars.pop().and_then(rank_index).ok_or_else(invalid)?;
+        let file = chars.pop().and_then(file_index).ok_or_else(invalid)?;
+        let dest = rank * 8 + file;
+
+        if chars.last() == Some(&'x') {
+            chars.pop();
+        }
+
+        let (mut from_file, from_rank) = match chars[..] {
+            [] => (None, None),
+            [c] => match (file_index(c), rank_index(c)) {
+                (Some(f), _) => (Some(f), None),
+                (_, Some(r)) => (None, Some(r)),
+                _ => return Err(invalid()),
+            },
+            [f, r] => (
+                Some(file_index(f).ok_or_else(invalid)?),
+                Some(rank_index(r).ok_or_else(invalid)?),
+            ),
+            _ => return Err(invalid()),
+        };
+
+        // pawns only leave their file when capturing, which names the source file
+        if kind == PieceKind::Pawn && from_file.is_none() {
+            from_file = Some(file);
+        }
+
+        let candidates: Vec<usize> = squares(self.pieces_bitboard(&kind, &side))
+            .filter(|sq| from_file.is_none_or(|f| sq.x() == f))
+            .filter(|sq| from_rank.is_none_or(|r| sq.y() == r))
+            .filter(|sq| match self.piece_at(*sq) {
+                Some(p) => contains(self.generate_move_bitboard(*sq, p), dest),
+                None => false,
+            })
+            .collect();
+
+        let source = match candidates[..] {
+            [source] => source,
+            [] => return Err(SanError::Illegal(san.to_string())),
+            _ => return Err(SanError::Ambiguous(san.to_string())),
+        };
+
+        let mv = ChessMove::new(
+            Square::from_index_unchecked(source),
+            Square::from_index_unchecked(dest),
+            promotion,
+        );
+
+        // castling is only ever written as `O-O` or `O-O-O`
+        if self.castle_kind(&mv).is_some() {
+            return Err(SanError::Illegal(san.to_string()));
+        }
+
+        match (self.is_promotion(mv.from(), mv.to()), promotion) {
+            (true, None) | (false, Some(_)) => Err(SanError::Illegal(san.to_string())),
+            _ => Ok(mv),
+        }
+    }
+
+    /// Plays a move given in Standard Algebraic Notation.
+    pub fn make_move_san(&mut self, san: &str) -> Result<(), SanError> {
+        let mv = self.parse_san(san)?;
+
+        self.make_move(mv)
+            .map_err(|_| SanError::Illegal(san.to_string()))
+    }
+
+    /// The moves played so far in Standard Algebraic Notation.
+    pub fn played_moves_san(&mut self) -> Vec<String> {
+        let moves: Vec<ChessMove> = self
+            .played_moves()
+            .iter()
+            .map(|mv| *mv.chess_move())
+            .collect();
+
+        for _ in moves.iter() {
+            self.unmake_move();
+        }
+
+        let mut san = vec![];
+
+        for mv in moves.iter() {
+            san.push(self.move_to_san(mv));
+            self.play(*mv);
+        }
+
+        san
+    }
+
+    /// A line of moves from this position in Standard Algebraic Notation with move numbers, e.g.
+    /// `1. e4 e5 2. Nf3` or `12... Nf6 13. Bb5`. Stops at the first illegal move.
+    pub fn line_to_san(&self, moves: &[ChessMove]) -> String {
+        let mut board = self.clone();
+        let mut line = vec![];
+
+        for (idx, mv) in moves.iter().enumerate() {
+            if !board.is_legal(mv) {
+                break;
+            }
+
+            match board.turn() {
+                Side::White => line.push(format!("{}.", board.fullmove_number())),
+                Side::Black if idx == 0 => line.push(format!("{}...", board.fullmove_number())),
+                Side::Black => (),
+            }
+
+            line.push(board.move_to_san(mv));
+            board.play(*mv);
+        }
+
+        line.join(" ")
+    }
+}
@@ -0,0 +1,75 @@
+use super::MoveError;
+use std::fmt;
+use std::str::FromStr;
+
+/// A square of the board, from a1 to h8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square {
+    /// The square on `file` and `rank`, both counted from 0.
+    pub fn new(file: usize, rank: usize) -> Option<Self> {
+        match file < 8 && rank < 8 {
+            true => Some(Self((rank * 8 + file) as u8)),
+            false => None,
+        }
+    }
+
+    /// The square with the board index `idx`, where a1 is 0 and h8 is 63.
+    pub fn from_index(idx: usize) -> Option<Self> {
+        match idx < 64 {
+            true => Some(Self(idx as u8)),
+            false => None,
+        }
+    }
+
+    // for indices coming out of the board itself, which are always in range
+    pub(crate) fn from_index_unchecked(idx: usize) -> Self {
+        debug_assert!(idx < 64);
+        Self(idx as u8 & 63)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn file(&self) -> usize {
+        self.index() % 8
+    }
+
+    pub fn rank(&self) -> usize {
+        self.index() / 8
+    }
+}
+
+impl FromStr for Square {
+    type Err = MoveError;
+
+    fn from_str(s: &str) -> Result<Self, MoveError> {
+        let invalid = || MoveError::InvalidSquare(s.to_string());
+
+        let mut chars = s.chars();
+
+        let file = match chars.next() {
+            Some(c @ 'a'..='h') => c as usize - 'a' as usize,
+            _ => return Err(invalid()),
+        };
+
+        let rank = match chars.next() {
+            Some(c @ '1'..='8') => c as usize - '1' as usize,
+            _ => return Err(invalid()),
+        };
+
+        match chars.next() {
+            None => Square::new(file, rank).ok_or_else(invalid),
+            Some(_) => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = (b'a' + self.file() as u8) as char;
+        write!(f, "{}{}", file, self.rank() + 1)
+    }
+}
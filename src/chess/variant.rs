@@ -0,0 +1,132 @@
+use super::fen::STARTING_FEN;
+use super::{PieceKind, Side};
+
+/// The rules a board is played by. Chess960 isn't one of them, since it only changes the start
+/// position and castling, see `Board::is_chess960`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// The first side to give three checks wins.
+    ThreeCheck,
+    /// A king that reaches one of the four center squares wins.
+    KingOfTheHill,
+    /// Captures blow up every piece but pawns around the capture square, including the capturer.
+    Atomic,
+    /// Captures are compulsory, and the first side that can't move wins.
+    Antichess,
+    /// White has a horde of pawns and no king, and wins by checkmate. Black wins by capturing it.
+    Horde,
+    /// Checks are illegal, and the first king to reach the eighth rank wins.
+    RacingKings,
+    /// Captured pieces go to the capturer's pocket, from where they can be dropped back in play.
+    Crazyhouse,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 8] = [
+        Variant::Standard,
+        Variant::ThreeCheck,
+        Variant::KingOfTheHill,
+        Variant::Atomic,
+        Variant::Antichess,
+        Variant::Horde,
+        Variant::RacingKings,
+        Variant::Crazyhouse,
+    ];
+
+    /// The name used by Lichess and in the `Variant` tag of PGN files.
+    pub fn name(&self) -> &'static str {
+        use Variant::*;
+
+        match self {
+            Standard => "Standard",
+            ThreeCheck => "Three-check",
+            KingOfTheHill => "King of the Hill",
+            Atomic => "Atomic",
+            Antichess => "Antichess",
+            Horde => "Horde",
+            RacingKings => "Racing Kings",
+            Crazyhouse => "Crazyhouse",
+        }
+    }
+
+    /// The `key` of the variant in the Lichess API, e.g. `threeCheck`.
+    pub fn key(&self) -> &'static str {
+        use Variant::*;
+
+        match self {
+            Standard => "standard",
+            ThreeCheck => "threeCheck",
+            KingOfTheHill => "kingOfTheHill",
+            Atomic => "atomic",
+            Antichess => "antichess",
+            Horde => "horde",
+            RacingKings => "racingKings",
+            Crazyhouse => "crazyhouse",
+        }
+    }
+
+    /// Parses a Lichess variant key. Chess960 and games from a position use the standard rules.
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "chess960" | "fromPosition" => Some(Variant::Standard),
+            key => Self::ALL.iter().find(|v| v.key() == key).copied(),
+        }
+    }
+
+    /// Parses the name of a variant as written in PGN files, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "chess960" | "from position" => Some(Variant::Standard),
+            name => Self::ALL
+                .iter()
+                .find(|v| v.name().to_ascii_lowercase() == name)
+                .copied(),
+        }
+    }
+
+    pub fn starting_fen(&self) -> &'static str {
+        use Variant::*;
+
+        match self {
+            Antichess => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+            Horde => "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1",
+            RacingKings => "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1",
+            Crazyhouse => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1",
+            _ => STARTING_FEN,
+        }
+    }
+
+    /// How many kings `side` has at the start, or `None` if it may have any number of them.
+    pub fn king_count(&self, side: &Side) -> Option<usize> {
+        match (self, side) {
+            (Variant::Antichess, _) => None,
+            (Variant::Horde, Side::White) => Some(0),
+            _ => Some(1),
+        }
+    }
+
+    /// Whether the king is a royal piece that can't be left in check.
+    pub fn has_king_safety(&self) -> bool {
+        !matches!(self, Variant::Antichess)
+    }
+
+    pub fn allows_castling(&self) -> bool {
+        !matches!(self, Variant::Antichess | Variant::RacingKings)
+    }
+
+    pub fn has_pockets(&self) -> bool {
+        matches!(self, Variant::Crazyhouse)
+    }
+
+    /// The pieces a pawn can promote to.
+    pub fn promotion_kinds(&self) -> &'static [PieceKind] {
+        use PieceKind::*;
+
+        match self {
+            Variant::Antichess => &[Queen, Rook, Bishop, Knight, King],
+            _ => &[Queen, Rook, Bishop, Knight],
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use super::{CastleKind, CastlingRights, Piece, PieceKind, Pocket, Side};
+
+// keys are generated at compile time with splitmix64, so hashes are stable between runs
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_keys<const N: usize>(seed: u64) -> [u64; N] {
+    let mut keys = [0; N];
+    let mut state = seed;
+    let mut idx = 0;
+
+    while idx < N {
+        let (next, key) = splitmix64(state);
+        state = next;
+        keys[idx] = key;
+        idx += 1;
+    }
+
+    keys
+}
+
+// 12 piece kinds (6 per side) on 64 squares
+const PIECE_KEYS: [u64; 768] = generate_keys(0x7a63_6865_7373);
+const CASTLING_KEYS: [u64; 4] = generate_keys(0x6361_7374_6c65);
+const EN_PASSANT_KEYS: [u64; 8] = generate_keys(0x6570_6173_7361);
+// 0 to 16 pieces of each of the 5 pocket kinds, for both sides
+const POCKET_KEYS: [u64; 170] = generate_keys(0x706f_636b_6574);
+const SIDE_KEY: u64 = generate_keys::<1>(0x7369_6465)[0];
+
+pub fn piece_key(piece: &Piece, sq: usize) -> u64 {
+    let idx = piece.side().index() * 6 + piece.kind().index();
+
+    PIECE_KEYS[idx * 64 + sq]
+}
+
+pub fn castling_key(rights: &CastlingRights) -> u64 {
+    use CastleKind::*;
+
+    [WhiteShort, WhiteLong, BlackShort, BlackLong]
+        .iter()
+        .enumerate()
+        .filter(|(_, kind)| rights.has(kind))
+        .fold(0, |key, (idx, _)| key ^ CASTLING_KEYS[idx])
+}
+
+pub fn pocket_key(side: &Side, pocket: &Pocket) -> u64 {
+    use PieceKind::*;
+
+    [Pawn, Knight, Bishop, Rook, Queen]
+        .iter()
+        .map(|kind| (side.index() * 5 + kind.index()) * 17 + pocket.count(kind).min(16) as usize)
+        .fold(0, |key, idx| key ^ POCKET_KEYS[idx])
+}
+
+pub fn en_passant_key(file: usize) -> u64 {
+    EN_PASSANT_KEYS[file]
+}
+
+pub fn side_key(turn: &Side) -> u64 {
+    match turn {
+        Side::White => 0,
+        Side::Black => SIDE_KEY,
+    }
+}
@@ -1,6 +1,8 @@
+use crate::challenge::{Challenge, DeclineReason, TimeControl};
 use crate::chess::PieceKind;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::{env, fs};
 use toml::de::Error;
 
@@ -20,16 +22,172 @@ impl PieceRender {
     }
 }
 
+/// An external UCI engine to play against, from the `[engine]` table.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EngineConfig {
+    path: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    threads: Option<u32>,
+    #[serde(default)]
+    hash: Option<u32>,
+    // any other UCI options, e.g. `"Skill Level" = 10`
+    #[serde(default)]
+    options: HashMap<String, toml::Value>,
+}
+
+impl EngineConfig {
+    pub fn path(&self) -> &String {
+        &self.path
+    }
+
+    pub fn args(&self) -> &Vec<String> {
+        &self.args
+    }
+
+    pub fn threads(&self) -> &Option<u32> {
+        &self.threads
+    }
+
+    pub fn hash(&self) -> &Option<u32> {
+        &self.hash
+    }
+
+    /// The UCI options to set after the handshake, with `Threads` and `Hash` first.
+    pub fn uci_options(&self) -> Vec<(String, String)> {
+        let mut options = vec![];
+
+        if let Some(threads) = self.threads {
+            options.push(("Threads".into(), threads.to_string()));
+        }
+
+        if let Some(hash) = self.hash {
+            options.push(("Hash".into(), hash.to_string()));
+        }
+
+        let mut rest: Vec<(String, String)> = self
+            .options
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    toml::Value::String(s) => s.clone(),
+                    value => value.to_string(),
+                };
+
+                (name.clone(), value)
+            })
+            .collect();
+
+        rest.sort();
+        options.extend(rest);
+
+        options
+    }
+
+    /// The file name of the engine binary, to show when the engine doesn't report its own name.
+    pub fn name(&self) -> String {
+        match std::path::Path::new(&self.path).file_name() {
+            Some(name) => name.to_string_lossy().into(),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/// Which challenges `zch bot` accepts and how it plays, from the `[bot]` table. Times are in
+/// seconds.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BotConfig {
+    // the keys of the accepted variants, e.g. `standard` or `chess960`
+    variants: Vec<String>,
+    min_initial: u64,
+    max_initial: Option<u64>,
+    min_increment: u64,
+    max_increment: Option<u64>,
+    rated: bool,
+    casual: bool,
+    // correspondence and unlimited games
+    correspondence: bool,
+    // how many games are played at once
+    concurrency: usize,
+    // play with the UCI engine from `[engine]` instead of the built-in one
+    external_engine: bool,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            variants: vec!["standard".into()],
+            min_initial: 0,
+            max_initial: None,
+            min_increment: 0,
+            max_increment: None,
+            rated: true,
+            casual: true,
+            correspondence: false,
+            concurrency: 1,
+            external_engine: false,
+        }
+    }
+}
+
+impl BotConfig {
+    /// Whether to accept `challenge`, or the reason to decline it with.
+    pub fn check(&self, challenge: &Challenge) -> Result<(), DeclineReason> {
+        if !self.variants.contains(challenge.variant().key()) {
+            return Err(DeclineReason::Variant);
+        }
+
+        match challenge.time_control() {
+            TimeControl::Clock { limit, increment } => {
+                if *limit < self.min_initial || *increment < self.min_increment {
+                    return Err(DeclineReason::TooFast);
+                }
+
+                let too_slow = |value: &u64, max: &Option<u64>| max.is_some_and(|max| *value > max);
+
+                if too_slow(limit, &self.max_initial) || too_slow(increment, &self.max_increment) {
+                    return Err(DeclineReason::TooSlow);
+                }
+            }
+            _ if !self.correspondence => return Err(DeclineReason::TimeControl),
+            _ => (),
+        }
+
+        match challenge.rated() {
+            true if !self.rated => Err(DeclineReason::Casual),
+            false if !self.casual => Err(DeclineReason::Rated),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency.max(1)
+    }
+
+    pub fn external_engine(&self) -> &bool {
+        &self.external_engine
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pieces: HashMap<String, PieceRender>,
+    #[serde(default)]
     token: String,
     #[serde(default)]
+    lichess_url: String,
+    #[serde(default)]
     debug: bool,
     #[serde(default)]
     center_pieces: bool,
     #[serde(default)]
+    auto_queen: bool,
+    #[serde(default)]
+    pgn_dir: String,
+    #[serde(default)]
     dark_square_color: String,
     #[serde(default)]
     light_square_color: String,
@@ -39,6 +197,12 @@ pub struct Config {
     black_piece_color: String,
     #[serde(default)]
     white_piece_color: String,
+    #[serde(default)]
+    engine: Option<EngineConfig>,
+    #[serde(default)]
+    analysis_lines: usize,
+    #[serde(default)]
+    bot: BotConfig,
 }
 
 impl Config {
@@ -53,6 +217,23 @@ impl Config {
         &self.center_pieces
     }
 
+    pub fn auto_queen(&self) -> &bool {
+        &self.auto_queen
+    }
+
+    /// The directory exported games are written to, `~/.local/share/zchess/games` by default.
+    pub fn pgn_dir(&self) -> PathBuf {
+        let home = env::var("HOME").unwrap_or_default();
+
+        match self.pgn_dir.as_str() {
+            "" => PathBuf::from(home).join(".local/share/zchess/games"),
+            dir => match dir.strip_prefix("~/") {
+                Some(rest) => PathBuf::from(home).join(rest),
+                None => PathBuf::from(dir),
+            },
+        }
+    }
+
     pub fn white_piece_color(&self) -> &String {
         &self.white_piece_color
     }
@@ -76,6 +257,14 @@ impl Config {
         &self.token
     }
 
+    /// The Lichess instance to play on, `https://lichess.org` by default.
+    pub fn lichess_url(&self) -> &str {
+        match self.lichess_url.as_str() {
+            "" => "https://lichess.org",
+            url => url,
+        }
+    }
+
     pub fn piece_render(&self, kind: &PieceKind) -> Option<&PieceRender> {
         use PieceKind::*;
 
@@ -88,7 +277,7 @@ impl Config {
             King => "king",
         };
 
-        self.pieces.get(idx_str.into())
+        self.pieces.get(idx_str)
     }
 
     pub fn legal_move_indicator_color(&self) -> &String {
@@ -98,4 +287,20 @@ impl Config {
     pub fn debug(&self) -> &bool {
         &self.debug
     }
+
+    pub fn engine(&self) -> &Option<EngineConfig> {
+        &self.engine
+    }
+
+    pub fn bot(&self) -> &BotConfig {
+        &self.bot
+    }
+
+    /// How many of the best moves analysis shows a line for, 3 by default.
+    pub fn analysis_lines(&self) -> usize {
+        match self.analysis_lines {
+            0 => 3,
+            lines => lines,
+        }
+    }
 }
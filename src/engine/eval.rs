@@ -0,0 +1,198 @@
+use crate::chess::{Board, PieceKind, Side, Variant};
+
+// piece-square tables from the Simplified Evaluation Function, from white's point of view with a8
+// first, so a white piece on `sq` looks up `sq ^ 56`
+#[rustfmt::skip]
+const PAWN: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const KING_MIDDLEGAME: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const KING_ENDGAME: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+// the game phase is 24 with all minor pieces, rooks and queens on the board, and 0 without them
+const MAX_PHASE: i32 = 24;
+
+/// The material value of a piece in centipawns.
+pub fn piece_value(kind: &PieceKind) -> i32 {
+    use PieceKind::*;
+
+    match kind {
+        Pawn => 100,
+        Knight => 320,
+        Bishop => 330,
+        Rook => 500,
+        Queen => 900,
+        King => 0,
+    }
+}
+
+fn phase_weight(kind: &PieceKind) -> i32 {
+    use PieceKind::*;
+
+    match kind {
+        Knight | Bishop => 1,
+        Rook => 2,
+        Queen => 4,
+        Pawn | King => 0,
+    }
+}
+
+/// Scores the position in centipawns from the point of view of the side to move, by material and
+/// piece placement. The king moves to the center as the pieces come off the board.
+pub fn evaluate(board: &Board) -> i32 {
+    use PieceKind::*;
+
+    let phase = board
+        .pieces()
+        .iter()
+        .flatten()
+        .map(|p| phase_weight(p.kind()))
+        .sum::<i32>()
+        .min(MAX_PHASE);
+
+    let mut score = 0;
+
+    for (sq, piece) in board.pieces().iter().enumerate() {
+        let piece = match piece {
+            Some(p) => p,
+            None => continue,
+        };
+
+        let idx = match piece.side() {
+            Side::White => sq ^ 56,
+            Side::Black => sq,
+        };
+
+        let placement = match piece.kind() {
+            Pawn => PAWN[idx],
+            Knight => KNIGHT[idx],
+            Bishop => BISHOP[idx],
+            Rook => ROOK[idx],
+            Queen => QUEEN[idx],
+            King => {
+                (KING_MIDDLEGAME[idx] * phase + KING_ENDGAME[idx] * (MAX_PHASE - phase)) / MAX_PHASE
+            }
+        };
+
+        let value = piece_value(piece.kind()) + placement;
+
+        score += match piece.side() {
+            Side::White => value,
+            Side::Black => -value,
+        };
+    }
+
+    score += variant_bonus(board);
+
+    match board.turn() {
+        Side::White => score,
+        Side::Black => -score,
+    }
+}
+
+// adjustments for the variants whose goals aren't just about material, from white's point of view
+fn variant_bonus(board: &Board) -> i32 {
+    let white_minus_black = |f: &dyn Fn(&Side) -> i32| f(&Side::White) - f(&Side::Black);
+
+    match board.variant() {
+        // losing pieces is the goal
+        Variant::Antichess => {
+            -2 * white_minus_black(&|side| {
+                board
+                    .pieces()
+                    .iter()
+                    .flatten()
+                    .filter(|p| p.side() == side)
+                    .map(|p| piece_value(p.kind()))
+                    .sum()
+            })
+        }
+        Variant::ThreeCheck => white_minus_black(&|side| 150 * i32::from(board.checks_given(side))),
+        // pieces in hand are worth about as much as the ones on the board
+        Variant::Crazyhouse => white_minus_black(&|side| {
+            let pocket = board.pocket(side);
+            pocket
+                .kinds()
+                .map(|kind| piece_value(&kind) * i32::from(pocket.count(&kind)))
+                .sum()
+        }),
+        _ => 0,
+    }
+}
@@ -0,0 +1,89 @@
+pub mod eval;
+pub mod search;
+pub mod server;
+pub mod tt;
+pub mod uci;
+
+pub use search::{Limits, Score, SearchInfo, Searcher};
+
+use std::time::Duration;
+
+/// How strong the built-in engine plays, by how deep and how long it may search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Beginner,
+    Casual,
+    Intermediate,
+    Advanced,
+    Maximum,
+}
+
+impl Level {
+    pub const ALL: [Level; 5] = [
+        Level::Beginner,
+        Level::Casual,
+        Level::Intermediate,
+        Level::Advanced,
+        Level::Maximum,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        use Level::*;
+
+        match self {
+            Beginner => "Beginner",
+            Casual => "Casual",
+            Intermediate => "Intermediate",
+            Advanced => "Advanced",
+            Maximum => "Maximum",
+        }
+    }
+
+    /// The search limits for a move with `remaining` on the engine's clock and `increment` added
+    /// after each move.
+    pub fn limits(&self, remaining: Duration, increment: Duration) -> Limits {
+        use Level::*;
+
+        let budget = time_budget(remaining, increment);
+
+        let (depth, max_time) = match self {
+            Beginner => (Some(1), Duration::from_millis(200)),
+            Casual => (Some(2), Duration::from_millis(500)),
+            Intermediate => (Some(4), Duration::from_secs(1)),
+            Advanced => (Some(6), Duration::from_secs(3)),
+            Maximum => (None, budget),
+        };
+
+        let limits = Limits::new().with_movetime(budget.min(max_time));
+
+        match depth {
+            Some(depth) => limits.with_depth(depth),
+            None => limits,
+        }
+    }
+}
+
+/// Who the computer opponent is: the built-in engine at some level, or an external UCI engine by
+/// name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Opponent {
+    BuiltIn(Level),
+    External(String),
+}
+
+impl Opponent {
+    pub fn name(&self) -> String {
+        match self {
+            Opponent::BuiltIn(level) => format!("zchess ({})", level.name()),
+            Opponent::External(name) => name.clone(),
+        }
+    }
+}
+
+/// How long to think about a move with `remaining` on the clock, spreading it over about 30 more
+/// moves and spending most of the increment.
+pub fn time_budget(remaining: Duration, increment: Duration) -> Duration {
+    let budget = remaining / 30 + increment * 3 / 4;
+
+    budget.min(remaining / 2).max(Duration::from_millis(20))
+}
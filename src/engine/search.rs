@@ -0,0 +1,587 @@
+use super::eval::{evaluate, piece_value};
+use super::tt::{Bound, TranspositionTable};
+use crate::chess::{Board, ChessMove, Outcome, PieceKind};
+
+use std::fmt;
+use std::ops::Neg;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const MATE: i32 = 32_000;
+const INFINITY: i32 = 32_500;
+
+/// How many plies deep the search goes at most, counting check extensions and captures.
+pub const MAX_PLY: usize = 96;
+
+/// A search score from the point of view of the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    /// Centipawns.
+    Cp(i32),
+    /// Mate in that many moves, negative if the side to move gets mated.
+    Mate(i32),
+}
+
+impl Score {
+    fn from_search(score: i32) -> Self {
+        let plies = MATE - score.abs();
+
+        match plies <= MAX_PLY as i32 {
+            true if score > 0 => Score::Mate((plies + 1) / 2),
+            true => Score::Mate(-(plies + 1) / 2),
+            false => Score::Cp(score),
+        }
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    /// The same score from the other side's point of view.
+    fn neg(self) -> Score {
+        match self {
+            Score::Cp(cp) => Score::Cp(-cp),
+            Score::Mate(moves) => Score::Mate(-moves),
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    /// Pawns with two decimals, e.g. `+0.35`, or the moves to mate, e.g. `#3` or `#-2`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Score::Cp(cp) => write!(f, "{:+.2}", *cp as f64 / 100.0),
+            Score::Mate(moves) => write!(f, "#{}", moves),
+        }
+    }
+}
+
+/// When to stop searching. Without any limit, the search goes on until it's stopped.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    depth: Option<u32>,
+    movetime: Option<Duration>,
+    nodes: Option<u64>,
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_depth(mut self, depth: u32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn with_movetime(mut self, movetime: Duration) -> Self {
+        self.movetime = Some(movetime);
+        self
+    }
+
+    pub fn with_nodes(mut self, nodes: u64) -> Self {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    pub fn depth(&self) -> &Option<u32> {
+        &self.depth
+    }
+
+    pub fn movetime(&self) -> &Option<Duration> {
+        &self.movetime
+    }
+
+    pub fn nodes(&self) -> &Option<u64> {
+        &self.nodes
+    }
+}
+
+/// The result of the deepest iteration the search completed.
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    depth: u32,
+    score: Score,
+    nodes: u64,
+    time: Duration,
+    pv: Vec<ChessMove>,
+}
+
+impl SearchInfo {
+    pub fn depth(&self) -> &u32 {
+        &self.depth
+    }
+
+    pub fn score(&self) -> &Score {
+        &self.score
+    }
+
+    pub fn nodes(&self) -> &u64 {
+        &self.nodes
+    }
+
+    pub fn time(&self) -> &Duration {
+        &self.time
+    }
+
+    /// The principal variation, the line both sides are expected to play.
+    pub fn pv(&self) -> &Vec<ChessMove> {
+        &self.pv
+    }
+
+    pub fn best_move(&self) -> Option<ChessMove> {
+        self.pv.first().copied()
+    }
+}
+
+/// An alpha-beta searcher with iterative deepening. It keeps its transposition table between
+/// searches, so it should be reused for the moves of one game.
+pub struct Searcher {
+    tt: TranspositionTable,
+    stop: Arc<AtomicBool>,
+    // two quiet moves per ply that caused a cutoff, tried right after the captures
+    killers: Vec<[Option<ChessMove>; 2]>,
+    // how often a quiet move from one square to another caused a cutoff, weighted by depth
+    history: Vec<i32>,
+    nodes: u64,
+    started: Instant,
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+    // the first iteration always runs to completion, so there's always a move to play
+    can_stop: bool,
+    stopped: bool,
+    // moves left out at the root, to find the lines after the best one
+    excluded: Vec<ChessMove>,
+}
+
+impl Searcher {
+    /// A searcher with a transposition table of about `hash_mb` megabytes.
+    pub fn new(hash_mb: usize) -> Self {
+        Self {
+            tt: TranspositionTable::new(hash_mb),
+            stop: Arc::new(AtomicBool::new(false)),
+            killers: vec![[None; 2]; MAX_PLY + 1],
+            history: vec![0; 64 * 64],
+            nodes: 0,
+            started: Instant::now(),
+            deadline: None,
+            max_nodes: None,
+            can_stop: false,
+            stopped: false,
+            excluded: vec![],
+        }
+    }
+
+    /// A flag that stops the running search once set, from any thread. It's cleared when the search
+    /// returns, so setting it before the search starts stops it after its first depth.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Makes `stop` the flag that stops the search, e.g. to give every search its own.
+    pub fn set_stop_handle(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = stop;
+    }
+
+    pub fn set_hash_size(&mut self, mb: usize) {
+        self.tt = TranspositionTable::new(mb);
+    }
+
+    /// Forgets everything learned in earlier searches, e.g. when a new game starts.
+    pub fn clear(&mut self) {
+        self.tt.clear();
+        self.history.iter_mut().for_each(|h| *h = 0);
+    }
+
+    /// Searches the position one ply deeper at a time until a limit is hit, calling `on_depth` with
+    /// the result of every completed depth. Returns the result of the last one.
+    pub fn search<F: FnMut(&SearchInfo)>(
+        &mut self,
+        board: &Board,
+        limits: &Limits,
+        mut on_depth: F,
+    ) -> SearchInfo {
+        let mut lines = self.search_lines(board, limits, 1, |lines| on_depth(&lines[0]));
+        lines.swap_remove(0)
+    }
+
+    /// Like `search`, but finds the best `count` moves with a line for each, best first. Every
+    /// depth searches the position again without the moves of the lines found before.
+    pub fn search_lines<F: FnMut(&[SearchInfo])>(
+        &mut self,
+        board: &Board,
+        limits: &Limits,
+        count: usize,
+        mut on_depth: F,
+    ) -> Vec<SearchInfo> {
+        let result = self.deepen(board, limits, count, &mut on_depth);
+        self.stop.store(false, Ordering::Relaxed);
+
+        result
+    }
+
+    fn deepen(
+        &mut self,
+        board: &Board,
+        limits: &Limits,
+        count: usize,
+        on_depth: &mut dyn FnMut(&[SearchInfo]),
+    ) -> Vec<SearchInfo> {
+        let mut board = board.clone();
+
+        self.nodes = 0;
+        self.started = Instant::now();
+        self.deadline = limits.movetime.map(|time| self.started + time);
+        self.max_nodes = limits.nodes;
+        self.can_stop = false;
+        self.stopped = false;
+        self.killers.iter_mut().for_each(|k| *k = [None; 2]);
+        self.history.iter_mut().for_each(|h| *h /= 8);
+
+        let max_depth = limits
+            .depth
+            .unwrap_or(MAX_PLY as u32)
+            .clamp(1, MAX_PLY as u32);
+
+        let legal_moves = board.legal_moves();
+        let count = count.clamp(1, legal_moves.len().max(1));
+
+        let mut result = vec![SearchInfo {
+            depth: 0,
+            score: Score::Cp(0),
+            nodes: 0,
+            time: Duration::ZERO,
+            pv: legal_moves.into_iter().take(1).collect(),
+        }];
+
+        if result[0].pv.is_empty() {
+            return result;
+        }
+
+        for depth in 1..=max_depth {
+            let mut lines = Vec::with_capacity(count);
+            self.excluded.clear();
+
+            while lines.len() < count {
+                let mut pv = vec![];
+                let score = self.negamax(&mut board, depth as i32, 0, -INFINITY, INFINITY, &mut pv);
+
+                if self.stopped || pv.is_empty() {
+                    break;
+                }
+
+                self.excluded.push(pv[0]);
+
+                lines.push(SearchInfo {
+                    depth,
+                    score: Score::from_search(score),
+                    nodes: self.nodes,
+                    time: self.started.elapsed(),
+                    pv,
+                });
+            }
+
+            self.excluded.clear();
+
+            if self.stopped {
+                break;
+            }
+
+            result = lines;
+
+            on_depth(&result);
+            self.can_stop = true;
+
+            // another iteration would most likely not finish in the time that's left
+            let out_of_time = match (limits.movetime, self.deadline) {
+                (Some(time), Some(_)) => self.started.elapsed() > time / 2,
+                _ => false,
+            };
+
+            if out_of_time || matches!(result[0].score, Score::Mate(_)) {
+                break;
+            }
+        }
+
+        result
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if !self.can_stop || self.stopped {
+            return self.stopped;
+        }
+
+        // looking at the clock is slow, so only do it every so often
+        let timed_out = self.nodes & 1023 == 0
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline);
+
+        self.stopped = timed_out
+            || self.stop.load(Ordering::Relaxed)
+            || self.max_nodes.is_some_and(|max| self.nodes >= max);
+
+        self.stopped
+    }
+
+    fn negamax(
+        &mut self,
+        board: &mut Board,
+        mut depth: i32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<ChessMove>,
+    ) -> i32 {
+        pv.clear();
+
+        if self.should_stop() {
+            return 0;
+        }
+
+        if ply > 0 {
+            if *board.halfmove_clock() >= 100 || board.repetition_count() >= 2 {
+                return 0;
+            }
+
+            if let Some(outcome) = board.variant_outcome() {
+                return outcome_score(board, &outcome, ply);
+            }
+        }
+
+        // don't stop the search in the middle of a forcing sequence
+        if board.is_check(board.turn()) {
+            depth += 1;
+        }
+
+        if depth <= 0 || ply >= MAX_PLY {
+            return self.quiesce(board, ply, alpha, beta);
+        }
+
+        self.nodes += 1;
+
+        let key = *board.hash();
+        let mut tt_move = None;
+
+        if let Some(entry) = self.tt.probe(key) {
+            tt_move = entry.best();
+
+            if ply > 0 && entry.depth() >= depth {
+                let score = score_from_tt(entry.score(), ply);
+
+                match entry.bound() {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => (),
+                }
+            }
+        }
+
+        let mut moves = board.legal_moves();
+
+        if ply == 0 {
+            moves.retain(|mv| !self.excluded.contains(mv));
+        }
+
+        if moves.is_empty() {
+            return match board.outcome() {
+                Some(outcome) => outcome_score(board, &outcome, ply),
+                None => 0,
+            };
+        }
+
+        self.order_moves(board, &mut moves, tt_move, ply);
+
+        let alpha_orig = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        let mut child_pv = vec![];
+
+        for mv in moves {
+            let quiet = captured_kind(board, &mv).is_none() && mv.promotion().is_none();
+
+            board.play(mv);
+            let score = -self.negamax(board, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            board.unmake_move();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                best_move = Some(mv);
+            }
+
+            if score > alpha {
+                alpha = score;
+
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&child_pv);
+            }
+
+            if alpha >= beta {
+                if quiet {
+                    self.remember_cutoff(mv, depth, ply);
+                }
+
+                break;
+            }
+        }
+
+        let bound = match best {
+            best if best >= beta => Bound::Lower,
+            best if best > alpha_orig => Bound::Exact,
+            _ => Bound::Upper,
+        };
+
+        // the best of the remaining root moves isn't the best move of the position
+        if ply > 0 || self.excluded.is_empty() {
+            self.tt
+                .store(key, best_move, score_to_tt(best, ply), depth, bound);
+        }
+
+        best
+    }
+
+    // searches captures and queen promotions until the position is quiet, so the evaluation isn't
+    // taken in the middle of an exchange
+    fn quiesce(&mut self, board: &mut Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+
+        self.nodes += 1;
+
+        if let Some(outcome) = board.variant_outcome() {
+            return outcome_score(board, &outcome, ply);
+        }
+
+        let stand_pat = evaluate(board);
+
+        if stand_pat >= beta || ply >= MAX_PLY {
+            return stand_pat;
+        }
+
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<ChessMove> = board
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| match mv.promotion() {
+                Some(kind) => kind == PieceKind::Queen,
+                None => captured_kind(board, mv).is_some(),
+            })
+            .collect();
+
+        self.order_moves(board, &mut moves, None, ply);
+
+        for mv in moves {
+            board.play(mv);
+            let score = -self.quiesce(board, ply + 1, -beta, -alpha);
+            board.unmake_move();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score >= beta {
+                return score;
+            }
+
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+
+    // the move from the transposition table first, then promotions, then captures of the most
+    // valuable pieces by the least valuable ones, then killers and the rest by history
+    fn order_moves(
+        &self,
+        board: &Board,
+        moves: &mut [ChessMove],
+        tt_move: Option<ChessMove>,
+        ply: usize,
+    ) {
+        moves.sort_by_cached_key(|mv| {
+            let score = if Some(*mv) == tt_move {
+                1_000_000
+            } else if let Some(kind) = mv.promotion() {
+                900_000 + piece_value(&kind)
+            } else if let Some(victim) = captured_kind(board, mv) {
+                let attacker = match board.piece_at(mv.from().index()) {
+                    Some(p) => piece_value(p.kind()),
+                    None => 0,
+                };
+
+                800_000 + 10 * piece_value(&victim) - attacker
+            } else if self.killers[ply].contains(&Some(*mv)) {
+                700_000
+            } else {
+                self.history[mv.from().index() * 64 + mv.to().index()]
+            };
+
+            -score
+        });
+    }
+
+    fn remember_cutoff(&mut self, mv: ChessMove, depth: i32, ply: usize) {
+        let killers = &mut self.killers[ply];
+
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+
+        let history = &mut self.history[mv.from().index() * 64 + mv.to().index()];
+        *history = (*history + depth * depth).min(600_000);
+    }
+}
+
+// the kind of the piece `mv` captures, if any. Castling onto the own rook in Chess960 isn't a capture
+fn captured_kind(board: &Board, mv: &ChessMove) -> Option<PieceKind> {
+    if mv.is_drop() {
+        return None;
+    }
+
+    match board.piece_at(mv.to().index()) {
+        Some(p) if p.side() != board.turn() => Some(*p.kind()),
+        Some(_) => None,
+        None => match board.piece_at(mv.from().index()) {
+            Some(p) if *p.kind() == PieceKind::Pawn && board.en_passant() == Some(mv.to()) => {
+                Some(PieceKind::Pawn)
+            }
+            _ => None,
+        },
+    }
+}
+
+// won positions score higher the sooner they're reached
+fn outcome_score(board: &Board, outcome: &Outcome, ply: usize) -> i32 {
+    match outcome.winner() {
+        Some(side) if side == *board.turn() => MATE - ply as i32,
+        Some(_) => -MATE + ply as i32,
+        None => 0,
+    }
+}
+
+// mate scores are stored relative to the position rather than to the root
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    match score {
+        score if score > MATE - MAX_PLY as i32 => score + ply as i32,
+        score if score < -MATE + MAX_PLY as i32 => score - ply as i32,
+        score => score,
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    match score {
+        score if score > MATE - MAX_PLY as i32 => score - ply as i32,
+        score if score < -MATE + MAX_PLY as i32 => score + ply as i32,
+        score => score,
+    }
+}
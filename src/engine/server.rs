@@ -0,0 +1,274 @@
+use super::uci::GoParams;
+use super::{Score, SearchInfo, Searcher};
+use crate::chess::Board;
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const DEFAULT_HASH_MB: usize = 16;
+const MAX_HASH_MB: usize = 1024;
+const MAX_LINES: usize = 16;
+
+// a search running on its own thread, which hands the searcher back when it's done
+struct RunningSearch {
+    handle: JoinHandle<Searcher>,
+    // set by `stop`, separately from the searcher's own flag, which is cleared when it returns
+    stopped: Arc<AtomicBool>,
+    searcher_stop: Arc<AtomicBool>,
+    infinite: bool,
+}
+
+/// The built-in engine speaking UCI to a GUI, reading commands from `input` and writing its replies
+/// to `output`, until `quit` or the end of the input. Searches run on their own thread, so `stop`
+/// and `isready` are answered while the engine is thinking.
+pub fn run<R: BufRead, W: Write + Send + 'static>(input: R, output: W) -> io::Result<()> {
+    let output = Arc::new(Mutex::new(output));
+
+    let mut board = Board::default();
+    let mut searcher = Some(Searcher::new(DEFAULT_HASH_MB));
+    let mut search: Option<RunningSearch> = None;
+    let mut chess960 = false;
+    let mut lines = 1;
+
+    for line in input.lines() {
+        let line = line?;
+        let (command, args) = match line.trim().split_once(' ') {
+            Some((command, args)) => (command, args.trim()),
+            None => (line.trim(), ""),
+        };
+
+        match command {
+            "uci" => {
+                say(
+                    &output,
+                    &format!("id name zchess {}", env!("CARGO_PKG_VERSION")),
+                )?;
+                say(&output, "id author bloatoo")?;
+                say(
+                    &output,
+                    &format!(
+                        "option name Hash type spin default {} min 1 max {}",
+                        DEFAULT_HASH_MB, MAX_HASH_MB
+                    ),
+                )?;
+                say(
+                    &output,
+                    &format!(
+                        "option name MultiPV type spin default 1 min 1 max {}",
+                        MAX_LINES
+                    ),
+                )?;
+                say(&output, "option name UCI_Chess960 type check default false")?;
+                say(&output, "uciok")?;
+            }
+
+            "isready" => say(&output, "readyok")?,
+
+            "setoption" => {
+                let (name, value) = parse_option(args);
+                finish(&mut search, &mut searcher, true);
+
+                match (name.to_lowercase().as_str(), value.parse::<usize>()) {
+                    ("hash", Ok(mb)) => {
+                        if let Some(searcher) = searcher.as_mut() {
+                            searcher.set_hash_size(mb.clamp(1, MAX_HASH_MB));
+                        }
+                    }
+                    ("multipv", Ok(count)) => lines = count.clamp(1, MAX_LINES),
+                    ("uci_chess960", _) => chess960 = value == "true",
+                    _ => say(&output, &format!("info string unknown option '{}'", name))?,
+                }
+            }
+
+            "ucinewgame" => {
+                finish(&mut search, &mut searcher, true);
+
+                if let Some(searcher) = searcher.as_mut() {
+                    searcher.clear();
+                }
+
+                board = Board::default();
+            }
+
+            "position" => {
+                finish(&mut search, &mut searcher, true);
+
+                match parse_position(args, chess960) {
+                    Ok(position) => board = position,
+                    Err(e) => say(&output, &format!("info string {}", e))?,
+                }
+            }
+
+            "go" => {
+                finish(&mut search, &mut searcher, true);
+
+                match args.parse::<GoParams>() {
+                    Ok(params) => {
+                        let searcher = searcher
+                            .take()
+                            .unwrap_or_else(|| Searcher::new(DEFAULT_HASH_MB));
+                        search = Some(start(searcher, &board, params, lines, output.clone()));
+                    }
+                    Err(_) => say(
+                        &output,
+                        &format!("info string invalid go command '{}'", line),
+                    )?,
+                }
+            }
+
+            "stop" => finish(&mut search, &mut searcher, true),
+
+            // the position as FEN, to check how the moves of `position` were played
+            "d" => say(&output, &format!("Fen: {}", board.to_fen()))?,
+
+            "quit" => {
+                finish(&mut search, &mut searcher, true);
+                return Ok(());
+            }
+
+            "" => (),
+            _ => say(&output, &format!("info string unknown command '{}'", line))?,
+        }
+    }
+
+    // a search started right before the input ended still gets to send its best move
+    finish(&mut search, &mut searcher, false);
+
+    Ok(())
+}
+
+// waits for the running search to send its best move, stopping it first if `stop` is set. Infinite
+// searches are always stopped, as they'd never end otherwise
+fn finish(search: &mut Option<RunningSearch>, searcher: &mut Option<Searcher>, stop: bool) {
+    let running = match search.take() {
+        Some(running) => running,
+        None => return,
+    };
+
+    if stop || running.infinite {
+        running.stopped.store(true, Ordering::Relaxed);
+        running.searcher_stop.store(true, Ordering::Relaxed);
+    }
+
+    if let Ok(done) = running.handle.join() {
+        *searcher = Some(done);
+    }
+}
+
+fn start<W: Write + Send + 'static>(
+    mut searcher: Searcher,
+    board: &Board,
+    params: GoParams,
+    lines: usize,
+    output: Arc<Mutex<W>>,
+) -> RunningSearch {
+    let board = board.clone();
+    let limits = params.limits(board.turn());
+    let infinite = params.is_infinite();
+
+    let stopped = Arc::new(AtomicBool::new(false));
+    let searcher_stop = searcher.stop_handle();
+    let thread_stopped = stopped.clone();
+
+    let handle = thread::spawn(move || {
+        let result = searcher.search_lines(&board, &limits, lines, |infos| {
+            for (idx, info) in infos.iter().enumerate() {
+                let _ = say(&output, &info_line(info, idx + 1));
+            }
+        });
+
+        // an infinite search waits for `stop` even if it's done, e.g. because it found a mate
+        while infinite && !thread_stopped.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let best = match result.first().and_then(|info| info.best_move()) {
+            Some(mv) => mv.to_string(),
+            None => "(none)".into(),
+        };
+
+        let _ = say(&output, &format!("bestmove {}", best));
+
+        searcher
+    });
+
+    RunningSearch {
+        handle,
+        stopped,
+        searcher_stop,
+        infinite,
+    }
+}
+
+// the `info` line of one of the lines of a completed depth
+fn info_line(info: &SearchInfo, multipv: usize) -> String {
+    let score = match info.score() {
+        Score::Cp(cp) => format!("cp {}", cp),
+        Score::Mate(moves) => format!("mate {}", moves),
+    };
+
+    let millis = info.time().as_millis() as u64;
+    let nps = info.nodes() * 1000 / millis.max(1);
+
+    let pv: Vec<String> = info.pv().iter().map(|mv| mv.to_string()).collect();
+
+    format!(
+        "info depth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+        info.depth(),
+        multipv,
+        score,
+        info.nodes(),
+        nps,
+        millis,
+        pv.join(" ")
+    )
+}
+
+// `name <name> value <value>`, where the name may contain spaces
+fn parse_option(args: &str) -> (String, String) {
+    let args = args.strip_prefix("name").unwrap_or(args).trim();
+
+    match args.split_once(" value") {
+        Some((name, value)) => (name.trim().into(), value.trim().into()),
+        None => (args.into(), String::new()),
+    }
+}
+
+// `startpos` or `fen <fen>`, optionally followed by `moves` and the moves played from there
+fn parse_position(args: &str, chess960: bool) -> Result<Board, Box<dyn std::error::Error>> {
+    let (position, moves) = match args.split_once("moves") {
+        Some((position, moves)) => (position.trim(), moves),
+        None => (args.trim(), ""),
+    };
+
+    let mut board = match position.split_once(' ') {
+        None if position == "startpos" => Board::default(),
+        Some(("fen", fen)) => Board::from_fen(fen.trim())?,
+        _ => return Err(format!("invalid position '{}'", args).into()),
+    };
+
+    if chess960 {
+        board.set_chess960(true);
+    }
+
+    for mv in moves.split_whitespace() {
+        board
+            .make_move_str(mv)
+            .map_err(|e| format!("{} in 'position {}'", e, args))?;
+    }
+
+    Ok(board)
+}
+
+fn say<W: Write>(output: &Mutex<W>, line: &str) -> io::Result<()> {
+    let mut output = match output.lock() {
+        Ok(output) => output,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    writeln!(output, "{}", line)?;
+    output.flush()
+}
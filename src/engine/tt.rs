@@ -0,0 +1,98 @@
+use crate::chess::ChessMove;
+
+/// Whether a stored score is exact, or only a bound because the search was cut off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    key: u64,
+    best: Option<ChessMove>,
+    score: i32,
+    depth: i32,
+    bound: Bound,
+}
+
+impl Entry {
+    pub fn best(&self) -> Option<ChessMove> {
+        self.best
+    }
+
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+
+    pub fn depth(&self) -> i32 {
+        self.depth
+    }
+
+    pub fn bound(&self) -> Bound {
+        self.bound
+    }
+}
+
+/// Remembers search results by position hash, so positions reached again through other move orders,
+/// or at the next depth, don't have to be searched again.
+#[derive(Debug, Clone)]
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+}
+
+impl TranspositionTable {
+    /// A table taking up roughly `mb` megabytes, rounded down to a power of two entries.
+    pub fn new(mb: usize) -> Self {
+        let wanted = (mb.max(1) << 20) / std::mem::size_of::<Option<Entry>>();
+
+        // the largest power of two that fits, so the index is a mask of the hash
+        let len = 1 << (usize::BITS - 1 - wanted.leading_zeros());
+
+        Self {
+            entries: vec![None; len],
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        key as usize & (self.entries.len() - 1)
+    }
+
+    pub fn probe(&self, key: u64) -> Option<&Entry> {
+        self.entries[self.index(key)]
+            .as_ref()
+            .filter(|entry| entry.key == key)
+    }
+
+    /// Stores a result, replacing whatever was in its slot unless that came from a deeper search of
+    /// the same position.
+    pub fn store(
+        &mut self,
+        key: u64,
+        best: Option<ChessMove>,
+        score: i32,
+        depth: i32,
+        bound: Bound,
+    ) {
+        let idx = self.index(key);
+
+        if let Some(old) = &self.entries[idx] {
+            if old.key == key && old.depth > depth && bound != Bound::Exact {
+                return;
+            }
+        }
+
+        self.entries[idx] = Some(Entry {
+            key,
+            best,
+            score,
+            depth,
+            bound,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+    }
+}
@@ -0,0 +1,424 @@
+use super::{time_budget, Limits};
+use crate::chess::{Board, ChessMove, Side};
+use crate::config::EngineConfig;
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::time::{self, Instant};
+
+/// How long the engine gets to answer `uci` and `isready`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum EngineError {
+    Spawn(String, io::Error),
+    Io(io::Error),
+    Timeout(&'static str),
+    Exited,
+    Protocol(String),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EngineError::*;
+
+        match self {
+            Spawn(path, e) => write!(f, "failed to start the engine '{}': {}", path, e),
+            Io(e) => write!(f, "failed to talk to the engine: {}", e),
+            Timeout(waiting_for) => write!(f, "the engine didn't send '{}' in time", waiting_for),
+            Exited => write!(f, "the engine exited unexpectedly"),
+            Protocol(line) => write!(f, "unexpected output from the engine: '{}'", line),
+        }
+    }
+}
+
+impl Error for EngineError {}
+
+impl From<io::Error> for EngineError {
+    fn from(e: io::Error) -> Self {
+        EngineError::Io(e)
+    }
+}
+
+/// The parameters of a `go` command. Times are in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GoParams {
+    wtime: Option<u64>,
+    btime: Option<u64>,
+    winc: Option<u64>,
+    binc: Option<u64>,
+    movetime: Option<u64>,
+    depth: Option<u32>,
+    nodes: Option<u64>,
+    infinite: bool,
+}
+
+impl GoParams {
+    /// Lets the engine manage its own time with both clocks.
+    pub fn clock(wtime: u64, btime: u64, winc: u64, binc: u64) -> Self {
+        Self {
+            wtime: Some(wtime),
+            btime: Some(btime),
+            winc: Some(winc),
+            binc: Some(binc),
+            ..Default::default()
+        }
+    }
+
+    pub fn movetime(movetime: u64) -> Self {
+        Self {
+            movetime: Some(movetime),
+            ..Default::default()
+        }
+    }
+
+    pub fn infinite() -> Self {
+        Self {
+            infinite: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn to_command(&self) -> String {
+        let mut command = String::from("go");
+
+        let params = [
+            ("wtime", self.wtime),
+            ("btime", self.btime),
+            ("winc", self.winc),
+            ("binc", self.binc),
+            ("movetime", self.movetime),
+            ("depth", self.depth.map(u64::from)),
+            ("nodes", self.nodes),
+        ];
+
+        for (name, value) in params.iter() {
+            if let Some(value) = value {
+                command.push_str(&format!(" {} {}", name, value));
+            }
+        }
+
+        if self.infinite {
+            command.push_str(" infinite");
+        }
+
+        command
+    }
+
+    pub fn is_infinite(&self) -> bool {
+        self.infinite
+    }
+
+    /// The limits for the built-in engine playing `side`: the fixed ones if there are any, and
+    /// otherwise a share of its clock.
+    pub fn limits(&self, side: &Side) -> Limits {
+        let mut limits = Limits::new();
+
+        if let Some(depth) = self.depth {
+            limits = limits.with_depth(depth);
+        }
+
+        if let Some(nodes) = self.nodes {
+            limits = limits.with_nodes(nodes);
+        }
+
+        let (remaining, increment) = match side {
+            Side::White => (self.wtime, self.winc),
+            Side::Black => (self.btime, self.binc),
+        };
+
+        match (self.movetime, remaining) {
+            (Some(movetime), _) => limits.with_movetime(Duration::from_millis(movetime)),
+            (None, Some(remaining)) if !self.infinite => limits.with_movetime(time_budget(
+                Duration::from_millis(remaining),
+                Duration::from_millis(increment.unwrap_or(0)),
+            )),
+            _ => limits,
+        }
+    }
+}
+
+impl FromStr for GoParams {
+    type Err = EngineError;
+
+    /// Parses the arguments of a `go` command, e.g. `wtime 60000 btime 60000 winc 1000 binc 1000`.
+    /// Arguments zchess has no use for, like `ponder` or `searchmoves`, are skipped.
+    fn from_str(args: &str) -> Result<Self, Self::Err> {
+        let mut params = GoParams::default();
+        let mut words = args.split_whitespace();
+
+        while let Some(word) = words.next() {
+            let mut number = || {
+                words
+                    .next()
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .ok_or_else(|| EngineError::Protocol(format!("go {}", args)))
+            };
+
+            match word {
+                "wtime" => params.wtime = Some(number()?),
+                "btime" => params.btime = Some(number()?),
+                "winc" => params.winc = Some(number()?),
+                "binc" => params.binc = Some(number()?),
+                "movetime" => params.movetime = Some(number()?),
+                "depth" => params.depth = Some(number()? as u32),
+                "nodes" => params.nodes = Some(number()?),
+                "movestogo" | "mate" => {
+                    number()?;
+                }
+                "infinite" => params.infinite = true,
+                _ => (),
+            }
+        }
+
+        Ok(params)
+    }
+}
+
+/// The `position` command for `board`, as its initial position followed by the moves played on it.
+pub fn position_command(board: &Board) -> String {
+    let mut board = board.clone();
+    let mut moves = vec![];
+
+    while let Some(played) = board.played_moves().last() {
+        moves.push(played.uci());
+        board.unmake_move();
+    }
+
+    let mut command = format!("position fen {}", board.to_fen());
+
+    if !moves.is_empty() {
+        moves.reverse();
+        command.push_str(" moves ");
+        command.push_str(&moves.join(" "));
+    }
+
+    command
+}
+
+/// The GUI side of the UCI protocol, talking to an engine through any pair of async pipes.
+pub struct UciClient<R, W> {
+    lines: Lines<BufReader<R>>,
+    writer: W,
+    name: Option<String>,
+    // the options the engine listed during the handshake
+    options: Vec<String>,
+    // whether the engine was told to play Chess960
+    chess960: bool,
+}
+
+impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> UciClient<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            name: None,
+            options: vec![],
+            chess960: false,
+        }
+    }
+
+    pub async fn send(&mut self, command: &str) -> Result<(), EngineError> {
+        self.writer.write_all(command.as_bytes()).await?;
+        self.writer.write_all(b"\n").await?;
+        self.writer.flush().await?;
+
+        Ok(())
+    }
+
+    async fn read_line(
+        &mut self,
+        waiting_for: &'static str,
+        deadline: Instant,
+    ) -> Result<String, EngineError> {
+        match time::timeout_at(deadline, self.lines.next_line()).await {
+            Ok(Ok(Some(line))) => Ok(line),
+            Ok(Ok(None)) => Err(EngineError::Exited),
+            Ok(Err(e)) => Err(e.into()),
+            Err(_) => Err(EngineError::Timeout(waiting_for)),
+        }
+    }
+
+    /// The `uci` handshake, which collects the engine's name and options.
+    pub async fn init(&mut self) -> Result<(), EngineError> {
+        self.send("uci").await?;
+
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+
+        loop {
+            let line = self.read_line("uciok", deadline).await?;
+            let line = line.trim();
+
+            if line == "uciok" {
+                return Ok(());
+            } else if let Some(name) = line.strip_prefix("id name ") {
+                self.name = Some(name.into());
+            } else if let Some(option) = line.strip_prefix("option name ") {
+                if let Some(idx) = option.find(" type ") {
+                    self.options.push(option[..idx].into());
+                }
+            }
+        }
+    }
+
+    /// Sets the configured options the engine knows about and waits until it's ready.
+    pub async fn configure(&mut self, config: &EngineConfig) -> Result<(), EngineError> {
+        for (name, value) in config.uci_options() {
+            if self.has_option(&name) {
+                self.set_option(&name, &value).await?;
+            }
+        }
+
+        self.is_ready().await
+    }
+
+    pub async fn set_option(&mut self, name: &str, value: &str) -> Result<(), EngineError> {
+        self.send(&format!("setoption name {} value {}", name, value))
+            .await
+    }
+
+    pub async fn is_ready(&mut self) -> Result<(), EngineError> {
+        self.send("isready").await?;
+
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+
+        while self.read_line("readyok", deadline).await?.trim() != "readyok" {}
+
+        Ok(())
+    }
+
+    pub async fn new_game(&mut self) -> Result<(), EngineError> {
+        self.send("ucinewgame").await?;
+        self.is_ready().await
+    }
+
+    /// Sends the position, first switching the engine to Chess960 or back if it has to read
+    /// castling as the king taking its rook.
+    pub async fn set_position(&mut self, board: &Board) -> Result<(), EngineError> {
+        let chess960 = board.is_chess960();
+
+        if chess960 != self.chess960 && self.has_option("UCI_Chess960") {
+            self.set_option("UCI_Chess960", &chess960.to_string())
+                .await?;
+            self.chess960 = chess960;
+        }
+
+        self.send(&position_command(board)).await
+    }
+
+    /// Starts a search and waits for its best move, or `None` if the position has no moves.
+    /// Setting `stop` makes the engine answer right away, and taking longer than `timeout` is an
+    /// error.
+    pub async fn go(
+        &mut self,
+        params: &GoParams,
+        stop: &AtomicBool,
+        timeout: Duration,
+    ) -> Result<Option<ChessMove>, EngineError> {
+        self.send(&params.to_command()).await?;
+
+        let deadline = Instant::now() + timeout;
+        let mut stop_sent = false;
+
+        loop {
+            // a stopped search still ends with a bestmove, which has to be read
+            let line = match stop_sent {
+                true => Some(self.read_line("bestmove", deadline).await?),
+                false => tokio::select! {
+                    line = self.read_line("bestmove", deadline) => Some(line?),
+                    _ = stopped(stop) => None,
+                },
+            };
+
+            let line = match line {
+                Some(line) => line,
+                None => {
+                    self.send("stop").await?;
+                    stop_sent = true;
+                    continue;
+                }
+            };
+
+            let mut words = line.split_whitespace();
+
+            if words.next() != Some("bestmove") {
+                continue;
+            }
+
+            return match words.next() {
+                Some("(none)") | Some("0000") => Ok(None),
+                Some(mv) => match mv.parse() {
+                    Ok(mv) => Ok(Some(mv)),
+                    Err(_) => Err(EngineError::Protocol(line.clone())),
+                },
+                None => Err(EngineError::Protocol(line.clone())),
+            };
+        }
+    }
+
+    /// The name from the engine's `id name`, once the handshake is done.
+    pub fn name(&self) -> &Option<String> {
+        &self.name
+    }
+
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options
+            .iter()
+            .any(|option| option.eq_ignore_ascii_case(name))
+    }
+}
+
+async fn stopped(stop: &AtomicBool) {
+    while !stop.load(Ordering::Relaxed) {
+        time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// An engine process started from the configuration, killed when this is dropped.
+pub struct UciEngine {
+    client: UciClient<ChildStdout, ChildStdin>,
+    _child: Child,
+}
+
+impl UciEngine {
+    /// Starts the engine, does the handshake and sets the configured options.
+    pub async fn start(config: &EngineConfig) -> Result<Self, EngineError> {
+        let spawn_err = |e| EngineError::Spawn(config.path().clone(), e);
+
+        let mut child = Command::new(config.path())
+            .args(config.args())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(spawn_err)?;
+
+        let (stdout, stdin) = match (child.stdout.take(), child.stdin.take()) {
+            (Some(stdout), Some(stdin)) => (stdout, stdin),
+            _ => return Err(EngineError::Exited),
+        };
+
+        let mut client = UciClient::new(stdout, stdin);
+
+        client.init().await?;
+        client.configure(config).await?;
+
+        Ok(Self {
+            client,
+            _child: child,
+        })
+    }
+
+    pub fn client(&mut self) -> &mut UciClient<ChildStdout, ChildStdin> {
+        &mut self.client
+    }
+}
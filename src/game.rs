@@ -1,5 +1,11 @@
-use crate::chess::Board;
+use crate::challenge::TimeControl;
+use crate::chess::{Board, MoveError, Outcome, PieceKind, Pocket, Side, Variant};
+use crate::engine::Opponent;
+use crate::pgn;
+use crate::utils::fmt_date;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::SystemTime;
 
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct GameState {
@@ -7,6 +13,25 @@ pub struct GameState {
     wtime: u64,
     btime: u64,
     status: String,
+    #[serde(default)]
+    winner: Option<String>,
+    #[serde(default)]
+    crazyhouse: Option<CrazyhouseState>,
+    // whether a side is offering a draw or proposing a takeback
+    #[serde(default)]
+    wdraw: bool,
+    #[serde(default)]
+    bdraw: bool,
+    #[serde(default)]
+    wtakeback: bool,
+    #[serde(default)]
+    btakeback: bool,
+}
+
+// the pockets of a Crazyhouse game, white's first, as counts by role, e.g. `{"pawn": 2}`
+#[derive(Deserialize, Debug, Clone, Default)]
+struct CrazyhouseState {
+    pockets: Vec<HashMap<String, u8>>,
 }
 
 impl GameState {
@@ -16,6 +41,9 @@ impl GameState {
             wtime: initial,
             btime: initial,
             status: String::new(),
+            winner: None,
+            crazyhouse: None,
+            ..Default::default()
         }
     }
 
@@ -28,6 +56,13 @@ impl GameState {
     }
 }
 
+/// Something the other player of an online game asks for, to be answered with yes or no.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Offer {
+    Draw,
+    Takeback,
+}
+
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct ChatMessage {
     username: String,
@@ -65,28 +100,129 @@ impl GameState {
     pub fn status(&self) -> &String {
         &self.status
     }
+
+    pub fn winner(&self) -> &Option<String> {
+        &self.winner
+    }
+
+    /// How the game ended by its status and winner, `None` while it's still going. Endings by
+    /// position are taken from `board`, which tells which rule a draw was by.
+    pub fn outcome(&self, board: &Board) -> Option<Outcome> {
+        let winner = match self.winner.as_deref() {
+            Some("white") => Some(Side::White),
+            Some("black") => Some(Side::Black),
+            _ => None,
+        };
+
+        let outcome = match (self.status.as_str(), winner) {
+            ("created" | "started" | "", _) => return None,
+            ("aborted" | "noStart", _) => Outcome::Aborted,
+            ("mate", Some(side)) => board.outcome().unwrap_or(Outcome::Checkmate(side)),
+            ("stalemate", _) => Outcome::Stalemate,
+            ("draw", _) => board
+                .outcome()
+                .filter(|outcome| outcome.winner().is_none())
+                .unwrap_or(Outcome::Draw),
+            ("variantEnd", winner) => board.outcome().unwrap_or(Outcome::Ended(winner)),
+            ("resign", Some(side)) => Outcome::Resignation(side),
+            ("timeout" | "outoftime", Some(side)) => Outcome::Timeout(side),
+            // flagging against a side that can't mate is a draw
+            ("timeout" | "outoftime", None) => Outcome::Draw,
+            (_, winner) => Outcome::Ended(winner),
+        };
+
+        Some(outcome)
+    }
+
+    pub fn draw_offered(&self, side: &Side) -> bool {
+        match side {
+            Side::White => self.wdraw,
+            Side::Black => self.bdraw,
+        }
+    }
+
+    pub fn takeback_proposed(&self, side: &Side) -> bool {
+        match side {
+            Side::White => self.wtakeback,
+            Side::Black => self.btakeback,
+        }
+    }
+
+    /// The pockets Lichess sent for a Crazyhouse game, if any.
+    pub fn pockets(&self) -> Option<[Pocket; 2]> {
+        let crazyhouse = self.crazyhouse.as_ref()?;
+        let mut pockets = [Pocket::default(); 2];
+
+        for (pocket, counts) in pockets.iter_mut().zip(crazyhouse.pockets.iter()) {
+            for (role, count) in counts.iter() {
+                let kind = match role.as_str() {
+                    "pawn" => PieceKind::Pawn,
+                    "knight" => PieceKind::Knight,
+                    "bishop" => PieceKind::Bishop,
+                    "rook" => PieceKind::Rook,
+                    "queen" => PieceKind::Queen,
+                    _ => continue,
+                };
+
+                for _ in 0..*count {
+                    pocket.add(&kind);
+                }
+            }
+        }
+
+        Some(pockets)
+    }
 }
 
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct GameData {
-    clock: Clock,
+    // correspondence and unlimited games have no clock
+    #[serde(default)]
+    clock: Option<Clock>,
+    #[serde(default, rename = "daysPerTurn")]
+    days_per_turn: Option<u32>,
     rated: bool,
     white: Player,
     black: Player,
+    #[serde(default)]
+    variant: GameVariant,
+    #[serde(default, rename = "initialFen")]
+    initial_fen: String,
 }
 
 impl GameData {
     pub fn with_clock(initial: u64, increment: u64) -> Self {
         Self {
-            clock: Clock::new(initial, increment),
+            clock: Some(Clock::new(initial, increment)),
             ..Default::default()
         }
     }
 
-    pub fn clock(&self) -> &Clock {
+    pub fn clock(&self) -> &Option<Clock> {
         &self.clock
     }
 
+    pub fn days_per_turn(&self) -> &Option<u32> {
+        &self.days_per_turn
+    }
+
+    /// The increment in milliseconds, 0 for games without a clock.
+    pub fn increment(&self) -> u64 {
+        self.clock.as_ref().map_or(0, |clock| clock.increment)
+    }
+
+    /// The time control, with clock times in seconds.
+    pub fn time_control(&self) -> TimeControl {
+        match (&self.clock, self.days_per_turn) {
+            (Some(clock), _) => TimeControl::Clock {
+                limit: clock.initial / 1000,
+                increment: clock.increment / 1000,
+            },
+            (None, Some(days_per_turn)) => TimeControl::Correspondence { days_per_turn },
+            (None, None) => TimeControl::Unlimited,
+        }
+    }
+
     pub fn rated(&self) -> &bool {
         &self.rated
     }
@@ -98,6 +234,47 @@ impl GameData {
     pub fn black(&self) -> &Player {
         &self.black
     }
+
+    pub fn variant(&self) -> &GameVariant {
+        &self.variant
+    }
+
+    pub fn initial_fen(&self) -> &String {
+        &self.initial_fen
+    }
+
+    /// The position the game started from, played by the rules of its variant and with Chess960
+    /// castling for `chess960` games. Lichess sends `startpos` for the variant's starting position.
+    pub fn start_board(&self) -> Board {
+        let variant = Variant::from_key(self.variant.key()).unwrap_or_default();
+
+        let mut board = match self.initial_fen.as_str() {
+            "startpos" | "" => Board::new(variant),
+            fen => Board::from_fen_variant(fen, variant).unwrap_or_else(|_| Board::new(variant)),
+        };
+
+        if self.variant.key() == "chess960" {
+            board.set_chess960(true);
+        }
+
+        board
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct GameVariant {
+    key: String,
+    name: String,
+}
+
+impl GameVariant {
+    pub fn key(&self) -> &String {
+        &self.key
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
 }
 
 #[derive(Deserialize, Debug, Clone, Default)]
@@ -122,8 +299,11 @@ impl Clock {
 
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct Player {
+    #[serde(default)]
     id: String,
+    #[serde(default)]
     name: String,
+    #[serde(default)]
     rating: u32,
 }
 
@@ -145,6 +325,11 @@ impl Player {
 pub enum GameKind {
     Online,
     Local,
+    /// Against an engine, which plays `engine_side`.
+    Computer {
+        engine_side: Side,
+        opponent: Opponent,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -156,37 +341,77 @@ pub struct Game {
     state: GameState,
     messages: Vec<ChatMessage>,
     kind: GameKind,
+    outcome: Option<Outcome>,
+    started: SystemTime,
 }
 
 impl Game {
+    /// A Lichess game, with the moves played so far in `state`.
     pub fn online<T: ToString>(id: T, data: GameData, state: GameState) -> Self {
-        Self {
-            board: Board::default(),
+        let mut game = Self {
+            board: data.start_board(),
             id: id.to_string(),
             move_count: 0,
             data,
             kind: GameKind::Online,
             messages: vec![],
-            state,
-        }
+            state: GameState::default(),
+            outcome: None,
+            started: SystemTime::now(),
+        };
+
+        // a game that's only just starting has no moves to play
+        let _ = game.apply_state(state);
+        game
     }
 
-    pub fn local(initial: u64, increment: u64) -> Self {
+    pub fn local(board: Board, initial: u64, increment: u64) -> Self {
         Self {
-            board: Default::default(),
+            board,
             id: Default::default(),
             move_count: Default::default(),
             data: GameData::with_clock(initial, increment),
             messages: Default::default(),
             state: GameState::with_time(initial),
             kind: GameKind::Local,
+            outcome: None,
+            started: SystemTime::now(),
+        }
+    }
+
+    /// A game against an engine, played locally.
+    pub fn computer(
+        board: Board,
+        engine_side: Side,
+        opponent: Opponent,
+        initial: u64,
+        increment: u64,
+    ) -> Self {
+        Self {
+            kind: GameKind::Computer {
+                engine_side,
+                opponent,
+            },
+            ..Self::local(board, initial, increment)
         }
     }
 
     pub fn is_online(&self) -> bool {
         match self.kind {
             GameKind::Online => true,
-            GameKind::Local => false,
+            GameKind::Local | GameKind::Computer { .. } => false,
+        }
+    }
+
+    pub fn kind(&self) -> &GameKind {
+        &self.kind
+    }
+
+    /// The side the engine plays, in games against it.
+    pub fn engine_side(&self) -> Option<&Side> {
+        match &self.kind {
+            GameKind::Computer { engine_side, .. } => Some(engine_side),
+            _ => None,
         }
     }
 
@@ -198,10 +423,56 @@ impl Game {
         &self.messages
     }
 
+    pub fn outcome(&self) -> &Option<Outcome> {
+        &self.outcome
+    }
+
+    pub fn set_outcome(&mut self, outcome: Option<Outcome>) {
+        self.outcome = outcome;
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// Whether the engine may analyse the game. It's off during online games until they're over,
+    /// so it can't help with moves.
+    pub fn analysis_allowed(&self) -> bool {
+        !self.is_online() || self.is_over()
+    }
+
     pub fn set_state(&mut self, state: GameState) {
         self.state = state;
     }
 
+    /// Sets up the board from the start position and the moves in a state sent by Lichess, and
+    /// takes over its clocks and status. Moves after an illegal one are left out.
+    pub fn apply_state(&mut self, state: GameState) -> Result<(), MoveError> {
+        let mut board = self.data.start_board();
+        let mut res = Ok(());
+
+        for mv in state.moves().split_whitespace() {
+            if let Err(e) = board.make_move_str(mv) {
+                res = Err(e);
+                break;
+            }
+        }
+
+        if let Some(pockets) = state.pockets() {
+            board.set_pockets(pockets);
+        }
+
+        if self.is_online() {
+            self.outcome = state.outcome(&board);
+        }
+
+        self.board = board;
+        self.state = state;
+        self.board.reset_turn_timer();
+
+        res
+    }
+
     pub fn board(&self) -> &Board {
         &self.board
     }
@@ -222,6 +493,12 @@ impl Game {
         self.move_count += 1;
     }
 
+    pub fn decr_move_count(&mut self) {
+        if self.move_count > 0 {
+            self.move_count -= 1;
+        }
+    }
+
     pub fn data(&self) -> &GameData {
         &self.data
     }
@@ -229,4 +506,79 @@ impl Game {
     pub fn state(&self) -> &GameState {
         &self.state
     }
+
+    pub fn started(&self) -> &SystemTime {
+        &self.started
+    }
+
+    /// The result in PGN notation, `*` while the game is still going.
+    pub fn result(&self) -> &'static str {
+        match &self.outcome {
+            Some(outcome) => outcome.result(),
+            None => "*",
+        }
+    }
+
+    /// Exports the game in PGN, with the Seven Tag Roster followed by ratings and the time control.
+    /// Online games link to their page on the Lichess instance at `site`.
+    pub fn to_pgn(&self, site: &str) -> String {
+        let (event, site, white, black) = match &self.kind {
+            GameKind::Online => (
+                match self.data.rated() {
+                    true => "Rated game",
+                    false => "Casual game",
+                },
+                format!("{}/{}", site, self.id),
+                self.data.white().name().clone(),
+                self.data.black().name().clone(),
+            ),
+            GameKind::Local => ("Local game", "?".into(), "?".into(), "?".into()),
+            GameKind::Computer {
+                engine_side,
+                opponent,
+            } => {
+                let engine = opponent.name();
+
+                match engine_side {
+                    Side::White => ("Game vs computer", "?".into(), engine, "?".into()),
+                    Side::Black => ("Game vs computer", "?".into(), "?".into(), engine),
+                }
+            }
+        };
+
+        let mut tags = vec![
+            ("Event", event.to_string()),
+            ("Site", site),
+            ("Date", fmt_date(self.started)),
+            ("Round", "-".into()),
+            ("White", white),
+            ("Black", black),
+            ("Result", self.result().into()),
+        ];
+
+        if self.is_online() {
+            for (tag, player) in [
+                ("WhiteElo", self.data.white()),
+                ("BlackElo", self.data.black()),
+            ] {
+                if *player.rating() > 0 {
+                    tags.push((tag, player.rating().to_string()));
+                }
+            }
+        }
+
+        let time_control = match self.data.time_control() {
+            TimeControl::Clock {
+                limit: 0,
+                increment: 0,
+            }
+            | TimeControl::Correspondence { .. }
+            | TimeControl::Unlimited => "-".into(),
+            TimeControl::Clock { limit, increment } => format!("{}+{}", limit, increment),
+        };
+
+        tags.push(("TimeControl", time_control));
+
+        pgn::write(&tags, &self.board, self.result())
+    }
 }
@@ -1,20 +1,1592 @@
+pub mod analysis;
 pub mod app;
+pub mod bot;
+pub mod challenge;
+pub mod chat;
 pub mod chess;
 pub mod config;
+pub mod engine;
 pub mod game;
+pub mod lichess;
 pub mod message;
+pub mod pgn;
+pub mod replay;
+pub mod seek;
+pub mod stream;
 pub mod ui;
 pub mod user;
 pub mod utils;
 
 #[cfg(test)]
 mod tests {
+    use crate::app::App;
+    use crate::challenge::{Challenge, ChallengeForm, DeclineReason, Outgoing};
+    use crate::chat::{self, Chat, Room};
     use crate::chess::utils::idx_to_square;
-    use crate::chess::utils::square_to_idx;
+    use crate::config::{BotConfig, EngineConfig};
+    use crate::engine::server;
+    use crate::engine::uci::{EngineError, GoParams, UciClient};
+    use crate::engine::{time_budget, Limits, Score, Searcher};
+    use crate::game::{ChatMessage, Game, GameData, GameState};
+    use crate::lichess::{self, Lichess, LichessError};
+    use crate::message::Message;
+    use crate::pgn::{self, PgnError};
+    use crate::replay::Replay;
+    use crate::seek::{SeekField, SeekForm};
+    use crate::stream::{Event, GameEvent, NdjsonStream};
+    use crate::ui::UIState;
+    use crate::user::User;
+    use crate::utils::fmt_date;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, UNIX_EPOCH};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    use crate::chess::{
+        fen::STARTING_FEN, Board, ChessMove, FenError, MoveError, Outcome, PieceKind, SanError,
+        Side, Square, Variant,
+    };
+
+    fn sq(s: &str) -> Square {
+        s.parse().unwrap()
+    }
+
+    fn mv(uci: &str) -> ChessMove {
+        uci.parse().unwrap()
+    }
+
+    // the game in a `gameFull` event
+    fn online_game(json: serde_json::Value) -> Game {
+        let id = json["id"].as_str().unwrap_or_default().to_string();
+
+        match GameEvent::from_json(json).unwrap() {
+            GameEvent::GameFull { data, state } => Game::online(id, *data, state),
+            event => panic!("expected the full game, got {:?}", event),
+        }
+    }
 
     #[test]
     fn idx_to_sq() {
         assert_eq!(idx_to_square(63), "h8");
-        assert_eq!(square_to_idx("h8"), 63);
+        assert_eq!(sq("h8").index(), 63);
+    }
+
+    #[test]
+    fn square_and_move_parsing() {
+        let e4 = sq("e4");
+        assert_eq!((e4.file(), e4.rank()), (4, 3));
+        assert_eq!(Square::new(4, 3), Some(e4));
+        assert_eq!(Square::from_index(64), None);
+
+        assert_eq!(
+            "i1".parse::<Square>(),
+            Err(MoveError::InvalidSquare("i1".into()))
+        );
+        assert_eq!(
+            "e44".parse::<Square>(),
+            Err(MoveError::InvalidSquare("e44".into()))
+        );
+
+        assert_eq!(mv("e7e8n").promotion(), Some(PieceKind::Knight));
+        assert_eq!(mv("e7e8n").to_string(), "e7e8n");
+        assert_eq!(mv("g1f3"), ChessMove::new(sq("g1"), sq("f3"), None));
+
+        for uci in ["e2e", "e2e4e", "e7e8p", "z2e4", "e2e4qq"] {
+            assert_eq!(
+                uci.parse::<ChessMove>(),
+                Err(MoveError::InvalidUci(uci.into()))
+            );
+        }
+
+        let mut board = Board::default();
+        assert_eq!(board.legal_moves().len(), 20);
+        assert_eq!(board.legal_moves_from(sq("g1")).len(), 2);
+        assert!(board.legal_moves_from(sq("e4")).is_empty());
+
+        assert_eq!(
+            board.make_move(mv("e2e5")),
+            Err(MoveError::Illegal("e2e5".into()))
+        );
+        assert_eq!(
+            board.make_move_str("e2"),
+            Err(MoveError::InvalidUci("e2".into()))
+        );
+        assert!(board.played_moves().is_empty());
+
+        board.make_move(mv("e2e4")).unwrap();
+        assert_eq!(board.en_passant(), Some(sq("e3")));
+    }
+
+    #[test]
+    fn en_passant() {
+        let mut board = Board::default();
+
+        for uci in ["e2e4", "a7a6", "e4e5", "d7d5"] {
+            board.make_move_str(uci).unwrap();
+        }
+
+        assert_eq!(board.en_passant(), Some(sq("d6")));
+        assert!(board.legal_moves_from(sq("e5")).contains(&mv("e5d6")));
+
+        board.make_move_str("e5d6").unwrap();
+        assert!(board.piece_at(sq("d5").index()).is_none());
+
+        board.unmake_move();
+        let pawn = board.piece_at(sq("d5").index()).clone().unwrap();
+        assert_eq!(*pawn.kind(), PieceKind::Pawn);
+        assert_eq!(board.en_passant(), Some(sq("d6")));
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        let fens = [
+            STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40",
+        ];
+
+        for fen in fens.iter() {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), *fen);
+        }
+
+        let mut board = Board::default();
+        board.make_move_str("e2e4").unwrap();
+
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+    }
+
+    #[test]
+    fn fen_errors() {
+        assert_eq!(
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1").unwrap_err(),
+            FenError::InvalidRankCount(7)
+        );
+
+        assert_eq!(
+            Board::from_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+                .unwrap_err(),
+            FenError::InvalidRank("9".into())
+        );
+
+        assert_eq!(
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1")
+                .unwrap_err(),
+            FenError::InvalidSide("x".into())
+        );
+
+        assert_eq!(
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1")
+                .unwrap_err(),
+            FenError::InvalidEnPassant("e3".into())
+        );
+
+        assert!(
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KKkq - 0 1").is_err()
+        );
+        assert!(
+            Board::from_fen("rnbqqbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_err()
+        );
+    }
+
+    #[test]
+    fn castling_rights() {
+        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let moves = board.legal_moves_from(sq("e1"));
+        assert!(moves.contains(&mv("e1g1")) && moves.contains(&mv("e1c1")));
+
+        // rook captures rook, which takes away a castling right from both sides
+        board.make_move_str("h1h8").unwrap();
+        assert_eq!(board.castling().to_fen(board.pieces()), "Qq");
+
+        board.unmake_move();
+        assert_eq!(board.castling().to_fen(board.pieces()), "KQkq");
+
+        // a bishop on g5 stops black from castling through d8
+        let board = Board::from_fen("r3k2r/8/8/6B1/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        let moves = board.legal_moves_from(sq("e8"));
+        assert!(!moves.contains(&mv("e8c8")));
+        assert!(moves.contains(&mv("e8g8")));
+
+        // but an attacked b8 doesn't matter, since the king never crosses it
+        let board = Board::from_fen("r3k2r/8/8/8/8/6B1/8/R3K2R b KQkq - 0 1").unwrap();
+        let moves = board.legal_moves_from(sq("e8"));
+        assert!(moves.contains(&mv("e8c8")));
+
+        // pieces in between
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/RN2K1NR w KQkq - 0 1").unwrap();
+        assert!(board.legal_moves_from(sq("e1")).len() == 5);
+    }
+
+    #[test]
+    fn outcomes() {
+        let mut board = Board::default();
+
+        for uci in ["f2f3", "e7e5", "g2g4"] {
+            board.make_move_str(uci).unwrap();
+        }
+
+        assert_eq!(board.outcome(), None);
+
+        board.make_move_str("d8h4").unwrap();
+        assert_eq!(board.outcome(), Some(Outcome::Checkmate(Side::Black)));
+
+        let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(board.outcome(), Some(Outcome::Stalemate));
+
+        let board = Board::from_fen("8/8/4k3/8/2b5/8/4K3/8 w - - 0 1").unwrap();
+        assert_eq!(board.outcome(), Some(Outcome::InsufficientMaterial));
+
+        let board = Board::from_fen("8/8/4k3/8/2bb4/8/4K3/8 w - - 0 1").unwrap();
+        assert_eq!(board.outcome(), None);
+
+        let mut board = Board::from_fen("8/8/4k3/8/8/8/4K3/R7 w - - 99 80").unwrap();
+        board.make_move_str("a1a2").unwrap();
+        assert_eq!(board.outcome(), Some(Outcome::FiftyMoveRule));
+
+        let mut board = Board::default();
+
+        for _ in 0..2 {
+            for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                board.make_move_str(uci).unwrap();
+            }
+        }
+
+        assert_eq!(board.repetition_count(), 3);
+        assert_eq!(board.outcome(), Some(Outcome::ThreefoldRepetition));
+    }
+
+    #[test]
+    fn underpromotion() {
+        let mut board = Board::from_fen("8/4P3/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        board.make_move_str("e7e8n").unwrap();
+
+        let piece = board.piece_at(sq("e8").index()).clone().unwrap();
+        assert_eq!(*piece.kind(), PieceKind::Knight);
+        assert_eq!(board.played_moves().last().unwrap().uci(), "e7e8n");
+    }
+
+    #[test]
+    fn make_unmake() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        let hash = *board.hash();
+
+        // castling, captures, a double push followed by en passant, and a promotion with capture
+        let moves = [
+            "e1g1", "h3g2", "a2a4", "b4a3", "e5f7", "g2f1q", "g1f1", "e8g8",
+        ];
+
+        for uci in moves.iter() {
+            board.make_move_str(uci).unwrap();
+        }
+
+        assert_eq!(
+            board.to_fen(),
+            "r4rk1/p1ppqNb1/bn2pnp1/3P4/4P3/p1N2Q2/1PPBBP1P/R4K2 w - - 1 5"
+        );
+
+        for _ in moves.iter() {
+            board.unmake_move();
+        }
+
+        assert_eq!(board.to_fen(), fen);
+        assert_eq!(*board.hash(), hash);
+        assert!(board.played_moves().is_empty());
+    }
+
+    #[test]
+    fn perft() {
+        let positions = [
+            (STARTING_FEN, [20, 400, 8902]),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                [48, 2039, 97862],
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", [14, 191, 2812]),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                [6, 264, 9467],
+            ),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                [44, 1486, 62379],
+            ),
+            (
+                "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+                [46, 2079, 89890],
+            ),
+        ];
+
+        for (fen, counts) in positions.iter() {
+            let mut board = Board::from_fen(fen).unwrap();
+
+            for (depth, nodes) in counts.iter().enumerate() {
+                assert_eq!(board.perft(depth as u32 + 1), *nodes, "{}", fen);
+            }
+
+            assert_eq!(board.to_fen(), *fen);
+        }
+
+        let mut board = Board::default();
+        let divide = board.divide(2);
+
+        assert_eq!(divide.len(), 20);
+        assert!(divide.iter().all(|(_, nodes)| *nodes == 20));
+    }
+
+    #[test]
+    fn san() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+
+        let cases = [
+            ("e1g1", "O-O"),
+            ("e1c1", "O-O-O"),
+            ("e5f7", "Nxf7"),
+            ("c3b5", "Nb5"),
+            ("e2a6", "Bxa6"),
+            ("f3f6", "Qxf6"),
+            ("d5e6", "dxe6"),
+        ];
+
+        for (uci, san) in cases.iter() {
+            assert_eq!(board.move_to_san(&mv(uci)), *san);
+            assert_eq!(board.parse_san(san), Ok(mv(uci)));
+        }
+
+        // knights on b8 and f6 can both reach d7
+        let mut board =
+            Board::from_fen("rn2kb1r/ppp2ppp/5n2/3pp3/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_eq!(board.parse_san("Nbd7"), Ok(mv("b8d7")));
+        assert_eq!(
+            board.parse_san("Nd7"),
+            Err(SanError::Ambiguous("Nd7".into()))
+        );
+
+        assert_eq!(board.move_to_san(&mv("f6d7")), "Nfd7");
+
+        // en passant
+        let mut board = Board::default();
+
+        for san in ["e4", "a6", "e5", "d5", "exd6"] {
+            board.make_move_san(san).unwrap();
+        }
+
+        assert_eq!(board.played_moves().last().unwrap().uci(), "e5d6");
+
+        // promotions, checks and mate
+        let mut board = Board::from_fen("1r5k/P7/8/8/8/8/8/K5R1 w - - 0 1").unwrap();
+        assert_eq!(board.move_to_san(&mv("a7b8n")), "axb8=N");
+        assert_eq!(board.move_to_san(&mv("a7b8")), "axb8=Q+");
+        assert_eq!(board.parse_san("axb8=R"), Ok(mv("a7b8r")));
+        assert_eq!(
+            board.parse_san("axb8"),
+            Err(SanError::Illegal("axb8".into()))
+        );
+
+        let mut board = Board::default();
+
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            board.make_move_san(san).unwrap();
+        }
+
+        assert_eq!(board.played_moves_san(), vec!["f3", "e5", "g4", "Qh4#"]);
+        assert_eq!(board.parse_san("Ke9"), Err(SanError::Invalid("Ke9".into())));
+    }
+
+    #[test]
+    fn chess960() {
+        let board = Board::chess960(518).unwrap();
+        assert_eq!(board.to_fen(), STARTING_FEN);
+        assert!(board.is_chess960());
+        assert!(Board::chess960(960).is_none());
+
+        let fen = Board::chess960(0).unwrap().to_fen();
+        assert_eq!(
+            fen,
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+
+        let positions = [
+            (
+                "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+                [21, 528, 12189],
+            ),
+            (
+                "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+                [22, 593, 13440],
+            ),
+        ];
+
+        for (fen, counts) in positions.iter() {
+            let mut board = Board::from_fen(fen).unwrap();
+
+            for (depth, nodes) in counts.iter().enumerate() {
+                assert_eq!(board.perft(depth as u32 + 1), *nodes, "{}", fen);
+            }
+        }
+
+        // the king castles short without moving, and long past the other rook's square
+        let fen = "4k3/8/8/8/8/8/8/R5KR w HA - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/R5KR w KQ - 0 1");
+
+        assert_eq!(board.parse_san("O-O"), Ok(mv("g1h1")));
+        assert_eq!(board.move_to_san(&mv("g1a1")), "O-O-O");
+
+        board.make_move_str("g1h1").unwrap();
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/R4RK1 b - - 1 1");
+        assert_eq!(board.played_moves().last().unwrap().uci(), "g1h1");
+
+        board.unmake_move();
+        board.make_move_str("g1a1").unwrap();
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/2KR3R b - - 1 1");
+
+        board.unmake_move();
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/R5KR w KQ - 0 1");
+
+        // Lichess sends the start position and castles by taking the rook
+        let data: GameData = serde_json::from_str(
+            r#"{
+                "clock": { "initial": 180000, "increment": 0 },
+                "rated": false,
+                "white": { "id": "a", "name": "a", "rating": 1500 },
+                "black": { "id": "b", "name": "b", "rating": 1500 },
+                "variant": { "key": "chess960", "name": "Chess960" },
+                "initialFen": "bnrbqnkr/pppppppp/8/8/8/8/PPPPPPPP/BNRBQNKR w KQkq - 0 1"
+            }"#,
+        )
+        .unwrap();
+
+        let mut board = data.start_board();
+        assert!(board.is_chess960());
+
+        for uci in ["f1g3", "f8g6", "g1h1"] {
+            board.make_move_str(uci).unwrap();
+        }
+
+        assert_eq!(
+            board.to_fen(),
+            "bnrbq1kr/pppppppp/6n1/8/8/6N1/PPPPPPPP/BNRBQRK1 b kq - 3 2"
+        );
+    }
+
+    #[test]
+    fn variants() {
+        use Variant::*;
+
+        let perfts = [
+            (Atomic, [20, 400, 8902]),
+            (Antichess, [20, 400, 8067]),
+            (Horde, [8, 128, 1274]),
+            (RacingKings, [21, 421, 11264]),
+            (KingOfTheHill, [20, 400, 8902]),
+        ];
+
+        for (variant, counts) in perfts.iter() {
+            let mut board = Board::new(*variant);
+
+            for (depth, nodes) in counts.iter().enumerate() {
+                assert_eq!(board.perft(depth as u32 + 1), *nodes, "{:?}", variant);
+            }
+        }
+
+        // taking on f7 blows up everything around it but the pawns, the castled king included
+        let fen = "rnbqk2r/ppppbppp/4pn2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 1";
+        let mut board = Board::from_fen_variant(fen, Atomic).unwrap();
+
+        for uci in ["f3g5", "e8g8", "g5f7"] {
+            board.make_move_str(uci).unwrap();
+        }
+
+        assert_eq!(
+            board.to_fen(),
+            "rnbq4/pppp2pp/4p3/8/8/8/PPPPPPPP/RNBQKB1R b KQ - 0 2"
+        );
+        assert_eq!(board.outcome(), Some(Outcome::KingExploded(Side::White)));
+
+        board.unmake_move();
+        assert_eq!(
+            board.to_fen(),
+            "rnbq1rk1/ppppbppp/4pn2/6N1/8/8/PPPPPPPP/RNBQKB1R w KQ - 2 2"
+        );
+
+        // whoever can capture in Antichess has to
+        let mut board = Board::new(Antichess);
+
+        for uci in ["e2e3", "b7b5"] {
+            board.make_move_str(uci).unwrap();
+        }
+
+        assert_eq!(board.legal_moves(), vec![mv("f1b5")]);
+
+        // pawns can promote to kings in Antichess, and only there
+        let fen = "k7/4P3/8/8/8/8/8/4K3 w - - 0 1";
+        let mut board = Board::from_fen_variant(fen, Antichess).unwrap();
+        assert_eq!(board.move_to_san(&mv("e7e8k")), "e8=K");
+
+        board.make_move_san("e8=K").unwrap();
+        assert_eq!(board.to_fen(), "k3K3/8/8/8/8/8/8/4K3 b - - 0 1");
+
+        let board = Board::from_fen_variant(fen, Standard).unwrap();
+        assert!(board.parse_san("e8=K").is_err());
+
+        let mut board =
+            Board::from_fen_variant("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", ThreeCheck).unwrap();
+
+        for uci in ["a1a8", "e8e7", "a8a7", "e7e6", "a7a6"] {
+            board.make_move_str(uci).unwrap();
+        }
+
+        assert_eq!(board.outcome(), Some(Outcome::ThreeChecks(Side::White)));
+
+        let board = Board::from_fen_variant("8/8/8/8/3K4/8/8/k7 b - - 0 1", KingOfTheHill).unwrap();
+        assert_eq!(board.outcome(), Some(Outcome::KingOfTheHill(Side::White)));
+
+        let board = Board::from_fen_variant("4k3/8/8/8/8/8/8/8 w - - 0 1", Horde).unwrap();
+        assert_eq!(board.outcome(), Some(Outcome::HordeCaptured));
+
+        // a pawn pushed two squares from the first rank can't be taken en passant
+        let mut board = Board::from_fen_variant("4k3/8/8/8/8/1p6/8/P7 w - - 0 1", Horde).unwrap();
+        board.make_move_str("a1a3").unwrap();
+        assert!(board.make_move_str("b3a2").is_err());
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/Pp6/8/8 b - - 0 1");
+
+        // black gets a last move to reach the eighth rank too
+        let mut board =
+            Board::from_fen_variant("8/K6k/8/8/8/8/8/8 w - - 0 1", RacingKings).unwrap();
+        board.make_move_str("a7a8").unwrap();
+        assert_eq!(board.outcome(), None);
+
+        board.make_move_str("h7h8").unwrap();
+        assert_eq!(board.outcome(), Some(Outcome::RaceTied));
+
+        let data: GameData = serde_json::from_str(
+            r#"{
+                "clock": { "initial": 180000, "increment": 0 },
+                "rated": false,
+                "white": { "id": "a", "name": "a", "rating": 1500 },
+                "black": { "id": "b", "name": "b", "rating": 1500 },
+                "variant": { "key": "racingKings", "name": "Racing Kings" },
+                "initialFen": "startpos"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(*data.start_board().variant(), RacingKings);
+        assert_eq!(data.start_board().to_fen(), RacingKings.starting_fen());
+    }
+
+    #[test]
+    fn crazyhouse() {
+        let mut board = Board::new(Variant::Crazyhouse);
+
+        for (depth, nodes) in [20, 400, 8902, 197281].iter().enumerate() {
+            assert_eq!(board.perft(depth as u32 + 1), *nodes);
+        }
+
+        assert_eq!(mv("N@f3").to_string(), "N@f3");
+        assert_eq!(mv("p@e4"), ChessMove::new_drop(PieceKind::Pawn, sq("e4")));
+        assert!("K@e4".parse::<ChessMove>().is_err());
+
+        // pawns can be dropped anywhere but the back ranks, other pieces on any empty square
+        let fen = "4k3/8/8/8/8/8/8/4K3[Pn] w - - 0 1";
+        let board = Board::from_fen_variant(fen, Variant::Crazyhouse).unwrap();
+        assert_eq!(board.to_fen(), fen);
+        assert_eq!(board.legal_moves().len(), 5 + 48);
+
+        // in check, a drop has to block it
+        let fen = "4k3/8/8/8/8/8/8/r3K3[N] w - - 0 1";
+        let mut board = Board::from_fen_variant(fen, Variant::Crazyhouse).unwrap();
+        assert_eq!(board.legal_moves().len(), 3 + 3);
+        assert_eq!(board.move_to_san(&mv("N@c1")), "N@c1");
+
+        // a captured promoted piece goes back to the pocket as a pawn
+        let fen = "1r2k3/P7/8/8/8/8/8/4K3[] w - - 0 1";
+        let mut board = Board::from_fen_variant(fen, Variant::Crazyhouse).unwrap();
+
+        board.make_move_str("a7a8q").unwrap();
+        assert_eq!(board.to_fen(), "Q~r2k3/8/8/8/8/8/8/4K3[] b - - 0 1");
+
+        board.make_move_str("b8a8").unwrap();
+        board.make_move_str("e1e2").unwrap();
+        assert_eq!(board.to_fen(), "r3k3/8/8/8/8/8/4K3/8[p] b - - 1 2");
+
+        assert_eq!(
+            board.parse_san("P@a1"),
+            Err(SanError::Illegal("P@a1".into()))
+        );
+        let drop = board.parse_san("@d3").unwrap();
+        assert_eq!(board.move_to_san(&drop), "P@d3+");
+
+        board.make_move(drop).unwrap();
+        assert_eq!(board.to_fen(), "r3k3/8/8/8/8/3p4/4K3/8[] w - - 0 3");
+
+        board.unmake_move();
+        assert_eq!(board.pocket(&Side::Black).count(&PieceKind::Pawn), 1);
+
+        let state: GameState = serde_json::from_str(
+            r#"{
+                "type": "gameState",
+                "moves": "e2e4",
+                "wtime": 180000,
+                "btime": 180000,
+                "status": "started",
+                "crazyhouse": { "pockets": [{ "pawn": 2 }, { "knight": 1, "queen": 1 }] }
+            }"#,
+        )
+        .unwrap();
+
+        let pockets = state.pockets().unwrap();
+        assert_eq!(pockets[0].count(&PieceKind::Pawn), 2);
+        assert_eq!(
+            pockets[1].kinds().collect::<Vec<_>>(),
+            [PieceKind::Knight, PieceKind::Queen]
+        );
+    }
+
+    #[test]
+    fn engine() {
+        let mut searcher = Searcher::new(1);
+
+        // back rank mate
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let info = searcher.search(&board, &Limits::new().with_depth(3), |_| {});
+
+        assert_eq!(info.best_move(), Some(mv("a1a8")));
+        assert_eq!(*info.score(), Score::Mate(1));
+
+        // the same for black, which mustn't take the pawn instead
+        let board = Board::from_fen("6k1/8/8/8/8/8/r4PPP/6K1 b - - 0 1").unwrap();
+        let info = searcher.search(&board, &Limits::new().with_depth(4), |_| {});
+        assert_eq!(info.best_move(), Some(mv("a2a1")));
+
+        // the knight can take the undefended queen
+        searcher.clear();
+        let board = Board::from_fen("4k3/8/3q4/8/4N3/8/8/4K3 w - - 0 1").unwrap();
+        let mut depths = vec![];
+        let info = searcher.search(&board, &Limits::new().with_depth(3), |info| {
+            depths.push(*info.depth())
+        });
+
+        assert_eq!(info.best_move(), Some(mv("e4d6")));
+        assert_eq!(depths, [1, 2, 3]);
+        assert!(matches!(info.score(), Score::Cp(cp) if *cp > 200));
+
+        // a node limit still finishes the first iteration
+        let info = searcher.search(&Board::default(), &Limits::new().with_nodes(1), |_| {});
+        assert_eq!(*info.depth(), 1);
+        assert!(Board::default().is_legal(&info.best_move().unwrap()));
+
+        // a search's own stop flag, set before it starts, stops it after its first depth
+        let stop = Arc::new(AtomicBool::new(true));
+        searcher.set_stop_handle(stop.clone());
+        let info = searcher.search(&Board::default(), &Limits::new().with_depth(4), |_| {});
+        assert_eq!(*info.depth(), 1);
+        assert!(!stop.load(Ordering::Relaxed));
+
+        let budget = time_budget(Duration::from_secs(300), Duration::from_secs(3));
+        assert_eq!(budget, Duration::from_millis(12250));
+        assert_eq!(
+            time_budget(Duration::ZERO, Duration::ZERO),
+            Duration::from_millis(20)
+        );
+    }
+
+    #[test]
+    fn analysis_lines() {
+        let mut searcher = Searcher::new(1);
+
+        let board = Board::from_fen("4k3/8/3q4/8/4N3/8/8/4K3 w - - 0 1").unwrap();
+        let lines = searcher.search_lines(&board, &Limits::new().with_depth(3), 3, |_| {});
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].best_move(), Some(mv("e4d6")));
+        // the knight takes the queen in the best line only
+        assert!(matches!(lines[0].score(), Score::Cp(cp) if *cp > 200));
+        assert!(matches!(lines[1].score(), Score::Cp(cp) if *cp < 200));
+        assert_ne!(lines[1].best_move(), lines[2].best_move());
+
+        // there can't be more lines than legal moves
+        let board = Board::from_fen("k7/8/8/8/8/8/8/KR6 b - - 0 1").unwrap();
+        let lines = searcher.search_lines(&board, &Limits::new().with_depth(2), 3, |_| {});
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].best_move(), Some(mv("a8a7")));
+
+        let mut board = Board::default();
+        let line = [mv("e2e4"), mv("e7e5"), mv("g1f3")];
+        assert_eq!(board.line_to_san(&line), "1. e4 e5 2. Nf3");
+
+        board.make_move(line[0]).unwrap();
+        assert_eq!(board.line_to_san(&line[1..]), "1... e5 2. Nf3");
+
+        assert_eq!(Score::Cp(35).to_string(), "+0.35");
+        assert_eq!(Score::Cp(-120).to_string(), "-1.20");
+        assert_eq!((-Score::Mate(2)).to_string(), "#-2");
+    }
+
+    // a stand-in UCI engine answering over `io`, which returns the commands it got once it quits
+    async fn scripted_engine(io: tokio::io::DuplexStream) -> Vec<String> {
+        let (reader, mut writer) = tokio::io::split(io);
+        let mut lines = BufReader::new(reader).lines();
+        let mut received = vec![];
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            let reply = match line.as_str() {
+                "uci" => concat!(
+                    "id name Stand-in\n",
+                    "option name Hash type spin default 16 min 1 max 64\n",
+                    "option name Skill Level type spin default 20 min 0 max 20\n",
+                    "option name UCI_Chess960 type check default false\n",
+                    "uciok\n"
+                ),
+                "isready" => "readyok\n",
+                "stop" => "bestmove d7d5\n",
+                "quit" => break,
+                // never answers, to time out
+                cmd if cmd.starts_with("go movetime") => "",
+                cmd if cmd.starts_with("go infinite") => "info depth 1 score cp 0 pv d7d5\n",
+                cmd if cmd.starts_with("go") => "info depth 1 score cp 20 pv e7e5\nbestmove e7e5\n",
+                _ => "",
+            };
+
+            received.push(line);
+            writer.write_all(reply.as_bytes()).await.unwrap();
+        }
+
+        received
+    }
+
+    #[tokio::test]
+    async fn uci_client() {
+        let (client_io, engine_io) = tokio::io::duplex(4096);
+        let engine = tokio::spawn(scripted_engine(engine_io));
+
+        let (reader, writer) = tokio::io::split(client_io);
+        let mut client = UciClient::new(reader, writer);
+
+        client.init().await.unwrap();
+        assert_eq!(client.name().as_deref(), Some("Stand-in"));
+        assert!(client.has_option("hash"));
+
+        // only the options the engine knows about are set
+        let config: EngineConfig = toml::from_str(concat!(
+            "path = \"/usr/bin/stockfish\"\nthreads = 2\nhash = 32\n",
+            "[options]\n\"Skill Level\" = 5\nContempt = 0\n"
+        ))
+        .unwrap();
+        assert_eq!(config.name(), "stockfish");
+        client.configure(&config).await.unwrap();
+
+        let mut board = Board::default();
+        board.make_move(mv("e2e4")).unwrap();
+        client.set_position(&board).await.unwrap();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let params = GoParams::clock(60000, 60000, 1000, 1000);
+        let best = client.go(&params, &stop, Duration::from_secs(5)).await;
+        assert_eq!(best.unwrap(), Some(mv("e7e5")));
+
+        // stopping an infinite search still waits for its best move
+        let stopper = stop.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stopper.store(true, Ordering::Relaxed);
+        });
+
+        let best = client
+            .go(&GoParams::infinite(), &stop, Duration::from_secs(5))
+            .await;
+        assert_eq!(best.unwrap(), Some(mv("d7d5")));
+
+        stop.store(false, Ordering::Relaxed);
+        let best = client
+            .go(&GoParams::movetime(1000), &stop, Duration::from_millis(100))
+            .await;
+        assert!(matches!(best, Err(EngineError::Timeout("bestmove"))));
+
+        // Chess960 castling is only understood once the engine is told it's playing Chess960
+        let mut chess960 = Board::default();
+        chess960.set_chess960(true);
+        client.set_position(&chess960).await.unwrap();
+        client.set_position(&chess960).await.unwrap();
+        client.set_position(&board).await.unwrap();
+
+        client.send("quit").await.unwrap();
+        assert!(matches!(client.is_ready().await, Err(EngineError::Exited)));
+
+        let received = engine.await.unwrap();
+        let expected = [
+            "uci",
+            "setoption name Hash value 32",
+            "setoption name Skill Level value 5",
+            "isready",
+            &format!("position fen {} moves e2e4", STARTING_FEN),
+            "go wtime 60000 btime 60000 winc 1000 binc 1000",
+            "go infinite",
+            "stop",
+            "go movetime 1000",
+            "setoption name UCI_Chess960 value true",
+            &format!("position fen {}", chess960.to_fen()),
+            &format!("position fen {}", chess960.to_fen()),
+            "setoption name UCI_Chess960 value false",
+            &format!("position fen {} moves e2e4", STARTING_FEN),
+        ];
+        assert_eq!(received, expected);
+    }
+
+    // the output of the UCI engine, shared with its search thread
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn uci_engine() {
+        let params: GoParams = "wtime 60000 btime 30000 winc 1000 binc 500 movestogo 20"
+            .parse()
+            .unwrap();
+        assert_eq!(params, GoParams::clock(60000, 30000, 1000, 500));
+        assert_eq!(
+            *params.limits(&Side::Black).movetime(),
+            Some(Duration::from_millis(1375))
+        );
+        assert_eq!(*params.limits(&Side::White).depth(), None);
+
+        let params: GoParams = "depth 5 movetime 200".parse().unwrap();
+        assert_eq!(*params.limits(&Side::White).depth(), Some(5));
+        assert_eq!(
+            *params.limits(&Side::White).movetime(),
+            Some(Duration::from_millis(200))
+        );
+        assert!("depth x".parse::<GoParams>().is_err());
+
+        let input = [
+            "uci",
+            "setoption name Hash value 4",
+            "position startpos moves e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 e1g1",
+            "d",
+            "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+            "go depth 3",
+            "isready",
+            "position startpos moves e2e4 e2e4",
+            "d",
+            "setoption name UCI_Chess960 value true",
+            "position fen rkrbbqnn/pppppppp/8/8/8/8/PPPPPPPP/RKRBBQNN w KQkq - 0 1 moves g2g3",
+            "go infinite",
+            "stop",
+            "position startpos",
+            "go depth 1",
+        ]
+        .join("\n");
+
+        let output = SharedOutput::default();
+        server::run(input.as_bytes(), output.clone()).unwrap();
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], "id name zchess 0.1.0");
+        assert!(lines.contains(&"uciok"));
+        assert!(lines.contains(&"readyok"));
+        assert!(lines
+            .contains(&"info string illegal move 'e2e4' in 'position startpos moves e2e4 e2e4'"));
+
+        // castling the standard way, and the illegal move leaves the previous position as it was
+        let fens: Vec<&str> = lines
+            .iter()
+            .filter(|line| line.starts_with("Fen: "))
+            .copied()
+            .collect();
+        let castled = "Fen: r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4";
+        assert_eq!(fens, [castled, "Fen: 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"]);
+
+        assert!(lines.iter().any(
+            |line| line.starts_with("info depth 1 multipv 1 score mate 1 ")
+                && line.ends_with("pv a1a8")
+        ));
+
+        let best: Vec<&&str> = lines
+            .iter()
+            .filter(|line| line.starts_with("bestmove"))
+            .collect();
+        assert_eq!(best.len(), 3);
+        assert_eq!(*best[0], "bestmove a1a8");
+    }
+
+    #[tokio::test]
+    async fn bot_events() {
+        // a challenge split across chunks, with keep-alive newlines in between
+        let chunks: Vec<Result<&[u8], std::io::Error>> = vec![
+            Ok(b"\n{\"type\":\"challenge\",\"challenge\":{\"id\":\"abc\",\"challenger\":"),
+            Ok(b"{\"id\":\"bob\",\"name\":\"Bob\",\"rating\":1500},\"variant\":{\"key\":\"chess960\",\"name\":\"Chess960\"},"),
+            Ok(b"\"rated\":true,\"timeControl\":{\"type\":\"clock\",\"limit\":180,\"increment\":2}}}\n\n"),
+            Ok(b"{\"type\":\"gameStart\",\"game\":{\"id\":\"xyz\"}}"),
+        ];
+
+        let mut events = NdjsonStream::new(futures::stream::iter(chunks));
+
+        let challenge = match Event::from_json(events.next().await.unwrap().unwrap()).unwrap() {
+            Event::Challenge { challenge } => challenge,
+            event => panic!("expected a challenge, got {:?}", event),
+        };
+
+        assert_eq!(challenge.to_string(), "3+2 rated Chess960 from Bob (1500)");
+
+        match Event::from_json(events.next().await.unwrap().unwrap()).unwrap() {
+            Event::GameStart { game } => assert_eq!(game.id(), "xyz"),
+            event => panic!("expected a game start, got {:?}", event),
+        }
+
+        assert!(events.next().await.is_none());
+
+        let config: BotConfig = toml::from_str("variants = [\"standard\"]").unwrap();
+        assert_eq!(config.check(&challenge), Err(DeclineReason::Variant));
+
+        let config: BotConfig =
+            toml::from_str("variants = [\"chess960\"]\nmin_initial = 300").unwrap();
+        assert_eq!(config.check(&challenge), Err(DeclineReason::TooFast));
+
+        let config: BotConfig =
+            toml::from_str("variants = [\"chess960\"]\nmax_increment = 1").unwrap();
+        assert_eq!(config.check(&challenge), Err(DeclineReason::TooSlow));
+
+        let config: BotConfig = toml::from_str("variants = [\"chess960\"]\nrated = false").unwrap();
+        assert_eq!(config.check(&challenge), Err(DeclineReason::Casual));
+
+        let config: BotConfig = toml::from_str("variants = [\"chess960\"]").unwrap();
+        assert_eq!(config.check(&challenge), Ok(()));
+
+        let correspondence: Challenge = serde_json::from_str(
+            r#"{"id":"def","variant":{"key":"standard","name":"Standard"},
+                "timeControl":{"type":"correspondence","daysPerTurn":3}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            BotConfig::default().check(&correspondence),
+            Err(DeclineReason::TimeControl)
+        );
+        assert_eq!(
+            correspondence.to_string(),
+            "3 days per move casual Standard"
+        );
+
+        let config: BotConfig = toml::from_str("correspondence = true").unwrap();
+        assert_eq!(config.check(&correspondence), Ok(()));
+
+        let mut game = online_game(serde_json::json!({
+            "type": "gameFull",
+            "id": "xyz",
+            "clock": {"initial": 180000, "increment": 2000},
+            "rated": false,
+            "white": {"id": "zbot", "name": "zbot"},
+            "black": {"id": "bob", "name": "Bob", "rating": 1500},
+            "state": {"moves": "e2e4 e7e5", "wtime": 180000, "btime": 180000, "status": "started"}
+        }));
+
+        assert_eq!(game.board().played_moves().len(), 2);
+
+        let state: GameState = serde_json::from_str(
+            r#"{"moves":"e2e4 e7e5 g1f3","wtime":179000,"btime":180000,"status":"started"}"#,
+        )
+        .unwrap();
+
+        game.apply_state(state).unwrap();
+        assert_eq!(*game.board().turn(), Side::Black);
+    }
+
+    #[test]
+    fn correspondence_games() {
+        // correspondence games have no clock, only the days per turn
+        let game = online_game(serde_json::json!({
+            "type": "gameFull",
+            "id": "corr1234",
+            "daysPerTurn": 3,
+            "rated": true,
+            "white": {"id": "alice", "name": "Alice", "rating": 1800},
+            "black": {"id": "bob", "name": "Bob", "rating": 1700},
+            "state": {
+                "moves": "d2d4",
+                "wtime": 259200000,
+                "btime": 259200000,
+                "status": "started"
+            }
+        }));
+
+        assert!(game.data().clock().is_none());
+        assert_eq!(*game.data().days_per_turn(), Some(3));
+        assert_eq!(game.data().increment(), 0);
+        assert_eq!(game.data().time_control().to_string(), "3 days per move");
+        assert_eq!(game.board().played_moves().len(), 1);
+        assert!(game
+            .to_pgn("https://lichess.org")
+            .contains("[TimeControl \"-\"]"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn accepted_challenge() {
+        use tokio::net::TcpListener;
+
+        // lila accepting the challenge and streaming the game it started
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                let (read, mut write) = socket.into_split();
+                let mut lines = BufReader::new(read).lines();
+
+                let request = lines.next_line().await.unwrap().unwrap_or_default();
+                while let Some(line) = lines.next_line().await.unwrap() {
+                    if line.is_empty() {
+                        break;
+                    }
+                }
+
+                let body = match request.as_str() {
+                    "POST /api/challenge/corr1234/accept HTTP/1.1" => r#"{"ok":true}"#.into(),
+                    "GET /api/board/game/stream/corr1234 HTTP/1.1" => format!(
+                        "{}\n",
+                        serde_json::json!({
+                            "type": "gameFull",
+                            "id": "corr1234",
+                            "daysPerTurn": 3,
+                            "rated": true,
+                            "white": {"id": "alice", "name": "Alice"},
+                            "black": {"id": "bob", "name": "Bob"},
+                            "state": {"moves": "", "wtime": 259200000, "btime": 259200000,
+                                      "status": "started"}
+                        })
+                    ),
+                    _ => String::new(),
+                };
+
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+
+                write.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let config = toml::from_str(&format!("token = \"lip_test\"\nlichess_url = \"{}\"", url));
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut app = App::with_config(tx, config.unwrap());
+
+        let perf = r#"{"games": 0, "rating": 1500, "rd": 350, "prog": 0}"#;
+        let user: User = serde_json::from_str(&format!(
+            r#"{{"id": "bob", "username": "Bob", "online": true,
+                "perfs": {{"bullet": {0}, "blitz": {0}, "rapid": {0}, "classical": {0},
+                "correspondence": {0}}}}}"#,
+            perf
+        ))
+        .unwrap();
+        app.set_own_info(user);
+
+        let challenge: Challenge = serde_json::from_str(
+            r#"{"id": "corr1234", "challenger": {"id": "alice", "name": "Alice"},
+                "variant": {"key": "standard", "name": "Standard"}, "rated": true,
+                "timeControl": {"type": "correspondence", "daysPerTurn": 3}}"#,
+        )
+        .unwrap();
+
+        app.challenge_received(challenge.clone());
+        app.accept_challenge().await;
+        assert_eq!(app.status(), &None);
+
+        // the accepted challenge's `gameStart` opens its game
+        app.init_new_game(challenge.id());
+
+        match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            Message::GameDataInit(game) => app.start_game(*game),
+            _ => panic!("expected the game data"),
+        }
+
+        assert_eq!(*app.ui_state(), UIState::Game);
+        assert_eq!(*app.board_display_side(), Side::Black);
+
+        let game = app.game().as_ref().unwrap();
+        assert_eq!(game.id(), challenge.id());
+        assert_eq!(game.data().time_control(), *challenge.time_control());
+    }
+
+    #[test]
+    fn online_outcomes() {
+        let mut game = online_game(serde_json::json!({
+            "type": "gameFull",
+            "id": "abcd1234",
+            "clock": {"initial": 300000, "increment": 0},
+            "rated": true,
+            "white": {"id": "alice", "name": "Alice"},
+            "black": {"id": "bob", "name": "Bob"},
+            "state": {"moves": "e2e4", "wtime": 300000, "btime": 300000, "status": "started"}
+        }));
+
+        assert!(!game.is_over());
+        assert!(!game.analysis_allowed());
+        assert_eq!(game.result(), "*");
+
+        let state = |moves: &str, status: &str, winner: &str| -> GameState {
+            serde_json::from_str(&format!(
+                r#"{{"moves":"{}","wtime":1000,"btime":1000,"status":"{}","winner":"{}"}}"#,
+                moves, status, winner
+            ))
+            .unwrap()
+        };
+
+        game.apply_state(state("e2e4 e7e5", "resign", "white"))
+            .unwrap();
+        assert_eq!(*game.outcome(), Some(Outcome::Resignation(Side::White)));
+        assert!(game.analysis_allowed());
+        assert_eq!(game.result(), "1-0");
+
+        game.apply_state(state("f2f3 e7e5 g2g4 d8h4", "mate", "black"))
+            .unwrap();
+        assert_eq!(*game.outcome(), Some(Outcome::Checkmate(Side::Black)));
+
+        game.apply_state(state("e2e4", "outoftime", "black"))
+            .unwrap();
+        assert_eq!(*game.outcome(), Some(Outcome::Timeout(Side::Black)));
+
+        game.apply_state(state("e2e4 e7e5", "draw", "")).unwrap();
+        assert_eq!(*game.outcome(), Some(Outcome::Draw));
+        assert_eq!(game.result(), "1/2-1/2");
+
+        game.apply_state(state("", "aborted", "")).unwrap();
+        assert_eq!(*game.outcome(), Some(Outcome::Aborted));
+        assert_eq!(game.result(), "*");
+    }
+
+    #[test]
+    fn seek_form() {
+        let mut form = SeekForm::default();
+
+        assert_eq!(
+            form.params(None),
+            [
+                ("rated", "false".to_string()),
+                ("variant", "standard".into()),
+                ("color", "random".into()),
+                ("time", "10".into()),
+                ("increment", "5".into()),
+            ]
+        );
+
+        // the presets wrap around to the longest correspondence one
+        for _ in 0..4 {
+            form.change(false);
+        }
+
+        assert_eq!(form.time_control().to_string(), "14 days per move");
+        assert!(form.is_correspondence());
+
+        form.move_field(false);
+        form.change(true);
+        form.move_field(false);
+        form.change(false);
+        form.move_field(false);
+        form.change(true);
+        form.move_field(false);
+        form.change(true);
+        form.change(true);
+        assert_eq!(form.field(), SeekField::RatingRange);
+
+        let perf = r#"{"games": 10, "rating": 1500, "rd": 60, "prog": 0}"#;
+        let user: User = serde_json::from_str(&format!(
+            r#"{{"id": "alice", "username": "Alice", "online": true,
+                "perfs": {{"bullet": {0}, "blitz": {0}, "rapid": {0}, "classical": {0},
+                "correspondence": {{"games": 0, "rating": 1800, "rd": 300, "prog": 0}},
+                "chess960": {{"games": 5, "rating": 1900, "rd": 80, "prog": 0}}}}}}"#,
+            perf
+        ))
+        .unwrap();
+
+        assert_eq!(
+            form.value(SeekField::RatingRange, Some(&user)),
+            "±200 (1700-2100)"
+        );
+        assert_eq!(
+            form.params(Some(&user)),
+            [
+                ("rated", "true".to_string()),
+                ("variant", "chess960".into()),
+                ("color", "black".into()),
+                ("days", "14".into()),
+                ("ratingRange", "1700-2100".into()),
+            ]
+        );
+
+        // the range of standard games is around the rating in the speed, and that of a variant the
+        // player hasn't played around the rating Lichess starts them at
+        form.change_field(SeekField::Variant, false);
+        assert_eq!(form.rating_range(Some(&user)), Some((1600, 2000)));
+
+        form.change_field(SeekField::Variant, true);
+        form.change_field(SeekField::Variant, true);
+        assert_eq!(form.rating_range(Some(&user)), Some((1300, 1700)));
+
+        // without the player's rating, there's no range to send
+        assert_eq!(form.params(None).len(), 4);
+    }
+
+    #[test]
+    fn challenge_form() {
+        let mut form = ChallengeForm::default();
+        assert!(form.editing_username());
+
+        for c in "bobb".chars() {
+            form.push_char(c);
+        }
+
+        form.pop_char();
+        assert_eq!(form.username(), "bob");
+
+        // the username can't be changed like the settings
+        form.change(true);
+        form.move_field(false);
+        form.change(false);
+        form.move_field(false);
+        form.change(true);
+
+        assert_eq!(form.field(), Some(SeekField::Mode));
+        assert_eq!(
+            form.params(),
+            [
+                ("rated", "true".to_string()),
+                ("variant", "standard".into()),
+                ("color", "random".into()),
+                ("clock.limit", "300".into()),
+                ("clock.increment", "3".into()),
+            ]
+        );
+
+        let declined: Challenge = serde_json::from_str(
+            r#"{"id": "abc", "destUser": {"id": "bob", "name": "Bob"}, "rated": true,
+                "variant": {"key": "standard", "name": "Standard"},
+                "timeControl": {"type": "clock", "limit": 300, "increment": 3},
+                "declineReason": "I'm not accepting challenges at the moment."}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Outgoing::Pending(declined.clone()).to_string(),
+            "waiting for Bob to accept 5+3 rated Standard"
+        );
+        assert_eq!(
+            Outgoing::Declined(declined).to_string(),
+            "Bob declined: I'm not accepting challenges at the moment."
+        );
+    }
+
+    #[test]
+    fn offers_and_takebacks() {
+        let state = |moves: &str, flags: &str| -> GameState {
+            serde_json::from_str(&format!(
+                r#"{{"moves": "{}", "wtime": 60000, "btime": 60000, "status": "started"{}}}"#,
+                moves, flags
+            ))
+            .unwrap()
+        };
+
+        let mut game = Game::online(
+            "abc",
+            GameData::with_clock(60000, 0),
+            state("e2e4 e7e5 g1f3", ""),
+        );
+
+        assert!(!game.state().draw_offered(&Side::White));
+
+        game.apply_state(state(
+            "e2e4 e7e5 g1f3",
+            r#", "bdraw": true, "wtakeback": true"#,
+        ))
+        .unwrap();
+
+        assert!(game.state().draw_offered(&Side::Black));
+        assert!(!game.state().draw_offered(&Side::White));
+        assert!(game.state().takeback_proposed(&Side::White));
+
+        // an accepted takeback comes as a state with the move gone
+        game.apply_state(state("e2e4 e7e5", "")).unwrap();
+
+        assert!(!game.state().takeback_proposed(&Side::White));
+        assert_eq!(game.board().played_moves().len(), 2);
+        assert_eq!(*game.board().turn(), Side::White);
+        assert_eq!(
+            game.board().to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+    }
+
+    #[test]
+    fn chat() {
+        assert_eq!(
+            chat::wrap("good game, well played", 10),
+            ["good game,", "well", "played"]
+        );
+        assert_eq!(
+            chat::wrap("aaaaaaaaaaaa bb", 5),
+            ["aaaaa", "aaaaa", "aa bb"]
+        );
+        assert_eq!(chat::wrap("", 5), [""]);
+
+        let messages: Vec<ChatMessage> = serde_json::from_str(
+            r#"[{"username": "alice", "room": "player", "text": "hi"},
+                {"username": "carol", "room": "spectator", "text": "nice move"},
+                {"username": "bob", "room": "player", "text": "hello there"},
+                {"username": "alice", "room": "player", "text": "gl"}]"#,
+        )
+        .unwrap();
+
+        let mut chat = Chat::default();
+        assert_eq!(
+            chat.visible_lines(&messages, 10, 3),
+            ["bob: hello", "there", "alice: gl"]
+        );
+
+        // scrolled back as far as the first message, and no further
+        chat.scroll_by(5, true);
+        chat.clamp_scroll(&messages, 10, 3);
+        assert_eq!(*chat.scroll(), 1);
+        assert_eq!(
+            chat.visible_lines(&messages, 10, 3),
+            ["alice: hi", "bob: hello", "there"]
+        );
+
+        chat.toggle_room();
+        assert_eq!(*chat.room(), Room::Spectator);
+        assert_eq!(chat.visible_lines(&messages, 20, 3), ["carol: nice move"]);
+
+        chat.open_input();
+        chat.push_char('g');
+        chat.push_char('g');
+        assert_eq!(chat.take_input(), Some("gg".to_string()));
+        assert_eq!(chat.input(), &None);
+    }
+
+    #[test]
+    fn pgn_export() {
+        let mut game = Game::local(Board::default(), 300000, 3000);
+
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            game.board_mut().make_move_san(san).unwrap();
+        }
+
+        game.set_outcome(game.board().outcome());
+
+        let pgn = game.to_pgn("https://lichess.org");
+        let date = fmt_date(*game.started());
+
+        assert_eq!(
+            pgn,
+            format!(
+                "[Event \"Local game\"]\n[Site \"?\"]\n[Date \"{}\"]\n[Round \"-\"]\n\
+                 [White \"?\"]\n[Black \"?\"]\n[Result \"0-1\"]\n[TimeControl \"300+3\"]\n\n\
+                 1. f3 e5 2. g4 Qh4# 0-1\n",
+                date
+            )
+        );
+
+        // a finished online game is written with the result from its final state
+        let data: GameData = serde_json::from_str(
+            r#"{"clock": {"initial": 180000, "increment": 2000}, "rated": true,
+                "white": {"id": "alice", "name": "Alice"}, "black": {"id": "bob", "name": "Bob"}}"#,
+        )
+        .unwrap();
+        let state: GameState = serde_json::from_str(
+            r#"{"moves": "e2e4 e7e5", "wtime": 1000, "btime": 1000, "status": "resign",
+                "winner": "black"}"#,
+        )
+        .unwrap();
+
+        let pgn = Game::online("abcd1234", data, state).to_pgn("http://localhost:9663");
+        assert!(pgn.contains("[Site \"http://localhost:9663/abcd1234\"]"));
+        assert!(pgn.contains("[Result \"0-1\"]"));
+        assert!(pgn.ends_with("1. e4 e5 0-1\n"));
+
+        assert_eq!(
+            fmt_date(UNIX_EPOCH + Duration::from_secs(1633305600)),
+            "2021.10.04"
+        );
+    }
+
+    #[test]
+    fn pgn_import() {
+        let input = r#"[Event "Casual game"]
+[White "alice"]
+[Black "bob"]
+[Result "1-0"]
+
+{ An opening trap. } 1. e4 e5 2. Bc4 (2. Nf3 Nc6 (2... d6) 3. Bb5) 2... Nc6 $6
+3. Qh5 Nf6?? { Missing the threat. } ; the only move was g6
+4. Qxf7# 1-0
+
+[Event "Second game"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 40"]
+
+40... Kd7 41.e4 Ke6 *
+"#;
+
+        let games = pgn::parse(input).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let game = &games[0];
+        assert_eq!(game.tag("White").unwrap(), "alice");
+        assert_eq!(game.result(), "1-0");
+        assert_eq!(game.comments(), &vec!["An opening trap.".to_string()]);
+
+        let sans: Vec<&str> = game.moves().iter().map(|mv| mv.san().as_str()).collect();
+        assert_eq!(sans, ["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"]);
+
+        assert_eq!(game.moves()[3].nags(), &vec![6]);
+        assert_eq!(game.moves()[5].comments().len(), 2);
+
+        let mut replay = Replay::new(game.clone()).unwrap();
+        replay.to_end();
+        assert_eq!(
+            replay.board().outcome(),
+            Some(Outcome::Checkmate(Side::White))
+        );
+
+        replay.back();
+        assert_eq!(*replay.ply(), 6);
+        assert_eq!(
+            replay.comments(),
+            &vec![
+                "Missing the threat.".to_string(),
+                "the only move was g6".to_string()
+            ]
+        );
+
+        replay.to_start();
+        assert_eq!(replay.board().to_fen(), STARTING_FEN);
+
+        let mut replay = Replay::new(games[1].clone()).unwrap();
+        replay.to_end();
+        assert_eq!(replay.board().to_fen(), "8/8/4k3/8/4P3/8/8/4K3 w - - 1 42");
+
+        assert_eq!(
+            pgn::parse("1. e4 { unterminated"),
+            Err(PgnError::UnterminatedComment)
+        );
+        assert_eq!(
+            pgn::parse("1. e4 (1. d4"),
+            Err(PgnError::UnbalancedVariation)
+        );
+
+        let illegal = pgn::parse("1. e4 e5 2. Ke3").unwrap().remove(0);
+        assert!(matches!(
+            Replay::new(illegal),
+            Err(PgnError::InvalidMove(3, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn lichess_client() {
+        use reqwest::StatusCode;
+        use tokio::net::TcpListener;
+
+        assert_eq!(
+            lichess::error_message(StatusCode::BAD_REQUEST, r#"{"error":"Not your turn"}"#),
+            "Not your turn"
+        );
+        assert_eq!(
+            lichess::error_message(
+                StatusCode::BAD_REQUEST,
+                r#"{"error":{"time":["Must be at least 0"]}}"#
+            ),
+            "time: Must be at least 0"
+        );
+        assert_eq!(
+            lichess::error_message(StatusCode::NOT_FOUND, ""),
+            "Not Found"
+        );
+
+        // a server answering every request by its path, the way lila would
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                let (read, mut write) = socket.into_split();
+                let mut lines = BufReader::new(read).lines();
+
+                let request = lines.next_line().await.unwrap().unwrap_or_default();
+                let mut auth = String::new();
+
+                while let Some(line) = lines.next_line().await.unwrap() {
+                    if line.is_empty() {
+                        break;
+                    }
+
+                    if let Some(value) = line.strip_prefix("authorization: ") {
+                        auth = value.into();
+                    }
+                }
+
+                let (status, body) = match (request.as_str(), auth.as_str()) {
+                    (_, auth) if auth != "Bearer lip_test" => ("401 Unauthorized", "{}"),
+                    ("GET /api/stream/event HTTP/1.1", _) => (
+                        "200 OK",
+                        "{\"type\":\"gameStart\",\"game\":{\"id\":\"abcd1234\"}}\n\n",
+                    ),
+                    ("POST /api/board/game/abcd1234/resign HTTP/1.1", _) => {
+                        ("400 Bad Request", r#"{"error":"Not your turn"}"#)
+                    }
+                    _ => ("404 Not Found", ""),
+                };
+
+                let response = format!(
+                    "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+
+                write.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let client = Lichess::with_url(&url, "lip_test");
+        assert_eq!(client.base_url(), &url.trim_end_matches('/').to_string());
+
+        let mut events = client.stream_events().await.unwrap();
+        let event = Event::from_json(events.next().await.unwrap().unwrap()).unwrap();
+        assert!(matches!(event, Event::GameStart { game } if game.id() == "abcd1234"));
+        assert!(events.next().await.is_none());
+
+        assert!(matches!(
+            client.resign("abcd1234").await,
+            Err(LichessError::Status(StatusCode::BAD_REQUEST, e)) if e == "Not your turn"
+        ));
+        assert!(matches!(
+            client.abort("abcd1234").await,
+            Err(LichessError::Status(StatusCode::NOT_FOUND, e)) if e == "Not Found"
+        ));
+
+        let unauthorized = Lichess::with_url(&url, "");
+        assert!(matches!(
+            unauthorized.abort("abcd1234").await,
+            Err(LichessError::Status(StatusCode::UNAUTHORIZED, _))
+        ));
     }
 }
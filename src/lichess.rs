@@ -0,0 +1,270 @@
+use crate::challenge::{Challenge, DeclineReason};
+use crate::chat::Room;
+use crate::config::Config;
+use crate::stream::NdjsonStream;
+
+use bytes::Bytes;
+use futures::stream::{BoxStream, StreamExt};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+
+/// A streamed response, one JSON value per line.
+pub type LichessStream = NdjsonStream<BoxStream<'static, reqwest::Result<Bytes>>>;
+
+#[derive(Debug)]
+pub enum LichessError {
+    Http(reqwest::Error),
+    /// The status of a failed request, with the error Lichess gave for it.
+    Status(StatusCode, String),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for LichessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use LichessError::*;
+
+        match self {
+            Http(e) => write!(f, "{}", e),
+            Status(status, error) => write!(f, "{} ({})", error, status),
+            Json(e) => write!(f, "invalid response from Lichess: {}", e),
+        }
+    }
+}
+
+impl Error for LichessError {}
+
+impl From<reqwest::Error> for LichessError {
+    fn from(e: reqwest::Error) -> Self {
+        LichessError::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for LichessError {
+    fn from(e: serde_json::Error) -> Self {
+        LichessError::Json(e)
+    }
+}
+
+/// The Lichess API, authenticated with the token from the configuration. Clones share the same
+/// connection pool.
+#[derive(Debug, Clone)]
+pub struct Lichess {
+    client: Client,
+    base_url: String,
+    token: String,
+}
+
+impl Lichess {
+    pub fn new(config: &Config) -> Self {
+        Self::with_url(config.lichess_url(), config.token())
+    }
+
+    /// A client for the Lichess instance at `base_url`, e.g. `http://localhost:9663`.
+    pub fn with_url(base_url: &str, token: &str) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').into(),
+            token: format!("Bearer {}", token),
+        }
+    }
+
+    pub fn base_url(&self) -> &String {
+        &self.base_url
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.client
+            .get(format!("{}{}", self.base_url, path))
+            .header("Authorization", &self.token)
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.client
+            .post(format!("{}{}", self.base_url, path))
+            .header("Authorization", &self.token)
+    }
+
+    // sends the request, turning error statuses into the error from the response
+    async fn send(request: RequestBuilder) -> Result<Response, LichessError> {
+        let res = request.send().await?;
+        let status = res.status();
+
+        if status.is_success() {
+            return Ok(res);
+        }
+
+        let body = res.text().await.unwrap_or_default();
+        Err(LichessError::Status(status, error_message(status, &body)))
+    }
+
+    async fn json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, LichessError> {
+        let body = Self::send(request).await?.text().await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    async fn stream(request: RequestBuilder) -> Result<LichessStream, LichessError> {
+        let res = Self::send(request).await?;
+        Ok(NdjsonStream::new(res.bytes_stream().boxed()))
+    }
+
+    /// `GET /api/account`, the logged in player, usually as a `User`.
+    pub async fn account<T: DeserializeOwned>(&self) -> Result<T, LichessError> {
+        Self::json(self.get("/api/account")).await
+    }
+
+    /// `GET /api/stream/event`: games starting and finishing, and challenges.
+    pub async fn stream_events(&self) -> Result<LichessStream, LichessError> {
+        Self::stream(self.get("/api/stream/event")).await
+    }
+
+    /// `GET /api/board/game/stream/{id}`: the game, then its states and chat.
+    pub async fn stream_game(&self, id: &str) -> Result<LichessStream, LichessError> {
+        Self::stream(self.get(&format!("/api/board/game/stream/{}", id))).await
+    }
+
+    /// `GET /api/bot/game/stream/{id}`, the game stream of a BOT account.
+    pub async fn stream_bot_game(&self, id: &str) -> Result<LichessStream, LichessError> {
+        Self::stream(self.get(&format!("/api/bot/game/stream/{}", id))).await
+    }
+
+    /// `POST /api/board/seek`. A real-time seek lasts as long as the returned stream, and is
+    /// canceled by dropping it, while a correspondence one is created right away.
+    pub async fn seek(
+        &self,
+        params: &[(&'static str, String)],
+    ) -> Result<LichessStream, LichessError> {
+        Self::stream(self.post("/api/board/seek").form(params)).await
+    }
+
+    /// `POST /api/board/game/{id}/move/{move}`, with the move in UCI notation.
+    pub async fn make_move(&self, id: &str, uci: &str) -> Result<(), LichessError> {
+        Self::send(self.post(&format!("/api/board/game/{}/move/{}", id, uci))).await?;
+        Ok(())
+    }
+
+    /// `POST /api/bot/game/{id}/move/{move}`, a move of a BOT account.
+    pub async fn make_bot_move(&self, id: &str, uci: &str) -> Result<(), LichessError> {
+        Self::send(self.post(&format!("/api/bot/game/{}/move/{}", id, uci))).await?;
+        Ok(())
+    }
+
+    /// `POST /api/board/game/{id}/abort`
+    pub async fn abort(&self, id: &str) -> Result<(), LichessError> {
+        Self::send(self.post(&format!("/api/board/game/{}/abort", id))).await?;
+        Ok(())
+    }
+
+    /// `POST /api/board/game/{id}/resign`
+    pub async fn resign(&self, id: &str) -> Result<(), LichessError> {
+        Self::send(self.post(&format!("/api/board/game/{}/resign", id))).await?;
+        Ok(())
+    }
+
+    /// `POST /api/bot/game/{id}/resign`, a resignation of a BOT account.
+    pub async fn resign_bot_game(&self, id: &str) -> Result<(), LichessError> {
+        Self::send(self.post(&format!("/api/bot/game/{}/resign", id))).await?;
+        Ok(())
+    }
+
+    /// `POST /api/board/game/{id}/draw/{yes|no}`: offers or accepts a draw, or declines one.
+    pub async fn draw(&self, id: &str, yes: bool) -> Result<(), LichessError> {
+        Self::send(self.post(&format!("/api/board/game/{}/draw/{}", id, answer(yes)))).await?;
+        Ok(())
+    }
+
+    /// `POST /api/board/game/{id}/takeback/{yes|no}`: proposes or accepts a takeback, or declines
+    /// one.
+    pub async fn takeback(&self, id: &str, yes: bool) -> Result<(), LichessError> {
+        Self::send(self.post(&format!("/api/board/game/{}/takeback/{}", id, answer(yes)))).await?;
+        Ok(())
+    }
+
+    /// `POST /api/board/game/{id}/chat`
+    pub async fn chat(&self, id: &str, room: &Room, text: &str) -> Result<(), LichessError> {
+        let request = self
+            .post(&format!("/api/board/game/{}/chat", id))
+            .form(&[("room", room.key()), ("text", text)]);
+
+        Self::send(request).await?;
+        Ok(())
+    }
+
+    /// `POST /api/challenge/{username}`, the challenge sent.
+    pub async fn challenge(
+        &self,
+        username: &str,
+        params: &[(&'static str, String)],
+    ) -> Result<Challenge, LichessError> {
+        let request = self
+            .post(&format!("/api/challenge/{}", username))
+            .form(params);
+
+        let json: Value = Self::json(request).await?;
+
+        // older versions of the API wrap the challenge in a `challenge` field
+        let challenge = json.get("challenge").cloned().unwrap_or(json);
+
+        Ok(serde_json::from_value(challenge)?)
+    }
+
+    /// `POST /api/challenge/{id}/accept`
+    pub async fn accept_challenge(&self, id: &str) -> Result<(), LichessError> {
+        Self::send(self.post(&format!("/api/challenge/{}/accept", id))).await?;
+        Ok(())
+    }
+
+    /// `POST /api/challenge/{id}/decline`
+    pub async fn decline_challenge(
+        &self,
+        id: &str,
+        reason: DeclineReason,
+    ) -> Result<(), LichessError> {
+        let request = self
+            .post(&format!("/api/challenge/{}/decline", id))
+            .form(&[("reason", reason.key())]);
+
+        Self::send(request).await?;
+        Ok(())
+    }
+
+    /// `POST /api/challenge/{id}/cancel`
+    pub async fn cancel_challenge(&self, id: &str) -> Result<(), LichessError> {
+        Self::send(self.post(&format!("/api/challenge/{}/cancel", id))).await?;
+        Ok(())
+    }
+}
+
+fn answer(yes: bool) -> &'static str {
+    match yes {
+        true => "yes",
+        false => "no",
+    }
+}
+
+/// The error in the body of a failed request, which Lichess sends as `{"error": "..."}`, or as
+/// `{"error": {"field": ["..."]}}` for invalid forms.
+pub fn error_message(status: StatusCode, body: &str) -> String {
+    let json: Option<Value> = serde_json::from_str(body).ok();
+
+    match json.as_ref().and_then(|json| json.get("error")) {
+        Some(Value::String(error)) => error.clone(),
+        Some(Value::Object(fields)) => fields
+            .iter()
+            .map(|(field, errors)| match errors {
+                Value::Array(errors) => {
+                    let errors: Vec<&str> = errors.iter().filter_map(|e| e.as_str()).collect();
+                    format!("{}: {}", field, errors.join(", "))
+                }
+                errors => format!("{}: {}", field, errors),
+            })
+            .collect::<Vec<_>>()
+            .join("; "),
+        _ => match body.trim() {
+            "" => status.canonical_reason().unwrap_or("request failed").into(),
+            body => body.into(),
+        },
+    }
+}
@@ -1,14 +1,23 @@
-use reqwest::Client;
-use serde_json::Value;
-use zchess::{app::App, message::Message, ui, user::User, utils::debug};
+use zchess::{
+    app::App,
+    bot,
+    chess::{Board, Variant},
+    config::Config,
+    engine::server,
+    message::Message,
+    pgn,
+    stream::Event,
+    ui,
+    utils::debug,
+};
 
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Mutex;
 
-use futures::stream::StreamExt;
 use std::sync::mpsc::{self, Receiver};
 
-use std::panic::PanicInfo;
+use std::panic::PanicHookInfo;
 
 use crossterm::{
     execute,
@@ -16,7 +25,7 @@ use crossterm::{
     terminal::{disable_raw_mode, LeaveAlternateScreen},
 };
 
-fn panic_hook(info: &PanicInfo<'_>) {
+fn panic_hook(info: &PanicHookInfo<'_>) {
     let location = info.location().unwrap();
 
     let message = match info.payload().downcast_ref::<&'static str>() {
@@ -40,64 +49,115 @@ fn panic_hook(info: &PanicInfo<'_>) {
     .unwrap();
 }
 
+fn perft(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (fen, depth, variant) = match args {
+        [fen, depth] => (fen, depth, Variant::Standard),
+        [fen, depth, key] => (
+            fen,
+            depth,
+            Variant::from_key(key).ok_or_else(|| format!("unknown variant '{}'", key))?,
+        ),
+        _ => return Err("usage: zch perft <fen> <depth> [variant]".into()),
+    };
+
+    let depth = depth.parse::<u32>()?;
+    let mut board = Board::from_fen_variant(fen, variant)?;
+    let start = Instant::now();
+
+    let mut total = 0;
+
+    for (mv, nodes) in board.divide(depth) {
+        println!("{}: {}", mv, nodes);
+        total += nodes;
+    }
+
+    println!("\nnodes: {} ({:.2?})", total, start.elapsed());
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    std::panic::set_hook(Box::new(|info| panic_hook(info)));
+    let args: Vec<String> = std::env::args().collect();
 
-    let (main_tx, main_rx) = mpsc::channel::<Message>();
-    let stream_tx = main_tx.clone();
+    let mut pgn_games = None;
 
-    let app = App::new(main_tx.clone()).await.unwrap();
-    let debug_enabled = *app.config().debug();
+    match args.get(1).map(|arg| arg.as_str()) {
+        Some("perft") => return perft(&args[2..]),
+        Some("bot") => return bot::run(Config::new()?).await,
+        Some("uci") => return Ok(server::run(std::io::stdin().lock(), std::io::stdout())?),
+        Some("view") => {
+            let path = args.get(2).ok_or("usage: zch view <file.pgn>")?;
+            pgn_games = Some(pgn::parse(&std::fs::read_to_string(path)?)?);
+        }
+        _ => (),
+    }
 
-    let token = format!("Bearer {}", app.config().token());
-    let token_clone = token.clone();
+    std::panic::set_hook(Box::new(panic_hook));
 
-    tokio::spawn(async move {
-        let client = Client::new();
+    let (main_tx, main_rx) = mpsc::channel::<Message>();
+    let stream_tx = main_tx.clone();
 
-        let mut main_event_stream = client
-            .get("https://lichess.org/api/stream/event")
-            .header("Authorization", token)
-            .header("Content-Type", "application/x-ndjson")
-            .send()
-            .await
-            .unwrap()
-            .bytes_stream();
+    let mut app = App::new(main_tx.clone()).await.unwrap();
 
-        loop {
-            let ev = main_event_stream.next().await;
+    if let Some(games) = pgn_games {
+        app.open_pgn(games)?;
+    }
+    let debug_enabled = *app.config().debug();
 
-            if ev.is_none() {
-                break;
-            }
+    // without a token, only local games and games against the engine are available
+    let online = !app.config().token().is_empty();
+
+    let lichess = app.lichess().clone();
+    let lichess_clone = lichess.clone();
 
-            let ev_string = String::from_utf8(ev.unwrap().unwrap().to_vec()).unwrap();
+    tokio::spawn(async move {
+        if !online {
+            return;
+        }
 
-            if ev_string.len() > 1 {
+        let mut main_event_stream = match lichess.stream_events().await {
+            Ok(stream) => stream,
+            Err(e) => {
                 if debug_enabled {
-                    debug(&format!("main_event_stream: {}", ev_string));
+                    debug(&format!("main_event_stream: {}", e));
                 }
 
-                let json: Value =
-                    serde_json::from_str(&ev_string).unwrap_or_else(|_| panic!("{}", ev_string));
-
-                if let Some(p) = json.get("error") {
-                    panic!("{}", p);
-                }
+                return;
+            }
+        };
 
-                match json["type"].as_str().unwrap() {
-                    "gameStart" => {
-                        let game_id = json["game"]["id"].as_str().unwrap();
+        while let Some(json) = main_event_stream.next().await {
+            if debug_enabled {
+                debug(&format!("main_event_stream: {:?}\n", json));
+            }
 
-                        stream_tx.send(Message::GameStart(game_id.into())).unwrap();
+            let event = match json.and_then(Event::from_json) {
+                Ok(event) => event,
+                Err(e) => {
+                    if debug_enabled {
+                        debug(&format!("main_event_stream error: {}\n", e));
                     }
 
-                    "gameFinish" => {
-                        stream_tx.send(Message::GameEnd).unwrap();
-                    }
-                    _ => (),
+                    continue;
+                }
+            };
+
+            let message = match event {
+                Event::GameStart { game } => Message::GameStart(game.id().clone()),
+                Event::GameFinish { game } => Message::GameFinish(game.id().clone()),
+                Event::Challenge { challenge } => Message::Challenge(Box::new(challenge)),
+                Event::ChallengeCanceled { challenge } => {
+                    Message::ChallengeCanceled(challenge.id().clone())
                 }
+                Event::ChallengeDeclined { challenge } => {
+                    Message::ChallengeDeclined(Box::new(challenge))
+                }
+                Event::Other => continue,
+            };
+
+            if stream_tx.send(message).is_err() {
+                break;
             }
         }
     });
@@ -105,26 +165,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let own_info_tx = main_tx.clone();
 
     tokio::spawn(async move {
-        let client = reqwest::Client::new();
-
-        let res = client
-            .get("https://lichess.org/api/account")
-            .header("Authorization", token_clone)
-            .send()
-            .await;
-
-        if let Ok(res) = res {
-            let text = res.text().await;
-
-            if let Ok(text) = text {
-                let info: User = serde_json::from_str(&text).unwrap();
+        if !online {
+            return;
+        }
 
+        match lichess_clone.account().await {
+            Ok(info) => {
                 if debug_enabled {
                     let debug_str = serde_json::to_string_pretty(&info).unwrap();
                     debug(&format!("own_info: {}\n", debug_str));
                 }
 
-                own_info_tx.send(Message::GetOwnInfo(info)).unwrap();
+                own_info_tx
+                    .send(Message::GetOwnInfo(Box::new(info)))
+                    .unwrap();
+            }
+            Err(e) => {
+                if debug_enabled {
+                    debug(&format!("own_info: {}\n", e));
+                }
             }
         }
     });
@@ -153,25 +212,68 @@ async fn event_loop(rx: Receiver<Message>, app: Arc<Mutex<App>>) {
             }
 
             Message::GameDataInit(game) => {
-                app.start_game(game);
+                app.start_game(*game);
                 app.state_changed = true;
             }
 
-            Message::GameStateUpdate(state) => {
-                app.update_game_state(state);
+            Message::GameStateUpdate(id, state) => {
+                app.update_game_state(&id, state);
                 app.state_changed = true;
             }
 
             Message::NewMessage(msg) => {
-                let game = app.game_mut().as_mut().unwrap();
-                game.new_message(msg);
+                if let Some(game) = app.game_mut().as_mut() {
+                    game.new_message(msg);
+                    app.state_changed = true;
+                }
             }
             Message::GetOwnInfo(info) => {
-                app.set_own_info(info);
+                app.set_own_info(*info);
+            }
+
+            Message::EngineMove(mv, hash) => {
+                app.play_engine_move(mv, hash).await;
+                app.state_changed = true;
+            }
+
+            Message::AnalysisUpdate(hash, lines) => {
+                app.update_analysis_lines(hash, lines);
+                app.state_changed = true;
+            }
+
+            Message::EngineError(e) => {
+                app.engine_failed(e);
+                app.state_changed = true;
             }
 
-            Message::GameEnd => {
-                app.end_game();
+            Message::SeekFailed(e) => {
+                app.seek_failed(e);
+                app.state_changed = true;
+            }
+
+            Message::SeekPosted => {
+                app.seek_posted();
+                app.state_changed = true;
+            }
+
+            Message::Challenge(challenge) => {
+                app.challenge_received(*challenge);
+                app.state_changed = true;
+            }
+
+            Message::ChallengeCanceled(id) => {
+                app.challenge_canceled(&id);
+                app.state_changed = true;
+            }
+
+            Message::ChallengeDeclined(challenge) => {
+                app.challenge_declined(*challenge);
+                app.state_changed = true;
+            }
+
+            Message::GameFinish(id) => {
+                app.game_finished(&id);
+                app.state_changed = true;
             }
         }
     }
@@ -1,11 +1,22 @@
+use crate::challenge::Challenge;
+use crate::chess::ChessMove;
+use crate::engine::SearchInfo;
 use crate::game::{ChatMessage, Game, GameState};
 use crate::user::User;
 
 pub enum Message {
-    GameStart(String), // id
-    GameStateUpdate(GameState),
-    GameDataInit(Game),
+    GameStart(String),                  // id
+    GameStateUpdate(String, GameState), // the id of the game, and its state
+    GameDataInit(Box<Game>),
     NewMessage(ChatMessage),
-    GetOwnInfo(User),
-    GameEnd,
+    GetOwnInfo(Box<User>),
+    EngineMove(ChessMove, u64), // the move, and the hash of the position it was found for
+    EngineError(String),
+    AnalysisUpdate(u64, Vec<SearchInfo>), // the hash of the analysed position, and its lines
+    SeekFailed(String),
+    SeekPosted, // a correspondence seek was created
+    Challenge(Box<Challenge>),
+    ChallengeCanceled(String), // id
+    ChallengeDeclined(Box<Challenge>),
+    GameFinish(String), // id
 }
@@ -0,0 +1,376 @@
+use crate::chess::{Board, FenError, SanError, Side, Variant};
+use std::error::Error;
+use std::fmt;
+
+const LINE_WIDTH: usize = 80;
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    NoGames,
+    UnterminatedTag,
+    UnterminatedComment,
+    UnbalancedVariation,
+    InvalidFen(FenError),
+    InvalidMove(usize, SanError), // ply
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use PgnError::*;
+
+        match self {
+            NoGames => write!(f, "no games found"),
+            UnterminatedTag => write!(f, "unterminated tag pair"),
+            UnterminatedComment => write!(f, "unterminated comment"),
+            UnbalancedVariation => write!(f, "unbalanced parentheses in a variation"),
+            InvalidFen(e) => write!(f, "invalid FEN tag: {}", e),
+            InvalidMove(ply, e) => write!(f, "ply {}: {}", ply, e),
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+/// A move of the main line, with the comments and NAGs that follow it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnMove {
+    san: String,
+    comments: Vec<String>,
+    nags: Vec<u8>,
+}
+
+impl PgnMove {
+    pub fn san(&self) -> &String {
+        &self.san
+    }
+
+    pub fn comments(&self) -> &Vec<String> {
+        &self.comments
+    }
+
+    pub fn nags(&self) -> &Vec<u8> {
+        &self.nags
+    }
+}
+
+/// A game read from a PGN file. Variations are skipped, only the main line is kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    tags: Vec<(String, String)>,
+    comments: Vec<String>, // before the first move
+    moves: Vec<PgnMove>,
+    result: String,
+}
+
+impl PgnGame {
+    pub fn tags(&self) -> &Vec<(String, String)> {
+        &self.tags
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&String> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn comments(&self) -> &Vec<String> {
+        &self.comments
+    }
+
+    pub fn moves(&self) -> &Vec<PgnMove> {
+        &self.moves
+    }
+
+    pub fn result(&self) -> &String {
+        &self.result
+    }
+
+    /// The position the game starts from, taken from the `FEN` tag if there is one,
+    /// and played by the rules the `Variant` tag names.
+    pub fn start_board(&self) -> Result<Board, PgnError> {
+        let name = self.tag("Variant").map(|name| name.as_str()).unwrap_or("");
+        let variant = Variant::from_name(name).unwrap_or_default();
+
+        let mut board = match self.tag("FEN") {
+            Some(fen) => Board::from_fen_variant(fen, variant).map_err(PgnError::InvalidFen)?,
+            None => Board::new(variant),
+        };
+
+        if name.eq_ignore_ascii_case("chess960") {
+            board.set_chess960(true);
+        }
+
+        Ok(board)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.moves.is_empty() && self.comments.is_empty()
+    }
+
+    fn add_comment(&mut self, comment: String) {
+        match self.moves.last_mut() {
+            Some(mv) => mv.comments.push(comment),
+            None => self.comments.push(comment),
+        }
+    }
+}
+
+/// Parses every game in a PGN file.
+pub fn parse(input: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games = vec![];
+    let mut game = PgnGame::default();
+
+    // how deep into nested variations the parser is, moves and comments of variations are skipped
+    let mut depth = 0;
+
+    let mut chars = input.chars().peekable();
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        let at_line_start = line_start;
+        line_start = c == '\n';
+
+        match c {
+            // escaped lines
+            '%' if at_line_start => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line_start = true;
+                        break;
+                    }
+                }
+            }
+
+            '[' if depth == 0 => {
+                // a tag pair after the movetext starts the next game
+                if !game.moves.is_empty() {
+                    finish_game(&mut games, &mut game);
+                }
+
+                let mut name = String::new();
+                let mut value = String::new();
+                let mut in_value = false;
+
+                loop {
+                    match chars.next() {
+                        Some('"') if in_value => break,
+                        Some('"') => in_value = true,
+                        Some('\\') if in_value => {
+                            value.extend(chars.next());
+                        }
+                        Some(c) if in_value => value.push(c),
+                        Some(c) if !c.is_whitespace() => name.push(c),
+                        Some(_) => (),
+                        None => return Err(PgnError::UnterminatedTag),
+                    }
+                }
+
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some(_) => (),
+                        None => return Err(PgnError::UnterminatedTag),
+                    }
+                }
+
+                game.tags.push((name, value));
+            }
+
+            '{' => {
+                let mut comment = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => comment.push(c),
+                        None => return Err(PgnError::UnterminatedComment),
+                    }
+                }
+
+                if depth == 0 {
+                    game.add_comment(comment.split_whitespace().collect::<Vec<_>>().join(" "));
+                }
+            }
+
+            ';' => {
+                let mut comment = String::new();
+
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line_start = true;
+                        break;
+                    }
+
+                    comment.push(c);
+                }
+
+                if depth == 0 {
+                    game.add_comment(comment.trim().to_string());
+                }
+            }
+
+            '(' => depth += 1,
+
+            ')' => match depth {
+                0 => return Err(PgnError::UnbalancedVariation),
+                _ => depth -= 1,
+            },
+
+            c if c.is_whitespace() => (),
+
+            c => {
+                let mut token = c.to_string();
+
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{}()[];".contains(c) {
+                        break;
+                    }
+
+                    token.push(c);
+                    chars.next();
+                }
+
+                if depth > 0 {
+                    continue;
+                }
+
+                if RESULTS.contains(&token.as_str()) {
+                    game.result = token;
+                    finish_game(&mut games, &mut game);
+                    continue;
+                }
+
+                if let Some(nag) = token.strip_prefix('$') {
+                    if let (Ok(nag), Some(mv)) = (nag.parse(), game.moves.last_mut()) {
+                        mv.nags.push(nag);
+                    }
+
+                    continue;
+                }
+
+                // move numbers, e.g. `12.` or `12...`, possibly glued to the move itself
+                let digits = token.chars().take_while(|c| c.is_ascii_digit()).count();
+
+                let san = match token[digits..].starts_with('.') {
+                    true => token[digits..].trim_start_matches('.'),
+                    false => &token,
+                };
+
+                let san = san.trim_end_matches(['!', '?']);
+
+                if !san.is_empty() {
+                    game.moves.push(PgnMove {
+                        san: san.to_string(),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+    }
+
+    if depth > 0 {
+        return Err(PgnError::UnbalancedVariation);
+    }
+
+    if !game.is_empty() {
+        finish_game(&mut games, &mut game);
+    }
+
+    match games.is_empty() {
+        true => Err(PgnError::NoGames),
+        false => Ok(games),
+    }
+}
+
+fn finish_game(games: &mut Vec<PgnGame>, game: &mut PgnGame) {
+    let mut game = std::mem::take(game);
+
+    if game.result.is_empty() {
+        game.result = game.tag("Result").cloned().unwrap_or_else(|| "*".into());
+    }
+
+    games.push(game);
+}
+
+/// Renders a game in PGN, with the tag pairs in the given order followed by the moves played on
+/// `board`. Games that didn't start from the initial position get `SetUp` and `FEN` tags.
+pub fn write(tags: &[(&str, String)], board: &Board, result: &str) -> String {
+    let mut board = board.clone();
+    let san = board.played_moves_san();
+
+    for _ in san.iter() {
+        board.unmake_move();
+    }
+
+    let mut pgn = String::new();
+
+    for (name, value) in tags.iter() {
+        pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+    }
+
+    let variant = *board.variant();
+
+    if variant != Variant::Standard {
+        pgn.push_str(&format!("[Variant \"{}\"]\n", variant.name()));
+    } else if board.is_chess960() {
+        pgn.push_str("[Variant \"Chess960\"]\n");
+    }
+
+    let fen = board.to_fen();
+
+    if fen != variant.starting_fen() || board.is_chess960() {
+        pgn.push_str("[SetUp \"1\"]\n");
+        pgn.push_str(&format!("[FEN \"{}\"]\n", fen));
+    }
+
+    pgn.push('\n');
+
+    let mut tokens = vec![];
+    let mut number = *board.fullmove_number();
+    let mut turn = board.turn().clone();
+
+    for (idx, mv) in san.into_iter().enumerate() {
+        match turn {
+            Side::White => tokens.push(format!("{}.", number)),
+            Side::Black if idx == 0 => tokens.push(format!("{}...", number)),
+            Side::Black => (),
+        }
+
+        if turn == Side::Black {
+            number += 1;
+        }
+
+        tokens.push(mv);
+        turn = turn.opposite();
+    }
+
+    tokens.push(result.to_string());
+
+    let mut line = String::new();
+
+    for token in tokens {
+        if !line.is_empty() && line.len() + token.len() + 1 > LINE_WIDTH {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+        }
+
+        line.push_str(&token);
+    }
+
+    pgn.push_str(&line);
+    pgn.push('\n');
+
+    pgn
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
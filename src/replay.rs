@@ -0,0 +1,95 @@
+use crate::chess::{Board, ChessMove};
+use crate::pgn::{PgnError, PgnGame};
+
+/// Steps through the main line of an imported game.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    game: PgnGame,
+    board: Board,
+    moves: Vec<ChessMove>,
+    ply: usize,
+}
+
+impl Replay {
+    /// Resolves every move of the game against the board, failing on the first illegal one.
+    /// The replay starts at the initial position.
+    pub fn new(game: PgnGame) -> Result<Self, PgnError> {
+        let mut board = game.start_board()?;
+        let mut moves = vec![];
+
+        for (ply, mv) in game.moves().iter().enumerate() {
+            let invalid = |e| PgnError::InvalidMove(ply + 1, e);
+
+            let mv = board.parse_san(mv.san()).map_err(invalid)?;
+
+            board.play(mv);
+            moves.push(mv);
+        }
+
+        for _ in moves.iter() {
+            board.unmake_move();
+        }
+
+        Ok(Self {
+            game,
+            board,
+            moves,
+            ply: 0,
+        })
+    }
+
+    pub fn forward(&mut self) {
+        if let Some(mv) = self.moves.get(self.ply) {
+            self.board.play(*mv);
+            self.ply += 1;
+        }
+    }
+
+    pub fn back(&mut self) {
+        if self.ply > 0 {
+            self.board.unmake_move();
+            self.ply -= 1;
+        }
+    }
+
+    pub fn to_start(&mut self) {
+        while self.ply > 0 {
+            self.back();
+        }
+    }
+
+    pub fn to_end(&mut self) {
+        while self.ply < self.moves.len() {
+            self.forward();
+        }
+    }
+
+    pub fn game(&self) -> &PgnGame {
+        &self.game
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// How many moves of the main line have been played on the board.
+    pub fn ply(&self) -> &usize {
+        &self.ply
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// The comments on the last move played on the board, or on the game itself at the start.
+    pub fn comments(&self) -> &Vec<String> {
+        match self.ply {
+            0 => self.game.comments(),
+            ply => self.game.moves()[ply - 1].comments(),
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use crate::challenge::TimeControl;
+use crate::chess::Variant;
+use crate::user::{Perf, User};
+
+/// The quick pairing presets, in seconds for clocks.
+pub const PRESETS: [TimeControl; 13] = [
+    TimeControl::Clock {
+        limit: 60,
+        increment: 0,
+    },
+    TimeControl::Clock {
+        limit: 180,
+        increment: 2,
+    },
+    TimeControl::Clock {
+        limit: 300,
+        increment: 3,
+    },
+    TimeControl::Clock {
+        limit: 600,
+        increment: 5,
+    },
+    TimeControl::Clock {
+        limit: 900,
+        increment: 10,
+    },
+    TimeControl::Clock {
+        limit: 1800,
+        increment: 0,
+    },
+    TimeControl::Correspondence { days_per_turn: 1 },
+    TimeControl::Correspondence { days_per_turn: 2 },
+    TimeControl::Correspondence { days_per_turn: 3 },
+    TimeControl::Correspondence { days_per_turn: 5 },
+    TimeControl::Correspondence { days_per_turn: 7 },
+    TimeControl::Correspondence { days_per_turn: 10 },
+    TimeControl::Correspondence { days_per_turn: 14 },
+];
+
+const COLORS: [&str; 3] = ["random", "white", "black"];
+
+// the rating Lichess starts players at in a variant they haven't played
+const INITIAL_RATING: u32 = 1500;
+
+// how far the opponent's rating may be from the player's, `None` for any rating
+const RATING_RANGES: [Option<u32>; 5] = [None, Some(100), Some(200), Some(300), Some(500)];
+
+/// A row of the seek dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekField {
+    TimeControl,
+    Mode,
+    Color,
+    Variant,
+    RatingRange,
+}
+
+impl SeekField {
+    pub const ALL: [SeekField; 5] = [
+        SeekField::TimeControl,
+        SeekField::Mode,
+        SeekField::Color,
+        SeekField::Variant,
+        SeekField::RatingRange,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        use SeekField::*;
+
+        match self {
+            TimeControl => "Time control",
+            Mode => "Mode",
+            Color => "Color",
+            Variant => "Variant",
+            RatingRange => "Rating range",
+        }
+    }
+}
+
+/// The settings of the seek dialog, kept between seeks. Every field is picked from a fixed list of
+/// values.
+#[derive(Debug, Clone)]
+pub struct SeekForm {
+    field: usize,
+    preset: usize,
+    rated: bool,
+    color: usize,
+    // 0 is standard chess, 1 Chess960 and the rest the other variants in `Variant::ALL`
+    variant: usize,
+    range: usize,
+}
+
+impl Default for SeekForm {
+    fn default() -> Self {
+        Self {
+            field: 0,
+            // 10+5
+            preset: 3,
+            rated: false,
+            color: 0,
+            variant: 0,
+            range: 0,
+        }
+    }
+}
+
+impl SeekForm {
+    pub fn field(&self) -> SeekField {
+        SeekField::ALL[self.field]
+    }
+
+    pub fn move_field(&mut self, up: bool) {
+        self.field = match up {
+            true => self.field.saturating_sub(1),
+            false => (self.field + 1).min(SeekField::ALL.len() - 1),
+        };
+    }
+
+    /// Switches the selected field to its next or previous value, wrapping around.
+    pub fn change(&mut self, forward: bool) {
+        self.change_field(self.field(), forward);
+    }
+
+    pub fn change_field(&mut self, field: SeekField, forward: bool) {
+        let cycle = |idx: usize, len: usize| match forward {
+            true => (idx + 1) % len,
+            false => (idx + len - 1) % len,
+        };
+
+        match field {
+            SeekField::TimeControl => self.preset = cycle(self.preset, PRESETS.len()),
+            SeekField::Mode => self.rated = !self.rated,
+            SeekField::Color => self.color = cycle(self.color, COLORS.len()),
+            SeekField::Variant => self.variant = cycle(self.variant, Variant::ALL.len() + 1),
+            SeekField::RatingRange => self.range = cycle(self.range, RATING_RANGES.len()),
+        }
+    }
+
+    pub fn time_control(&self) -> &TimeControl {
+        &PRESETS[self.preset]
+    }
+
+    pub fn is_correspondence(&self) -> bool {
+        matches!(self.time_control(), TimeControl::Correspondence { .. })
+    }
+
+    pub fn rated(&self) -> &bool {
+        &self.rated
+    }
+
+    /// `random`, `white` or `black`.
+    pub fn color(&self) -> &'static str {
+        COLORS[self.color]
+    }
+
+    /// The Lichess key and the name of the variant.
+    pub fn variant(&self) -> (&'static str, &'static str) {
+        match self.variant {
+            0 => ("standard", "Standard"),
+            1 => ("chess960", "Chess960"),
+            idx => (Variant::ALL[idx - 1].key(), Variant::ALL[idx - 1].name()),
+        }
+    }
+
+    /// The ratings the opponent may have, around the player's rating in the variant, or in the
+    /// speed of the time control for standard chess. Always `None` while the player's info hasn't
+    /// loaded.
+    pub fn rating_range(&self, own_info: Option<&User>) -> Option<(u32, u32)> {
+        let range = RATING_RANGES[self.range]?;
+        let own_info = own_info?;
+
+        let rating = match self.variant().0 {
+            "standard" => *perf(self.time_control(), own_info).rating(),
+            key => own_info
+                .perfs()
+                .variant(key)
+                .map_or(INITIAL_RATING, |perf| *perf.rating()),
+        };
+
+        Some((rating.saturating_sub(range), rating + range))
+    }
+
+    /// The value of `field` as shown in the dialog.
+    pub fn value(&self, field: SeekField, own_info: Option<&User>) -> String {
+        match field {
+            SeekField::TimeControl => self.time_control().to_string(),
+            SeekField::Mode => match self.rated {
+                true => "Rated".into(),
+                false => "Casual".into(),
+            },
+            SeekField::Color => self.color().into(),
+            SeekField::Variant => self.variant().1.into(),
+            SeekField::RatingRange => match (RATING_RANGES[self.range], own_info) {
+                (None, _) => "Any".into(),
+                (Some(range), None) => format!("±{}", range),
+                (Some(range), Some(_)) => {
+                    let (min, max) = self.rating_range(own_info).unwrap_or_default();
+                    format!("±{} ({}-{})", range, min, max)
+                }
+            },
+        }
+    }
+
+    /// The form parameters of `/api/board/seek`.
+    pub fn params(&self, own_info: Option<&User>) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("rated", self.rated.to_string()),
+            ("variant", self.variant().0.into()),
+            ("color", self.color().into()),
+        ];
+
+        match self.time_control() {
+            TimeControl::Clock { limit, increment } => {
+                params.push(("time", (limit / 60).to_string()));
+                params.push(("increment", increment.to_string()));
+            }
+            TimeControl::Correspondence { days_per_turn } => {
+                params.push(("days", days_per_turn.to_string()))
+            }
+            TimeControl::Unlimited => (),
+        }
+
+        if let Some((min, max)) = self.rating_range(own_info) {
+            params.push(("ratingRange", format!("{}-{}", min, max)));
+        }
+
+        params
+    }
+}
+
+// the player's rating for the speed of `time_control`, as Lichess estimates it from the initial
+// time and 40 increments
+fn perf<'a>(time_control: &TimeControl, user: &'a User) -> &'a Perf {
+    let perfs = user.perfs();
+
+    match time_control {
+        TimeControl::Clock { limit, increment } => match limit + 40 * increment {
+            0..=179 => perfs.bullet(),
+            180..=479 => perfs.blitz(),
+            480..=1499 => perfs.rapid(),
+            _ => perfs.classical(),
+        },
+        _ => perfs.correspondence(),
+    }
+}
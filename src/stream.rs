@@ -0,0 +1,122 @@
+use crate::challenge::Challenge;
+use crate::game::{ChatMessage, GameData, GameState};
+
+use futures::stream::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+use std::error::Error;
+
+pub type StreamError = Box<dyn Error + Send + Sync>;
+
+/// A Lichess NDJSON stream, split into one JSON value per line. Lines sent in several chunks are
+/// put back together, and the empty lines that keep the connection open are skipped.
+pub struct NdjsonStream<S> {
+    inner: S,
+    buf: Vec<u8>,
+}
+
+impl<S, B, E> NdjsonStream<S>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: Into<StreamError>,
+{
+    pub fn new(inner: S) -> Self {
+        Self { inner, buf: vec![] }
+    }
+
+    /// The next value, or `None` once the stream has ended.
+    pub async fn next(&mut self) -> Option<Result<Value, StreamError>> {
+        loop {
+            if let Some(end) = self.buf.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = self.buf.drain(..=end).collect();
+
+                match parse_line(&line) {
+                    Some(value) => return Some(value),
+                    None => continue,
+                }
+            }
+
+            match self.inner.next().await {
+                Some(Ok(chunk)) => self.buf.extend_from_slice(chunk.as_ref()),
+                Some(Err(e)) => return Some(Err(e.into())),
+                // the last line may not end with a newline
+                None => {
+                    let line = std::mem::take(&mut self.buf);
+                    return parse_line(&line);
+                }
+            }
+        }
+    }
+}
+
+fn parse_line(line: &[u8]) -> Option<Result<Value, StreamError>> {
+    let line = String::from_utf8_lossy(line);
+
+    match line.trim() {
+        "" => None,
+        line => Some(serde_json::from_str(line).map_err(|e| e.into())),
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GameRef {
+    id: String,
+}
+
+impl GameRef {
+    pub fn id(&self) -> &String {
+        &self.id
+    }
+}
+
+/// An event from `/api/stream/event`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Event {
+    GameStart {
+        game: GameRef,
+    },
+    GameFinish {
+        game: GameRef,
+    },
+    Challenge {
+        challenge: Challenge,
+    },
+    ChallengeCanceled {
+        challenge: Challenge,
+    },
+    ChallengeDeclined {
+        challenge: Challenge,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// An event from a game stream, `/api/board/game/stream/{id}` or `/api/bot/game/stream/{id}`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum GameEvent {
+    /// The players, clock and variant of the game, with its state so far. Sent first.
+    GameFull {
+        #[serde(flatten)]
+        data: Box<GameData>,
+        state: GameState,
+    },
+    GameState(GameState),
+    ChatLine(ChatMessage),
+    #[serde(other)]
+    Other,
+}
+
+impl Event {
+    pub fn from_json(json: Value) -> Result<Self, StreamError> {
+        Ok(serde_json::from_value(json)?)
+    }
+}
+
+impl GameEvent {
+    pub fn from_json(json: Value) -> Result<Self, StreamError> {
+        Ok(serde_json::from_value(json)?)
+    }
+}
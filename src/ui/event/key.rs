@@ -8,6 +8,11 @@ pub enum Key {
     Right,
     Down,
     Left,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Tab,
     Backspace,
     Esc,
     Enter,
@@ -42,6 +47,31 @@ impl From<KeyEvent> for Key {
                 ..
             } => Key::Right,
 
+            KeyEvent {
+                code: event::KeyCode::Home,
+                ..
+            } => Key::Home,
+
+            KeyEvent {
+                code: event::KeyCode::End,
+                ..
+            } => Key::End,
+
+            KeyEvent {
+                code: event::KeyCode::PageUp,
+                ..
+            } => Key::PageUp,
+
+            KeyEvent {
+                code: event::KeyCode::PageDown,
+                ..
+            } => Key::PageDown,
+
+            KeyEvent {
+                code: event::KeyCode::Tab,
+                ..
+            } => Key::Tab,
+
             KeyEvent {
                 code: event::KeyCode::Up,
                 ..
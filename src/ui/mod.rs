@@ -1,11 +1,17 @@
 use crate::{
+    analysis::Analysis,
     app::App,
+    challenge::ChallengeForm,
+    chat::Room,
     chess::{
-        board::SquareColor,
-        utils::{get_square_color, uci_to_idx},
-        Side, Square,
+        board::SquareColor, utils::get_square_color, Board, ChessMove, Piece, PieceKind, Side,
+        Square, Variant,
     },
+    engine::{Level, Opponent},
+    game::{Game, GameKind, Offer},
     message::Message,
+    replay::Replay,
+    seek::SeekField,
     ui::event::*,
     user::User,
     utils::{fmt_clock, parse_config_hex},
@@ -34,114 +40,163 @@ const BLACK_PIECE_DEFAULT_COLOR: (u8, u8, u8) = (0, 0, 0);
 
 pub mod event;
 
+/// What the player picked to move in a game: a piece on the board or a piece from the pocket.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Selection {
+    Piece(Square),
+    Drop(PieceKind),
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum UIState {
     Menu,
-    Profile(User),
+    Profile(Box<User>),
+    /// The seek dialog.
     Seek,
+    /// Waiting for an opponent to accept the seek.
+    Seeking,
+    /// The challenges sent to the player, and the one they sent.
+    Challenges,
+    /// The "Challenge a player" form.
+    ChallengeForm,
     Game,
+    GamePicker,
+    Replay,
+    VariantPicker,
+    LevelPicker,
 }
 
-pub fn draw_seek(stdout: &mut Stdout) -> Result<(), Box<dyn std::error::Error>> {
-    let string = "Seeking for a new game...";
+pub fn draw_seek(app: &App, stdout: &mut Stdout) -> Result<(), Box<dyn std::error::Error>> {
+    let form = app.seek_form();
+
+    let string = format!(
+        "Seeking a {} {} game...",
+        form.time_control(),
+        form.variant().1
+    );
+    let hint = "esc to cancel";
 
     let size = terminal::size().unwrap();
 
-    let center_x = size.0 / 2 - string.len() as u16 / 2;
+    let center_x = |s: &str| (size.0 / 2).saturating_sub(s.chars().count() as u16 / 2);
     let center_y = size.1 / 2;
 
-    execute!(stdout, cursor::MoveTo(center_x, center_y), Print(string))?;
+    execute!(
+        stdout,
+        cursor::MoveTo(center_x(&string), center_y),
+        Print(&string),
+        cursor::MoveTo(center_x(hint), center_y + 2),
+        Print(hint)
+    )?;
+
     Ok(())
 }
 
-pub fn draw_profile(user: &User, cursor_pos: (u16, u16), stdout: &mut Stdout) {}
+/// The seek dialog, with the selected field in bold.
+pub fn draw_seek_form(app: &App, stdout: &mut Stdout) -> Result<(), Box<dyn std::error::Error>> {
+    execute!(stdout, Clear(ClearType::All))?;
 
-pub fn draw_board(
-    app: &App,
-    cursor_pos: (u16, u16),
-    selected_piece: Option<(usize, usize)>,
-    stdout: &mut Stdout,
-    no_board: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
+    let form = app.seek_form();
     let size = terminal::size()?;
+    let top = (size.1 / 2).saturating_sub(SeekField::ALL.len() as u16 / 2);
 
-    let dark_square_color = app.config().dark_square_color();
-    let dark_square = parse_config_hex(dark_square_color, DARK_SQUARE_DEFAULT_COLOR);
+    for (idx, field) in SeekField::ALL.iter().enumerate() {
+        let value = form.value(*field, app.own_info().as_ref());
 
-    let light_square_color = app.config().light_square_color();
-    let light_square = parse_config_hex(&light_square_color, LIGHT_SQUARE_DEFAULT_COLOR);
+        let row = match *field == form.field() {
+            true => format!("{:<14}< {} >", field.name(), value)
+                .bold()
+                .to_string(),
+            false => format!("{:<14}  {}", field.name(), value),
+        };
 
-    let legal_move_indicator_color = app.config().legal_move_indicator_color();
-    let legal_move_indicator = parse_config_hex(
-        legal_move_indicator_color,
-        LEGAL_MOVE_INDICATOR_DEFAULT_COLOR,
-    );
+        execute!(
+            stdout,
+            cursor::MoveTo((size.0 / 2).saturating_sub(16), top + idx as u16),
+            Print(row)
+        )?;
+    }
 
-    let black_piece_color = app.config().black_piece_color();
-    let black_piece = parse_config_hex(black_piece_color, BLACK_PIECE_DEFAULT_COLOR);
+    execute!(
+        stdout,
+        cursor::MoveTo(0, size.1),
+        Print("j/k to pick a field | h/l to change it | enter to seek | esc to return to the menu")
+    )?;
 
-    let white_piece_color = app.config().white_piece_color();
-    let white_piece = parse_config_hex(white_piece_color, WHITE_PIECE_DEFAULT_COLOR);
+    Ok(())
+}
 
-    let mut tile_width = 8;
-    let mut tile_height = 4;
+pub fn draw_promotion_prompt(
+    kinds: &[PieceKind],
+    stdout: &mut Stdout,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let choices: Vec<String> = kinds
+        .iter()
+        .enumerate()
+        .map(|(idx, kind)| {
+            let name = match kind {
+                PieceKind::Queen => "queen",
+                PieceKind::Rook => "rook",
+                PieceKind::Bishop => "bishop",
+                PieceKind::Knight => "knight",
+                PieceKind::King => "king",
+                PieceKind::Pawn => "pawn",
+            };
 
-    if !app.small_board() {
-        while tile_width * 8 > size.0 as usize / 2 {
-            tile_width -= 1;
-        }
+            format!("{} {}", idx + 1, name)
+        })
+        .collect();
 
-        while tile_width * 8 < size.0 as usize - (size.0 as f32 / 1.5) as usize {
-            tile_width += 1;
-        }
+    let prompt = format!("promote to: {} | esc to cancel", choices.join(" | "));
 
-        while tile_height * 8 > size.1 as usize - size.1 as usize / 8 {
-            tile_height -= 1;
-        }
+    let (_, y) = terminal::size()?;
 
-        while tile_height * 8 < (size.1 as f32 * 0.7) as usize {
-            tile_height += 1;
-        }
-    } else {
-        tile_width = 4;
-        tile_height = 2;
-    }
+    execute!(
+        stdout,
+        cursor::MoveTo(0, y - 1),
+        Clear(ClearType::CurrentLine),
+        Print(prompt.bold())
+    )?;
 
-    let tile_str = format!(
-        "{}{}",
-        " ".repeat(tile_width + 1).on(dark_square),
-        " ".repeat(tile_width + 1).on(light_square)
-    );
+    Ok(())
+}
 
-    let tile_str_alt = format!(
-        "{}{}",
-        " ".repeat(tile_width + 1).on(light_square),
-        " ".repeat(tile_width + 1).on(dark_square)
-    );
+pub fn draw_status(status: &str, stdout: &mut Stdout) -> Result<(), Box<dyn std::error::Error>> {
+    let (_, y) = terminal::size()?;
 
-    let center = size.0 / 2 - tile_width as u16 * 4 - 2;
+    execute!(
+        stdout,
+        cursor::MoveTo(0, y - 1),
+        Clear(ClearType::CurrentLine),
+        Print(status.bold())
+    )?;
+
+    Ok(())
+}
 
-    let game = app.game().as_ref().unwrap();
+pub fn draw_profile(_user: &User, _cursor_pos: (u16, u16), _stdout: &mut Stdout) {}
 
+/// The clocks and players of a live game, drawn on the last line below the board.
+pub fn game_statusline(game: &Game) -> String {
     let board = game.board();
 
-    let (wtime, btime) = match board.played_moves().len() >= 2 {
-        true => match board.turn() {
-            &Side::White => {
-                let wtime =
-                    *game.state().wtime() - board.turn_time_taken().elapsed().as_millis() as u64;
+    let (wtime, btime) = match board.played_moves().len() >= 2 && !game.is_over() {
+        true => match *board.turn() {
+            Side::White => {
+                let elapsed = board.turn_time_taken().elapsed().as_millis() as u64;
+                let wtime = game.state().wtime().saturating_sub(elapsed);
                 (wtime, *game.state().btime())
             }
-            &Side::Black => {
-                let btime =
-                    *game.state().btime() - board.turn_time_taken().elapsed().as_millis() as u64;
+            Side::Black => {
+                let elapsed = board.turn_time_taken().elapsed().as_millis() as u64;
+                let btime = game.state().btime().saturating_sub(elapsed);
                 (*game.state().wtime(), btime)
             }
         },
         false => (*game.state().wtime(), *game.state().btime()),
     };
 
-    let statusline = if game.is_online() {
+    if game.is_online() {
         let w_player = game.data().white();
         let b_player = game.data().black();
 
@@ -167,16 +222,19 @@ pub fn draw_board(
             black = format!("{}", black.bold());
         }
 
-        let clock = game.data().clock();
-
-        format!(
-            "id: {} | {}{} | {}+{}",
+        let line = format!(
+            "id: {} | {}{} | {}{}",
             game.id(),
             white,
             black,
-            clock.initial() / 1000 / 60,
-            clock.increment() / 1000
-        )
+            game.data().time_control(),
+            variant_label(board)
+        );
+
+        match game.outcome() {
+            Some(outcome) => format!("{} | {}, press enter to return to the menu", line, outcome),
+            None => line,
+        }
     } else {
         let mut white = format!("white: {} | ", fmt_clock(wtime));
 
@@ -190,9 +248,192 @@ pub fn draw_board(
             black = format!("{}", black.bold());
         }
 
-        format!("{}{}", white, black)
+        let opponent = match game.kind() {
+            GameKind::Computer { opponent, .. } => format!(" | vs {}", opponent.name()),
+            _ => String::new(),
+        };
+
+        match game.outcome() {
+            Some(outcome) => format!(
+                "{}{}{}{} | {}, press enter to return to the menu",
+                white,
+                black,
+                opponent,
+                variant_label(board),
+                outcome
+            ),
+            None => format!("{}{}{}{}", white, black, opponent, variant_label(board)),
+        }
+    }
+}
+
+// the variant of non-standard games for the statusline, with the checks given so far in Three-check
+fn variant_label(board: &Board) -> String {
+    match board.variant() {
+        Variant::Standard if board.is_chess960() => " | Chess960".into(),
+        Variant::Standard => String::new(),
+        Variant::ThreeCheck => format!(
+            " | Three-check ({}+{})",
+            board.checks_given(&Side::White),
+            board.checks_given(&Side::Black)
+        ),
+        variant => format!(" | {}", variant.name()),
+    }
+}
+
+// the width and height of the board's squares for a terminal of `size`
+fn tile_size(app: &App, size: (u16, u16)) -> (usize, usize) {
+    if *app.small_board() {
+        return (4, 2);
+    }
+
+    let mut tile_width = 8;
+    let mut tile_height = 4;
+
+    while tile_width * 8 > size.0 as usize / 2 {
+        tile_width -= 1;
+    }
+
+    while tile_width * 8 < size.0 as usize - (size.0 as f32 / 1.5) as usize {
+        tile_width += 1;
+    }
+
+    while tile_height * 8 > size.1 as usize - size.1 as usize / 8 {
+        tile_height -= 1;
+    }
+
+    while tile_height * 8 < (size.1 as f32 * 0.7) as usize {
+        tile_height += 1;
+    }
+
+    (tile_width, tile_height)
+}
+
+/// Where the chat panel of an online game goes, left of the board: its top left corner, width and
+/// height. `None` if there's no room for it.
+pub fn chat_panel(app: &App, size: (u16, u16)) -> Option<((u16, u16), usize, usize)> {
+    match (app.ui_state(), app.game()) {
+        (UIState::Game, Some(game)) if game.is_online() => (),
+        _ => return None,
+    }
+
+    let (tile_width, tile_height) = tile_size(app, size);
+
+    let center = (size.0 / 2).saturating_sub(tile_width as u16 * 4 + 2);
+    let center_y = (size.1 / 2).saturating_sub(((tile_height * 8) as f32 / 2.0).ceil() as u16);
+
+    // leaves room for the rank numbers and the evaluation bar
+    let width = center.saturating_sub(10) as usize;
+    let height = tile_height * 8;
+
+    match width >= 12 && height >= 4 {
+        true => Some(((1, center_y + 1), width, height)),
+        false => None,
+    }
+}
+
+// the room tabs, the messages of the room shown and the chat input
+fn draw_chat(
+    app: &App,
+    game: &Game,
+    panel: ((u16, u16), usize, usize),
+    stdout: &mut Stdout,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ((x, y), width, height) = panel;
+    let chat = app.chat();
+
+    let tabs: Vec<String> = [Room::Player, Room::Spectator]
+        .iter()
+        .map(|room| match room == chat.room() {
+            true => room.key().bold().underlined().to_string(),
+            false => room.key().with(Color::DarkGrey).to_string(),
+        })
+        .collect();
+
+    let mut header = tabs.join(" ");
+
+    if *chat.scroll() > 0 {
+        header.push_str(
+            &format!(" +{}", chat.scroll())
+                .with(Color::DarkGrey)
+                .to_string(),
+        );
+    }
+
+    execute!(stdout, cursor::MoveTo(x, y), Print(header))?;
+
+    let lines = chat.visible_lines(game.messages(), width, height - 2);
+
+    for (idx, line) in lines.iter().enumerate() {
+        execute!(stdout, cursor::MoveTo(x, y + 1 + idx as u16), Print(line))?;
+    }
+
+    let input = match chat.input() {
+        Some(input) => {
+            // the end of the message, where the cursor is
+            let text: Vec<char> = input.chars().collect();
+            let visible: String = text[text.len().saturating_sub(width - 3)..]
+                .iter()
+                .collect();
+            format!("> {}_", visible).bold().to_string()
+        }
+        None => "c to chat".with(Color::DarkGrey).to_string(),
     };
 
+    execute!(
+        stdout,
+        cursor::MoveTo(x, y + height as u16 - 1),
+        Print(input)
+    )?;
+
+    Ok(())
+}
+
+pub fn draw_board(
+    app: &App,
+    board: &Board,
+    statusline: &str,
+    cursor_pos: Option<(u16, u16)>,
+    selected: Option<Selection>,
+    stdout: &mut Stdout,
+    no_board: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let size = terminal::size()?;
+
+    let dark_square_color = app.config().dark_square_color();
+    let dark_square = parse_config_hex(dark_square_color, DARK_SQUARE_DEFAULT_COLOR);
+
+    let light_square_color = app.config().light_square_color();
+    let light_square = parse_config_hex(light_square_color, LIGHT_SQUARE_DEFAULT_COLOR);
+
+    let legal_move_indicator_color = app.config().legal_move_indicator_color();
+    let legal_move_indicator = parse_config_hex(
+        legal_move_indicator_color,
+        LEGAL_MOVE_INDICATOR_DEFAULT_COLOR,
+    );
+
+    let black_piece_color = app.config().black_piece_color();
+    let black_piece = parse_config_hex(black_piece_color, BLACK_PIECE_DEFAULT_COLOR);
+
+    let white_piece_color = app.config().white_piece_color();
+    let white_piece = parse_config_hex(white_piece_color, WHITE_PIECE_DEFAULT_COLOR);
+
+    let (tile_width, tile_height) = tile_size(app, size);
+
+    let tile_str = format!(
+        "{}{}",
+        " ".repeat(tile_width + 1).on(dark_square),
+        " ".repeat(tile_width + 1).on(light_square)
+    );
+
+    let tile_str_alt = format!(
+        "{}{}",
+        " ".repeat(tile_width + 1).on(light_square),
+        " ".repeat(tile_width + 1).on(dark_square)
+    );
+
+    let center = size.0 / 2 - tile_width as u16 * 4 - 2;
+
     let (_, y) = terminal::size().unwrap();
 
     if no_board {
@@ -200,7 +441,7 @@ pub fn draw_board(
             stdout,
             cursor::MoveTo(0, y),
             Clear(ClearType::CurrentLine),
-            Print(statusline.clone())
+            Print(statusline)
         )?;
 
         return Ok(());
@@ -216,16 +457,9 @@ pub fn draw_board(
         Print(statusline),
     )?;
 
-    // draw chat | deprecated, chat will be available on game dashboard
-    /*for (idx, msg) in app.game().as_ref().unwrap().messages().iter().enumerate() {
-        let msg_string = format!("{}: {}", msg.username(), msg.text());
-
-        execute!(
-            stdout,
-            cursor::MoveTo(x - x / 5, center_y + idx as u16),
-            Print(msg_string)
-        )?;
-    }*/
+    if let (Some(panel), Some(game)) = (chat_panel(app, size), app.game()) {
+        draw_chat(app, game, panel, stdout)?;
+    }
 
     // print rows
     for i in 1..=8 {
@@ -256,7 +490,7 @@ pub fn draw_board(
                 stdout,
                 cursor::MoveToNextLine(1),
                 cursor::MoveToColumn(center),
-                Print(&format!("{}", current_row.repeat(4))),
+                Print(current_row.repeat(4)),
             )?;
         }
     }
@@ -298,7 +532,7 @@ pub fn draw_board(
                 SquareColor::Dark => dark_square,
             };
 
-            let piece = board.piece_at(idx.into());
+            let piece = board.piece_at(idx);
 
             let mut piece_string;
 
@@ -311,8 +545,6 @@ pub fn draw_board(
                         }
                     } else if tile_width >= 6 {
                         p.render(tile_width).to_string()
-                    } else if *app.small_board() {
-                        p.render_char()
                     } else {
                         p.render_char()
                     }
@@ -333,45 +565,36 @@ pub fn draw_board(
                 }
             }
 
-            let is_selected_sq = match selected_piece {
-                Some((_, _)) => {
-                    if board.current_generated_moves().contains(&idx) {
-                        true
-                    } else {
-                        false
-                    }
-                }
+            let is_legal_dest = board
+                .current_generated_moves()
+                .iter()
+                .any(|mv| mv.to().index() == idx);
 
-                None => false,
-            };
+            let is_selected_sq = selected.is_some() && is_legal_dest;
 
             if is_selected_sq {
                 piece_string = "*".into()
             }
 
-            if let Some((x, y)) = selected_piece {
-                if x == idx.x() as usize && y == idx.y() as usize {
-                    piece_string = format!("{}", piece_string.bold());
-                }
+            if selected == Some(Selection::Piece(Square::from_index_unchecked(idx))) {
+                piece_string = format!("{}", piece_string.bold());
             }
 
-            if cursor_pos.0 == j && cursor_pos.1 == i {
+            if cursor_pos == Some((j, i)) {
                 piece_string = match piece_string.is_empty() {
                     false => format!("{}", piece_string.bold()),
                     true => format!("{}", "*".bold()),
                 };
-            } else if let Some(_) = selected_piece {
-                if board.current_generated_moves().contains(&idx) {
-                    piece_string = format!("{}", "*".with(legal_move_indicator));
-                }
+            } else if is_selected_sq {
+                piece_string = format!("{}", "*".with(legal_move_indicator));
             }
 
             if let Some(mv) = board.played_moves().last() {
-                let (src, dest) = uci_to_idx(&mv.uci());
+                let mv = mv.chess_move();
 
-                if src == idx {
+                if mv.from().index() == idx {
                     piece_string += &format!("{}", "*".with(Color::Blue).bold().on(color));
-                } else if dest == idx {
+                } else if mv.to().index() == idx {
                     piece_string += &format!("{}", "*".with(Color::Yellow).bold().on(color));
                 }
             }
@@ -386,23 +609,366 @@ pub fn draw_board(
 
             execute!(
                 stdout,
-                cursor::MoveToColumn(center + 1 + (tile_width as u16 + 1) * j as u16 + extra_x),
+                cursor::MoveToColumn(center + 1 + (tile_width as u16 + 1) * j + extra_x),
                 Print(piece_string),
             )?;
         }
     }
 
+    if board.variant().has_pockets() {
+        let x = center + (tile_width as u16 + 1) * 8 + 3;
+        let top = app.board_display_side().opposite();
+
+        let selected_drop = match selected {
+            Some(Selection::Drop(kind)) => Some(kind),
+            _ => None,
+        };
+
+        for (side, y) in [
+            (top.clone(), center_y + 1),
+            (top.opposite(), center_y + tile_height as u16 * 8),
+        ] {
+            execute!(
+                stdout,
+                cursor::MoveTo(x, y),
+                Print(pocket_string(board, &side, selected_drop))
+            )?;
+        }
+    }
+
+    if let Some(analysis) = app.analysis() {
+        let panel_x = center + (tile_width as u16 + 1) * 8 + 3;
+        let panel_y = match board.variant().has_pockets() {
+            true => center_y + 3,
+            false => center_y + 1,
+        };
+
+        draw_analysis(
+            analysis,
+            app.board_display_side(),
+            (center.saturating_sub(7), center_y + 1),
+            tile_height as u16 * 8,
+            (panel_x, panel_y),
+            stdout,
+        )?;
+    }
+
     Ok(())
 }
 
+// a vertical evaluation bar `height` rows tall at `bar`, filled from white's side of the board, and
+// the score, depth and lines of the analysis at `panel`
+fn draw_analysis(
+    analysis: &Analysis,
+    display_side: &Side,
+    bar: (u16, u16),
+    height: u16,
+    panel: (u16, u16),
+    stdout: &mut Stdout,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let white_rows = (analysis.white_share() * height as f32).round() as u16;
+
+    for row in 0..height {
+        // rows counted from white's side of the board
+        let from_white = match display_side {
+            Side::White => height - 1 - row,
+            Side::Black => row,
+        };
+
+        let color = match from_white < white_rows {
+            true => Color::White,
+            false => Color::Black,
+        };
+
+        execute!(
+            stdout,
+            cursor::MoveTo(bar.0, bar.1 + row),
+            Print("  ".on(color))
+        )?;
+    }
+
+    let width = terminal::size()?.0.saturating_sub(panel.0) as usize;
+    let board = analysis.board();
+
+    let header = match (analysis.lines().first(), board.outcome()) {
+        (_, Some(outcome)) => outcome.to_string(),
+        (Some(best), None) => format!("{} | depth {}", analysis.white_score(best), best.depth()),
+        (None, None) => "analysing...".into(),
+    };
+
+    let mut rows = vec![format!("{}", header.bold())];
+
+    for line in analysis.lines() {
+        rows.push(format!(
+            "{:>6} {}",
+            analysis.white_score(line).to_string(),
+            board.line_to_san(line.pv())
+        ));
+    }
+
+    for (idx, row) in rows.iter().enumerate() {
+        let row: String = match idx {
+            0 => row.clone(),
+            _ => row.chars().take(width).collect(),
+        };
+
+        execute!(
+            stdout,
+            cursor::MoveTo(panel.0, panel.1 + idx as u16),
+            Print(row)
+        )?;
+    }
+
+    Ok(())
+}
+
+// the pieces in the pocket of `side` with their counts, e.g. `P2 N1`, highlighting the selected one
+// if it's the side to move
+fn pocket_string(board: &Board, side: &Side, selected: Option<PieceKind>) -> String {
+    let pocket = board.pocket(side);
+
+    let pieces: Vec<String> = pocket
+        .kinds()
+        .map(|kind| {
+            let piece = Piece::new(kind, side.clone());
+            let entry = format!("{}{}", piece.render_char(), pocket.count(&kind));
+
+            match selected == Some(kind) && side == board.turn() {
+                true => format!("{}", entry.bold().underlined()),
+                false => entry,
+            }
+        })
+        .collect();
+
+    match pieces.is_empty() {
+        true => "-".with(Color::DarkGrey).to_string(),
+        false => pieces.join(" "),
+    }
+}
+
+/// The players, the current move and the result of a replayed game.
+pub fn replay_statusline(replay: &Replay) -> String {
+    let game = replay.game();
+
+    let player = |tag| game.tag(tag).cloned().unwrap_or_else(|| "?".into());
+
+    let last_move = match replay.ply() {
+        0 => "start".to_string(),
+        ply => {
+            let board = replay.board();
+
+            // the side to move is the one that didn't play the last move
+            let (number, dots) = match board.turn() {
+                Side::Black => (*board.fullmove_number(), "."),
+                Side::White => (board.fullmove_number() - 1, "..."),
+            };
+
+            format!("{}{} {}", number, dots, game.moves()[ply - 1].san())
+        }
+    };
+
+    format!(
+        "{} vs {} | {} ({}/{}) | {}",
+        player("White"),
+        player("Black"),
+        last_move,
+        replay.ply(),
+        replay.len(),
+        game.result(),
+    )
+}
+
+pub fn draw_game_picker(app: &App, stdout: &mut Stdout) -> Result<(), Box<dyn std::error::Error>> {
+    execute!(stdout, Clear(ClearType::All))?;
+
+    let size = terminal::size()?;
+    let visible = size.1.saturating_sub(4).max(1) as usize;
+    let offset = app.picker_idx().saturating_sub(visible - 1);
+
+    for (idx, game) in app
+        .pgn_games()
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(visible)
+    {
+        let tag = |name| game.tag(name).map(|v| v.as_str()).unwrap_or("?");
+
+        let mut line = format!(
+            "{}. {} vs {}, {} ({}, {})",
+            idx + 1,
+            tag("White"),
+            tag("Black"),
+            game.result(),
+            tag("Event"),
+            tag("Date"),
+        );
+
+        if idx == *app.picker_idx() {
+            line = format!("{}", line.bold());
+        }
+
+        execute!(
+            stdout,
+            cursor::MoveTo(2, 1 + (idx - offset) as u16),
+            Print(line)
+        )?;
+    }
+
+    execute!(
+        stdout,
+        cursor::MoveTo(0, size.1),
+        Print("enter to open a game | esc to return to the menu")
+    )?;
+
+    Ok(())
+}
+
+/// The challenges sent to the player, with the selected one in bold, below the one they sent.
+pub fn draw_challenges(app: &App, stdout: &mut Stdout) -> Result<(), Box<dyn std::error::Error>> {
+    execute!(stdout, Clear(ClearType::All))?;
+
+    let size = terminal::size()?;
+    let mut row = 1;
+
+    if let Some(outgoing) = app.outgoing() {
+        execute!(
+            stdout,
+            cursor::MoveTo(2, row),
+            Print(outgoing.to_string().italic())
+        )?;
+        row += 2;
+    }
+
+    if app.challenges().is_empty() {
+        execute!(stdout, cursor::MoveTo(2, row), Print("No challenges"))?;
+    }
+
+    let visible = size.1.saturating_sub(row + 3).max(1) as usize;
+    let offset = app.challenge_idx().saturating_sub(visible - 1);
+
+    for (idx, challenge) in app
+        .challenges()
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(visible)
+    {
+        let mut line = format!("{}. {}", idx + 1, challenge);
+
+        if idx == *app.challenge_idx() {
+            line = format!("{}", line.bold());
+        }
+
+        execute!(
+            stdout,
+            cursor::MoveTo(2, row + (idx - offset) as u16),
+            Print(line)
+        )?;
+    }
+
+    execute!(
+        stdout,
+        cursor::MoveTo(0, size.1),
+        Print("enter to accept | x to decline | c to cancel your challenge | n to challenge a player | esc to return to the menu")
+    )?;
+
+    Ok(())
+}
+
+/// The "Challenge a player" form, with the selected field in bold.
+pub fn draw_challenge_form(
+    app: &App,
+    stdout: &mut Stdout,
+) -> Result<(), Box<dyn std::error::Error>> {
+    execute!(stdout, Clear(ClearType::All))?;
+
+    let form = app.challenge_form();
+    let size = terminal::size()?;
+    let left = (size.0 / 2).saturating_sub(16);
+    let top = (size.1 / 2).saturating_sub(ChallengeForm::SETTINGS.len() as u16 / 2 + 1);
+
+    let player = match form.editing_username() {
+        true => format!("{:<14}  {}_", "Player", form.username())
+            .bold()
+            .to_string(),
+        false => format!("{:<14}  {}", "Player", form.username()),
+    };
+
+    execute!(stdout, cursor::MoveTo(left, top), Print(player))?;
+
+    for (idx, field) in ChallengeForm::SETTINGS.iter().enumerate() {
+        let value = form.settings().value(*field, None);
+
+        let row = match Some(*field) == form.field() {
+            true => format!("{:<14}< {} >", field.name(), value)
+                .bold()
+                .to_string(),
+            false => format!("{:<14}  {}", field.name(), value),
+        };
+
+        execute!(
+            stdout,
+            cursor::MoveTo(left, top + 1 + idx as u16),
+            Print(row)
+        )?;
+    }
+
+    execute!(
+        stdout,
+        cursor::MoveTo(0, size.1),
+        Print("up/down to pick a field | h/l to change it | enter to send | esc to return to the menu")
+    )?;
+
+    Ok(())
+}
+
+// the entries of the list shown in the app's state, if it's drawn with `draw_menu`
+fn menu_items(app: &App) -> Vec<&'static str> {
+    match app.ui_state() {
+        UIState::Menu => vec![
+            "New Lichess game",
+            "Local game",
+            "Play vs computer",
+            "Challenges",
+            "Challenge a player",
+        ],
+        UIState::VariantPicker => {
+            let mut items = vec!["Standard", "Chess960"];
+            items.extend(Variant::ALL.iter().skip(1).map(|variant| variant.name()));
+            items
+        }
+        UIState::LevelPicker => {
+            let mut items: Vec<_> = Level::ALL.iter().map(|level| level.name()).collect();
+
+            if app.config().engine().is_some() {
+                items.push("External engine");
+            }
+
+            items
+        }
+        _ => vec![],
+    }
+}
+
+// the board a local game picked from the variant picker starts with
+fn local_game_board(idx: usize) -> Option<Board> {
+    match idx {
+        0 => Some(Board::default()),
+        1 => Some(Board::random_chess960()),
+        idx => Variant::ALL
+            .get(idx - 1)
+            .map(|variant| Board::new(*variant)),
+    }
+}
+
 pub fn draw_menu(
     app: &App,
+    items: &[&str],
     cursor_pos: &mut (u16, u16),
     stdout: &mut Stdout,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let menu_items = vec!["New Lichess game", "Local game"];
-
-    if cursor_pos.1 > menu_items.len() as u16 {
+    if cursor_pos.1 >= items.len() as u16 {
         cursor_pos.1 = 0;
     }
 
@@ -410,7 +976,7 @@ pub fn draw_menu(
 
     let size = terminal::size().unwrap();
 
-    for (idx, i) in menu_items.iter().enumerate() {
+    for (idx, i) in items.iter().enumerate() {
         let center_x = size.0 / 2 - i.len() as u16 / 2;
         let center_y = size.1 / 2 + idx as u16;
 
@@ -429,7 +995,13 @@ pub fn draw_menu(
 
     let header_string = match app.own_info() {
         Some(info) => {
-            format!("Logged in as: {}", info.username())
+            let count = app.challenges().len();
+
+            match count {
+                0 => format!("Logged in as: {}", info.username()),
+                1 => format!("Logged in as: {} | 1 challenge", info.username()),
+                count => format!("Logged in as: {} | {} challenges", info.username(), count),
+            }
         }
         None => String::from("Loading Lichess info..."),
     };
@@ -446,7 +1018,7 @@ pub fn draw_menu(
 
 pub async fn start(
     app: Arc<Mutex<App>>,
-    main_tx: Sender<Message>,
+    _main_tx: Sender<Message>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let events = Events::new(1024);
 
@@ -455,23 +1027,55 @@ pub async fn start(
     enable_raw_mode()?;
 
     let mut cursor_pos = (0, 0);
-    let mut selected_piece: Option<(usize, usize)> = None;
+    let mut selected: Option<Selection> = None;
+    let mut pending_promotion: Option<ChessMove> = None;
 
     let mut size = terminal::size().unwrap();
 
     loop {
         let mut app = app.lock().await;
+        app.update_analysis();
 
         match app.ui_state() {
             UIState::Game => {
                 let curr_size = terminal::size().unwrap();
 
-                if app.state_changed || curr_size != size {
-                    draw_board(&app, cursor_pos, selected_piece, &mut stdout, false)?;
+                if let Some((_, width, height)) = chat_panel(&app, curr_size) {
+                    app.clamp_chat_scroll(width, height - 2);
+                }
+
+                let game = app.game().as_ref().unwrap();
+                let statusline = game_statusline(game);
+                let redraw = app.state_changed || curr_size != size;
+
+                draw_board(
+                    &app,
+                    game.board(),
+                    &statusline,
+                    Some(cursor_pos),
+                    selected,
+                    &mut stdout,
+                    !redraw,
+                )?;
+
+                if redraw {
                     app.state_changed = false;
                     size = curr_size;
-                } else {
-                    draw_board(&app, cursor_pos, selected_piece, &mut stdout, true)?;
+                }
+
+                if pending_promotion.is_some() {
+                    let kinds = app
+                        .game()
+                        .as_ref()
+                        .unwrap()
+                        .board()
+                        .variant()
+                        .promotion_kinds();
+                    draw_promotion_prompt(kinds, &mut stdout)?;
+                } else if let Some(prompt) = app.offer_prompt() {
+                    draw_status(&prompt, &mut stdout)?;
+                } else if let Some(status) = app.status() {
+                    draw_status(status, &mut stdout)?;
                 }
             }
 
@@ -479,11 +1083,97 @@ pub async fn start(
                 draw_profile(user, cursor_pos, &mut stdout);
             }
 
-            &UIState::Menu => {
-                draw_menu(&app, &mut cursor_pos, &mut stdout)?;
+            UIState::Replay => {
+                let curr_size = terminal::size().unwrap();
+
+                if app.state_changed || curr_size != size {
+                    let replay = app.replay().as_ref().unwrap();
+                    let statusline = replay_statusline(replay);
+
+                    draw_board(
+                        &app,
+                        replay.board(),
+                        &statusline,
+                        None,
+                        None,
+                        &mut stdout,
+                        false,
+                    )?;
+
+                    if let Some(status) = app.status() {
+                        draw_status(status, &mut stdout)?;
+                    } else if !replay.comments().is_empty() {
+                        draw_status(&replay.comments().join(" "), &mut stdout)?;
+                    }
+
+                    app.state_changed = false;
+                    size = curr_size;
+                }
             }
+
+            UIState::GamePicker => {
+                if app.state_changed {
+                    draw_game_picker(&app, &mut stdout)?;
+
+                    if let Some(status) = app.status() {
+                        draw_status(status, &mut stdout)?;
+                    }
+
+                    app.state_changed = false;
+                }
+            }
+
+            UIState::Menu | UIState::VariantPicker | UIState::LevelPicker => {
+                let items = menu_items(&app);
+                draw_menu(&app, &items, &mut cursor_pos, &mut stdout)?;
+
+                if let Some(status) = app.status() {
+                    draw_status(status, &mut stdout)?;
+                }
+            }
+
             UIState::Seek => {
-                draw_seek(&mut stdout)?;
+                if app.state_changed {
+                    draw_seek_form(&app, &mut stdout)?;
+
+                    if let Some(status) = app.status() {
+                        draw_status(status, &mut stdout)?;
+                    }
+
+                    app.state_changed = false;
+                }
+            }
+
+            UIState::Challenges => {
+                if app.state_changed {
+                    draw_challenges(&app, &mut stdout)?;
+
+                    if let Some(status) = app.status() {
+                        draw_status(status, &mut stdout)?;
+                    }
+
+                    app.state_changed = false;
+                }
+            }
+
+            UIState::ChallengeForm => {
+                if app.state_changed {
+                    draw_challenge_form(&app, &mut stdout)?;
+
+                    if let Some(status) = app.status() {
+                        draw_status(status, &mut stdout)?;
+                    }
+
+                    app.state_changed = false;
+                }
+            }
+
+            UIState::Seeking => {
+                if app.state_changed {
+                    execute!(stdout, Clear(ClearType::All))?;
+                    draw_seek(&app, &mut stdout)?;
+                    app.state_changed = false;
+                }
             }
         }
 
@@ -491,14 +1181,99 @@ pub async fn start(
 
         if let Ok(Event::Input(k)) = events.next() {
             app.state_changed = true;
+            app.clear_status();
+
+            let editing_username = app.ui_state() == &UIState::ChallengeForm
+                && app.challenge_form().editing_username();
+
+            let chatting = app.ui_state() == &UIState::Game && app.chat().input().is_some();
+
             match k {
+                Key::Char(c) if editing_username => app.challenge_form_mut().push_char(c),
+                Key::Backspace if editing_username => app.challenge_form_mut().pop_char(),
+
+                Key::Char(c) if chatting => app.chat_mut().push_char(c),
+                Key::Backspace if chatting => app.chat_mut().pop_char(),
+                Key::Enter if chatting => app.send_chat().await,
+                Key::Esc if chatting => app.chat_mut().close_input(),
+
                 Key::Char('q') => break,
-                Key::Char('h') | Key::Left if app.ui_state() == &UIState::Game => {
-                    if cursor_pos.0 >= 1 {
-                        cursor_pos.0 -= 1;
+                Key::Char(c @ '1'..='9') if pending_promotion.is_some() => {
+                    let kinds = app
+                        .game()
+                        .as_ref()
+                        .unwrap()
+                        .board()
+                        .variant()
+                        .promotion_kinds();
+
+                    if let Some(kind) = kinds.get(c as usize - '1' as usize) {
+                        let mv = pending_promotion.take().unwrap();
+
+                        app.play_move(ChessMove::new(mv.from(), mv.to(), Some(*kind)))
+                            .await;
+                    }
+                }
+
+                Key::Esc if pending_promotion.is_some() => {
+                    pending_promotion = None;
+                    app.game_mut()
+                        .as_mut()
+                        .unwrap()
+                        .board_mut()
+                        .set_generated_moves(vec![]);
+                }
+
+                _ if pending_promotion.is_some() => (),
+
+                Key::Char('l') | Key::Right if app.ui_state() == &UIState::Replay => {
+                    app.replay_mut().as_mut().unwrap().forward();
+                }
+
+                Key::Char('h') | Key::Left if app.ui_state() == &UIState::Replay => {
+                    app.replay_mut().as_mut().unwrap().back();
+                }
+
+                Key::Char('g') | Key::Home if app.ui_state() == &UIState::Replay => {
+                    app.replay_mut().as_mut().unwrap().to_start();
+                }
+
+                Key::Char('G') | Key::End if app.ui_state() == &UIState::Replay => {
+                    app.replay_mut().as_mut().unwrap().to_end();
+                }
+
+                Key::Char('f') if app.ui_state() == &UIState::Replay => {
+                    app.flip_board();
+                }
+
+                Key::Esc | Key::Backspace if app.ui_state() == &UIState::Replay => {
+                    app.close_replay();
+                }
+
+                Key::Char('j') | Key::Down if app.ui_state() == &UIState::GamePicker => {
+                    app.move_picker(false);
+                }
+
+                Key::Char('k') | Key::Up if app.ui_state() == &UIState::GamePicker => {
+                    app.move_picker(true);
+                }
+
+                Key::Enter if app.ui_state() == &UIState::GamePicker => {
+                    let idx = *app.picker_idx();
+
+                    if let Err(e) = app.open_replay(idx) {
+                        app.set_status(format!("failed to open the game: {}", e));
                     }
                 }
 
+                Key::Esc if app.ui_state() == &UIState::GamePicker => {
+                    app.set_ui_state(UIState::Menu);
+                }
+
+                Key::Char('h') | Key::Left if app.ui_state() == &UIState::Game => {
+                    cursor_pos.0 = cursor_pos.0.saturating_sub(1);
+                }
+
                 Key::Char('a') if app.ui_state() == &UIState::Game => {
                     app.abort_game().await;
                 }
@@ -508,31 +1283,155 @@ pub async fn start(
                 }
 
                 Key::Char('j') | Key::Down if app.ui_state() == &UIState::Game => {
-                    if cursor_pos.1 >= 1 {
-                        cursor_pos.1 -= 1;
+                    cursor_pos.1 = cursor_pos.1.saturating_sub(1);
+                }
+
+                Key::Char(c @ ('y' | 'n'))
+                    if app.ui_state() == &UIState::Game && app.pending_offer().is_some() =>
+                {
+                    match app.pending_offer() {
+                        Some(Offer::Draw) => app.answer_draw(c == 'y').await,
+                        Some(Offer::Takeback) => app.answer_takeback(c == 'y').await,
+                        None => (),
                     }
                 }
 
+                Key::Char('c')
+                    if app.ui_state() == &UIState::Game
+                        && app.game().as_ref().unwrap().is_online() =>
+                {
+                    app.chat_mut().open_input();
+                }
+
+                Key::Tab if app.ui_state() == &UIState::Game => {
+                    app.chat_mut().toggle_room();
+                }
+
+                Key::PageUp if app.ui_state() == &UIState::Game => {
+                    app.chat_mut().scroll_by(5, true);
+                }
+
+                Key::PageDown if app.ui_state() == &UIState::Game => {
+                    app.chat_mut().scroll_by(5, false);
+                }
+
+                Key::Char('o') if app.ui_state() == &UIState::Game => {
+                    app.answer_draw(true).await;
+                }
+
+                Key::Char('u') if app.ui_state() == &UIState::Game => {
+                    selected = None;
+
+                    match app.game().as_ref().unwrap().is_online() {
+                        true => app.answer_takeback(true).await,
+                        false => app.takeback(),
+                    }
+                }
+
+                Key::Char('s') if app.ui_state() == &UIState::Game => match app.save_pgn() {
+                    Ok(path) => app.set_status(format!("saved the game to {}", path.display())),
+                    Err(e) => app.set_status(format!("failed to save the game: {}", e)),
+                },
+
+                Key::Char('e') if matches!(app.ui_state(), UIState::Game | UIState::Replay) => {
+                    app.toggle_analysis();
+                }
+
                 Key::Char('z') if app.ui_state() == &UIState::Game => {
                     app.toggle_small_board();
                 }
 
-                Key::Char('j') | Key::Down => {
-                    if cursor_pos.1 < 1 {
-                        cursor_pos.1 += 1;
-                    }
+                Key::Char('j') | Key::Down if app.ui_state() == &UIState::Seek => {
+                    app.seek_form_mut().move_field(false);
+                }
+
+                Key::Char('k') | Key::Up if app.ui_state() == &UIState::Seek => {
+                    app.seek_form_mut().move_field(true);
+                }
+
+                Key::Char('l') | Key::Right if app.ui_state() == &UIState::Seek => {
+                    app.seek_form_mut().change(true);
+                }
+
+                Key::Char('h') | Key::Left if app.ui_state() == &UIState::Seek => {
+                    app.seek_form_mut().change(false);
+                }
+
+                Key::Esc if app.ui_state() == &UIState::Seek => {
+                    app.set_ui_state(UIState::Menu);
+                    cursor_pos.1 = 0;
+                }
+
+                Key::Esc if app.ui_state() == &UIState::Seeking => {
+                    app.cancel_seek();
+                }
+
+                Key::Char('j') | Key::Down if app.ui_state() == &UIState::Challenges => {
+                    app.move_challenge_cursor(false);
+                }
+
+                Key::Char('k') | Key::Up if app.ui_state() == &UIState::Challenges => {
+                    app.move_challenge_cursor(true);
+                }
+
+                Key::Char('x') if app.ui_state() == &UIState::Challenges => {
+                    app.decline_challenge().await;
+                }
+
+                Key::Char('c') if app.ui_state() == &UIState::Challenges => {
+                    app.cancel_challenge().await;
+                }
+
+                Key::Char('n') if app.ui_state() == &UIState::Challenges => {
+                    app.set_ui_state(UIState::ChallengeForm);
+                }
+
+                Key::Esc if app.ui_state() == &UIState::Challenges => {
+                    app.set_ui_state(UIState::Menu);
+                    cursor_pos.1 = 3;
+                }
+
+                Key::Char('j') | Key::Down if app.ui_state() == &UIState::ChallengeForm => {
+                    app.challenge_form_mut().move_field(false);
+                }
+
+                Key::Char('k') | Key::Up if app.ui_state() == &UIState::ChallengeForm => {
+                    app.challenge_form_mut().move_field(true);
+                }
+
+                Key::Char('l') | Key::Right if app.ui_state() == &UIState::ChallengeForm => {
+                    app.challenge_form_mut().change(true);
+                }
+
+                Key::Char('h') | Key::Left if app.ui_state() == &UIState::ChallengeForm => {
+                    app.challenge_form_mut().change(false);
+                }
+
+                Key::Esc if app.ui_state() == &UIState::ChallengeForm => {
+                    app.set_ui_state(UIState::Menu);
+                    cursor_pos.1 = 4;
+                }
+
+                Key::Esc if app.ui_state() == &UIState::VariantPicker => {
+                    app.set_ui_state(UIState::Menu);
+                    cursor_pos.1 = 1;
+                }
+
+                Key::Esc if app.ui_state() == &UIState::LevelPicker => {
+                    app.set_ui_state(UIState::Menu);
+                    cursor_pos.1 = 2;
+                }
+
+                Key::Char('j') | Key::Down if cursor_pos.1 + 1 < menu_items(&app).len() as u16 => {
+                    cursor_pos.1 += 1;
                 }
 
                 Key::Char('k') | Key::Up if app.ui_state() == &UIState::Game => {
-                    if cursor_pos.1 < 7 {
-                        cursor_pos.1 += 1;
-                    }
+                    cursor_pos.1 = (cursor_pos.1 + 1).min(7);
                 }
 
-                Key::Char('k') | Key::Up => {
-                    if cursor_pos.1 >= 1 {
-                        cursor_pos.1 -= 1;
-                    }
+                Key::Char('k') | Key::Up if cursor_pos.1 >= 1 => {
+                    cursor_pos.1 -= 1;
                 }
 
                 Key::Char('f') if app.ui_state() == &UIState::Game => {
@@ -546,152 +1445,146 @@ pub async fn start(
                 }
 
                 Key::Char('l') | Key::Right if app.ui_state() == &UIState::Game => {
-                    if cursor_pos.0 < 7 {
-                        cursor_pos.0 += 1;
+                    cursor_pos.0 = (cursor_pos.0 + 1).min(7);
+                }
+
+                Key::Char('d') if app.ui_state() == &UIState::Game => {
+                    let own_turn = app.can_move();
+
+                    let board = app.game_mut().as_mut().unwrap().board_mut();
+                    let kinds: Vec<PieceKind> = board.pocket(board.turn()).kinds().collect();
+
+                    // cycles through the pieces in the pocket, then back to no selection
+                    let next = match selected {
+                        Some(Selection::Drop(kind)) => {
+                            match kinds.iter().position(|k| *k == kind) {
+                                Some(idx) => idx + 1,
+                                None => 0,
+                            }
+                        }
+                        _ => 0,
+                    };
+
+                    match kinds.get(next) {
+                        Some(kind) if own_turn => {
+                            selected = Some(Selection::Drop(*kind));
+                            let moves = board.legal_drops(kind);
+                            board.set_generated_moves(moves);
+                        }
+                        _ => {
+                            selected = None;
+                            board.set_generated_moves(vec![]);
+                        }
                     }
                 }
 
                 Key::Backspace => {
-                    selected_piece = None;
+                    selected = None;
                 }
 
-                Key::Enter => {
-                    match app.ui_state() {
-                        UIState::Menu => match cursor_pos.1 {
-                            0 => {
-                                if app.own_info().is_some() {
-                                    app.seek_for_game().await;
-                                } else {
+                Key::Enter => match app.ui_state() {
+                    UIState::Menu => match cursor_pos.1 {
+                        0 if app.own_info().is_some() => {
+                            app.open_seek();
+                        }
+                        1 => {
+                            app.set_ui_state(UIState::VariantPicker);
+                            cursor_pos.1 = 0;
+                        }
+                        2 => {
+                            app.set_ui_state(UIState::LevelPicker);
+                            cursor_pos.1 = 0;
+                        }
+                        3 if app.own_info().is_some() => {
+                            app.set_ui_state(UIState::Challenges);
+                        }
+                        4 if app.own_info().is_some() => {
+                            app.set_ui_state(UIState::ChallengeForm);
+                        }
+                        _ => (),
+                    },
+
+                    UIState::VariantPicker => {
+                        if let Some(board) = local_game_board(cursor_pos.1 as usize) {
+                            app.local_game(board);
+                        }
+                    }
+
+                    UIState::LevelPicker => match Level::ALL.get(cursor_pos.1 as usize) {
+                        Some(level) => app.computer_game(Opponent::BuiltIn(*level)),
+                        None => app.external_engine_game(),
+                    },
+
+                    UIState::Seek => app.seek_for_game(),
+                    UIState::Seeking => {}
+                    UIState::Challenges => app.accept_challenge().await,
+                    UIState::ChallengeForm => app.send_challenge().await,
+                    UIState::Profile(_) => {}
+                    UIState::GamePicker | UIState::Replay => {}
+
+                    UIState::Game if app.game().as_ref().unwrap().is_over() => {
+                        app.end_game();
+                    }
+
+                    UIState::Game => {
+                        let can_move = app.can_move();
+
+                        let render_side = app.board_display_side().clone();
+
+                        let cursor_idx = (cursor_pos.1 * 8 + cursor_pos.0) as usize;
+
+                        let cursor_sq = Square::from_index_unchecked(match render_side {
+                            Side::White => cursor_idx,
+                            Side::Black => 63 - cursor_idx,
+                        });
+
+                        let board = app.game_mut().as_mut().unwrap().board_mut();
+
+                        let is_legal = board
+                            .current_generated_moves()
+                            .iter()
+                            .any(|mv| mv.to() == cursor_sq);
+
+                        match selected {
+                            Some(Selection::Drop(kind)) => {
+                                if is_legal {
+                                    selected = None;
+                                    app.play_move(ChessMove::new_drop(kind, cursor_sq)).await;
                                 }
                             }
-                            1 => app.local_game(),
-                            _ => (),
-                        },
-
-                        UIState::Seek => {}
-                        UIState::Profile(_) => {}
-
-                        UIState::Game => {
-                            let is_online = app.game().as_ref().unwrap().is_online();
-
-                            let side = if is_online {
-                                app.check_own_side()
-                            } else {
-                                Side::White
-                            };
-
-                            let render_side = app.board_display_side().clone();
-
-                            let id = app.game().as_ref().unwrap().id().to_string();
-                            let token = app.config().token().to_string();
-                            let board = app.game_mut().as_mut().unwrap().board_mut();
-
-                            match selected_piece {
-                                Some(p) => {
-                                    let idx = p.1 * 8 + p.0;
-
-                                    let cursor_idx = match render_side {
-                                        Side::White => (cursor_pos.1 * 8 + cursor_pos.0) as usize,
-                                        Side::Black => {
-                                            63 - ((cursor_pos.1 * 8 + cursor_pos.0) as usize)
-                                        }
-                                    };
-
-                                    if board /*.generate_moves(idx, &piece)*/
-                                        .current_generated_moves()
-                                        .contains(&cursor_idx)
+                            Some(Selection::Piece(from)) => {
+                                if is_legal {
+                                    selected = None;
+
+                                    let mv = ChessMove::new(from, cursor_sq, None);
+
+                                    if board.is_promotion(from, cursor_sq)
+                                        && !app.config().auto_queen()
                                     {
-                                        let piece = board
-                                            .pieces_mut()
-                                            .get_mut(idx)
-                                            .unwrap()
-                                            .as_mut()
-                                            .unwrap();
-
-                                        let piece_side = piece.side().clone();
-                                        let turn_time_taken =
-                                            board.turn_time_taken().elapsed().as_millis();
-
-                                        board
-                                            .submit_move(idx, cursor_idx, id, token, is_online)
-                                            .await;
-                                        selected_piece = None;
-                                        board.set_generated_moves(vec![]);
-
-                                        drop(board);
-
-                                        let game = app.game_mut().as_mut().unwrap();
-                                        game.incr_move_count();
-
-                                        let mut new_state = game.state().clone();
-
-                                        let wtime = *game.state().wtime();
-                                        let btime = *game.state().btime();
-
-                                        if *game.move_count() >= 3 {
-                                            let (wtime, btime) = match piece_side {
-                                                Side::White => (
-                                                    wtime - turn_time_taken as u64
-                                                        + game.data().clock().increment(),
-                                                    btime,
-                                                ),
-                                                Side::Black => (
-                                                    wtime,
-                                                    btime - turn_time_taken as u64
-                                                        + game.data().clock().increment(),
-                                                ),
-                                            };
-
-                                            new_state.set_btime(btime);
-                                            new_state.set_wtime(wtime);
-
-                                            game.set_state(new_state);
-                                        }
-
-                                        if !game.is_online() {
-                                            game.board_mut().reset_turn_timer();
-                                        }
+                                        pending_promotion = Some(mv);
+                                    } else {
+                                        app.play_move(mv).await;
                                     }
                                 }
-                                None => {
-                                    if is_online {
-                                        if *board.turn() != side {
-                                            continue;
-                                        }
-                                    }
+                            }
+                            None => {
+                                if !can_move {
+                                    continue;
+                                }
 
-                                    let idx = match render_side {
-                                        Side::White => (cursor_pos.1 * 8 + cursor_pos.0) as usize,
-                                        Side::Black => {
-                                            63 - (cursor_pos.1 * 8 + cursor_pos.0) as usize
-                                        }
-                                    };
-
-                                    if let Some(ref p) = board.piece_at(idx) {
-                                        if p.side() == board.turn() {
-                                            selected_piece = match render_side {
-                                                Side::White => Some((
-                                                    cursor_pos.0 as usize,
-                                                    cursor_pos.1 as usize,
-                                                )),
-                                                Side::Black => {
-                                                    let p = 63
-                                                        - (cursor_pos.1 * 8 + cursor_pos.0)
-                                                            as usize;
-                                                    Some((p.x(), p.y()))
-                                                }
-                                            };
-
-                                            let moves = board.generate_moves(idx, p);
-
-                                            board.set_generated_moves(moves);
-                                        }
+                                if let Some(p) = board.piece_at(cursor_sq.index()) {
+                                    if p.side() == board.turn() {
+                                        selected = Some(Selection::Piece(cursor_sq));
+
+                                        let moves = board.legal_moves_from(cursor_sq);
+
+                                        board.set_generated_moves(moves);
                                     }
                                 }
                             }
                         }
                     }
-                }
+                },
                 _ => (),
             }
         }
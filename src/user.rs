@@ -20,28 +20,60 @@ pub struct Perfs {
     rapid: Perf,
     correspondence: Perf,
     classical: Perf,
+    // the variants are only there once the player has played them
+    #[serde(default)]
+    chess960: Option<Perf>,
+    #[serde(default, rename = "threeCheck")]
+    three_check: Option<Perf>,
+    #[serde(default, rename = "kingOfTheHill")]
+    king_of_the_hill: Option<Perf>,
+    #[serde(default)]
+    atomic: Option<Perf>,
+    #[serde(default)]
+    antichess: Option<Perf>,
+    #[serde(default)]
+    horde: Option<Perf>,
+    #[serde(default, rename = "racingKings")]
+    racing_kings: Option<Perf>,
+    #[serde(default)]
+    crazyhouse: Option<Perf>,
 }
 
 impl Perfs {
-    fn blitz(&self) -> &Perf {
+    pub fn blitz(&self) -> &Perf {
         &self.blitz
     }
 
-    fn bullet(&self) -> &Perf {
+    pub fn bullet(&self) -> &Perf {
         &self.bullet
     }
 
-    fn rapid(&self) -> &Perf {
+    pub fn rapid(&self) -> &Perf {
         &self.rapid
     }
 
-    fn correspondence(&self) -> &Perf {
+    pub fn correspondence(&self) -> &Perf {
         &self.correspondence
     }
 
-    fn classical(&self) -> &Perf {
+    pub fn classical(&self) -> &Perf {
         &self.classical
     }
+
+    /// The rating in the variant with the Lichess key `key`, e.g. `atomic`, if the player has one.
+    pub fn variant(&self, key: &str) -> Option<&Perf> {
+        match key {
+            "chess960" => self.chess960.as_ref(),
+            "threeCheck" => self.three_check.as_ref(),
+            "kingOfTheHill" => self.king_of_the_hill.as_ref(),
+            "atomic" => self.atomic.as_ref(),
+            "antichess" => self.antichess.as_ref(),
+            "horde" => self.horde.as_ref(),
+            "racingKings" => self.racing_kings.as_ref(),
+            "crazyhouse" => self.crazyhouse.as_ref(),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -54,23 +86,23 @@ pub struct Perf {
 }
 
 impl Perf {
-    fn games(&self) -> &u32 {
+    pub fn games(&self) -> &u32 {
         &self.games
     }
 
-    fn rating(&self) -> &u32 {
+    pub fn rating(&self) -> &u32 {
         &self.rating
     }
 
-    fn rd(&self) -> &i32 {
+    pub fn rd(&self) -> &i32 {
         &self.rd
     }
 
-    fn prog(&self) -> &i32 {
+    pub fn prog(&self) -> &i32 {
         &self.prog
     }
 
-    fn prov(&self) -> &Option<bool> {
+    pub fn prov(&self) -> &Option<bool> {
         &self.prov
     }
 }
@@ -96,4 +128,8 @@ impl User {
     pub fn online(&self) -> &bool {
         &self.online
     }
+
+    pub fn perfs(&self) -> &Perfs {
+        &self.perfs
+    }
 }
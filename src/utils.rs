@@ -2,6 +2,7 @@ use crossterm::style::Color;
 use std::error::Error;
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn debug<T: AsRef<str>>(data: &T) {
     let mut file = OpenOptions::new()
@@ -13,28 +14,34 @@ pub fn debug<T: AsRef<str>>(data: &T) {
     file.write_all(data.as_ref().as_bytes()).unwrap();
 }
 
-pub fn fmt_clock<'a>(time: u64) -> String {
+pub fn fmt_clock(time: u64) -> String {
     let sec = (time as f32 / 1000.0).floor();
     let min = (sec / 60.0).floor();
     let sec = sec - (min * 60.0);
 
-    let min_str;
+    format!("{:02}:{:02}", min as u32, sec as u32)
+}
 
-    if (min as u32) < 10 {
-        min_str = format!("0{}", min as u32);
-    } else {
-        min_str = (min as u32).to_string();
-    }
+/// Formats a point in time as a UTC date in PGN format, e.g. `2021.10.04`.
+pub fn fmt_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
 
-    let sec_str;
+    // converts days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
 
-    if (sec as u32) < 10 {
-        sec_str = format!("0{}", sec as u32);
-    } else {
-        sec_str = (sec as u32).to_string();
-    }
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
 
-    format!("{}:{}", min_str, sec_str)
+    format!("{:04}.{:02}.{:02}", year, month, day)
 }
 
 pub fn hex_to_rgb<T: AsRef<str>>(hex: &T) -> Result<(u8, u8, u8), Box<dyn Error>> {